@@ -0,0 +1,18 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use log2vcd::value_change::{ FieldOrder, InputFormat, ParseOptions, ValueChange };
+
+// parsing arbitrary lines, in every input format, may fail but never panic
+fuzz_target!(|data: &[u8]| {
+	let line = match std::str::from_utf8(data) {
+		Ok(line) => line,
+		Err(_) => return
+	};
+	for input_format in &[InputFormat::Log, InputFormat::Kv, InputFormat::JsonLines, InputFormat::Leveled, InputFormat::VcdLike] {
+		for field_order in &[FieldOrder::ValueWidth, FieldOrder::WidthValue] {
+			let options = ParseOptions { input_format: *input_format, field_order: *field_order, assume_width: Some(1), ..ParseOptions::default() };
+			let _ = ValueChange::parse_located(line, &options);
+		}
+	}
+});
@@ -0,0 +1,62 @@
+use std::fs::{ self, File };
+use std::io::{ self, BufWriter, Write };
+use std::path::{ Path, PathBuf };
+
+/// Writes a file through a temporary file next to it, which is renamed over the destination
+/// when `write` succeeds. On failure the temporary file is removed and the destination is
+/// left untouched.
+pub fn write_atomically<P, F>(path: P, write: F) -> io::Result<()>
+	where P: AsRef<Path>, F: FnOnce(&mut dyn Write) -> io::Result<()>
+{
+	let path = path.as_ref();
+	let temp_path = temp_path(path);
+	let result = File::create(&temp_path).and_then(|file| {
+		let mut output = BufWriter::new(file);
+		write(&mut output)?;
+		output.into_inner().map_err(|e| e.into_error())?.sync_all()
+	});
+	match result.and_then(|_| fs::rename(&temp_path, path)) {
+		Ok(()) => Ok(()),
+		Err(e) => {
+			let _ = fs::remove_file(&temp_path);
+			Err(e)
+		}
+	}
+}
+
+/// A hidden file in the same directory, so the rename doesn't cross file systems.
+fn temp_path(path: &Path) -> PathBuf {
+	let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+	path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use crate::temp::TempPath;
+
+	fn destination(name: &str) -> TempPath {
+		TempPath::with_contents(name, "previous")
+	}
+
+	#[test]
+	fn success() {
+		let path = destination("atomic-success.vcd");
+		write_atomically(&path, |out| out.write_all(b"converted")).unwrap();
+		assert_eq!("converted", fs::read_to_string(&path).unwrap());
+		assert!(!temp_path(&path).exists());
+	}
+
+	#[test]
+	fn failure() {
+		let path = destination("atomic-failure.vcd");
+		let result = write_atomically(&path, |out| {
+			out.write_all(b"half written")?;
+			Err(io::Error::new(io::ErrorKind::InvalidData, "parse error"))
+		});
+		assert!(result.is_err());
+		assert_eq!("previous", fs::read_to_string(&path).unwrap());
+		assert!(!temp_path(&path).exists());
+	}
+}
@@ -0,0 +1,115 @@
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{ self, BufRead, BufReader, BufWriter, Write };
+use std::path::{ Path, PathBuf };
+use std::str::FromStr;
+
+use flate2::bufread::MultiGzDecoder;
+use serde::Serialize;
+use flate2::write::GzEncoder;
+
+/// Compression of the written VCD.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Compression {
+	Zstd
+}
+
+impl FromStr for Compression {
+	type Err = String;
+	fn from_str(s: &str) -> Result<Compression, String> {
+		match s {
+			"zstd" => Ok(Compression::Zstd),
+			_ => Err(format!("invalid compression '{}', expected 'zstd'", s))
+		}
+	}
+}
+
+impl Compression {
+	/// The compression implied by the extension of an output file.
+	pub fn for_path(path: &Path) -> Option<Compression> {
+		match path.extension().and_then(|e| e.to_str()) {
+			Some("zst") => Some(Compression::Zstd),
+			_ => None
+		}
+	}
+}
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Decompresses gzip or zstd compressed input, recognized by the magic number it starts with.
+///
+/// Only the bytes already buffered are looked at, so nothing has to be read twice.
+pub fn decompress<'a, R: BufRead + 'a>(mut input: R) -> io::Result<Box<dyn BufRead + 'a>> {
+	let start = input.fill_buf()?;
+	if start.starts_with(&GZIP_MAGIC) {
+		Ok(Box::new(BufReader::new(MultiGzDecoder::new(input))))
+	}
+	else if start.starts_with(&ZSTD_MAGIC) {
+		Ok(Box::new(BufReader::new(zstd::Decoder::with_buffer(input)?)))
+	}
+	else {
+		Ok(Box::new(input))
+	}
+}
+
+/// The path a sidecar file is written to, with `.gz` appended if it's compressed.
+pub fn sidecar_path(path: &Path, compressed: bool) -> PathBuf {
+	if !compressed || path.extension().is_some_and(|e| e == "gz") {
+		return path.to_path_buf();
+	}
+	let mut path = OsString::from(path);
+	path.push(".gz");
+	PathBuf::from(path)
+}
+
+/// Writes a sidecar file of the VCD, gzip compressed with `--compress-sidecar`.
+pub fn write_sidecar<F: FnOnce(&mut dyn Write) -> io::Result<()>>(path: &Path, compressed: bool, write: F) -> io::Result<()> {
+	let mut file = BufWriter::new(File::create(sidecar_path(path, compressed))?);
+	if compressed {
+		let mut gzip = GzEncoder::new(&mut file, flate2::Compression::default());
+		write(&mut gzip)?;
+		gzip.finish()?;
+	}
+	else {
+		write(&mut file)?;
+	}
+	file.flush()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use std::io::Read;
+
+	#[test]
+	fn for_path() {
+		assert_eq!(Some(Compression::Zstd), Compression::for_path(Path::new("out.vcd.zst")));
+		assert_eq!(None, Compression::for_path(Path::new("out.vcd")));
+	}
+
+	fn read(input: &[u8]) -> String {
+		let mut output = String::new();
+		decompress(input).unwrap().read_to_string(&mut output).unwrap();
+		output
+	}
+
+	#[test]
+	fn magic_numbers() {
+		let log = "#10 a 1 1\n#20 a 0 1\n";
+		let mut gzip = GzEncoder::new(Vec::new(), flate2::Compression::default());
+		gzip.write_all(log.as_bytes()).unwrap();
+		assert_eq!(log, read(&gzip.finish().unwrap()));
+		assert_eq!(log, read(&zstd::encode_all(log.as_bytes(), 0).unwrap()));
+		assert_eq!(log, read(log.as_bytes()));
+		assert_eq!("", read(b""));
+	}
+
+	#[test]
+	fn sidecar() {
+		assert_eq!(PathBuf::from("stats.json.gz"), sidecar_path(Path::new("stats.json"), true));
+		assert_eq!(PathBuf::from("stats.json.gz"), sidecar_path(Path::new("stats.json.gz"), true));
+		assert_eq!(PathBuf::from("stats.json"), sidecar_path(Path::new("stats.json"), false));
+	}
+}
@@ -0,0 +1,53 @@
+/// A log of the conformance corpus, with the arguments it's converted with and the VCD expected.
+pub struct Case {
+	pub name: &'static str,
+	pub args: &'static [&'static str],
+	pub log: &'static str,
+	pub vcd: &'static str
+}
+
+macro_rules! case {
+	($name:expr, $args:expr) => {
+		Case {
+			name: $name,
+			args: $args,
+			log: include_str!(concat!("../conformance/", $name, ".log")),
+			vcd: include_str!(concat!("../conformance/", $name, ".vcd"))
+		}
+	}
+}
+
+/// The built-in corpus run by `--conformance`.
+pub const CASES: [Case; 5] = [
+	case!("scalar", &["-u", "ns"]),
+	case!("vector", &["-u", "ns"]),
+	case!("real", &["-u", "ns"]),
+	case!("nested", &["-u", "ns"]),
+	case!("dedup", &["-u", "ns", "--collapse-x"])
+];
+
+/// Describes the first line where the converted VCD differs from the expected one.
+pub fn first_difference(expected: &str, actual: &str) -> Option<String> {
+	let mut expected_lines = expected.lines();
+	let mut actual_lines = actual.lines();
+	for line in 1.. {
+		match (expected_lines.next(), actual_lines.next()) {
+			(None, None) => return None,
+			(e, a) if e == a => continue,
+			(e, a) => return Some(format!("line {}: expected {:?}, got {:?}", line, e.unwrap_or("<end>"), a.unwrap_or("<end>")))
+		}
+	}
+	None
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn difference() {
+		assert_eq!(None, first_difference("a\nb\n", "a\nb\n"));
+		assert_eq!(Some(String::from("line 2: expected \"b\", got \"c\"")), first_difference("a\nb\n", "a\nc\n"));
+		assert_eq!(Some(String::from("line 3: expected \"<end>\", got \"d\"")), first_difference("a\nb\n", "a\nb\nd\n"));
+	}
+}
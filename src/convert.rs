@@ -0,0 +1,192 @@
+//! Helpers for emitting a VCD from value changes that are already parsed.
+
+use std::collections::HashSet;
+use std::io;
+
+use regex::Regex;
+use vcd::{ Writer, IdCode, VarType, Header, Scope, ScopeItem, SimulationCommand };
+
+use crate::value_change::{ self, Value, ValueChange, ScalarValue };
+
+/// Returns the type and width of the variable a value is declared as.
+pub fn var_kind(value: &Value) -> (VarType, usize) {
+	match *value {
+		Value::Scalar(_) => (VarType::Wire, 1),
+		Value::BinaryVector{width, ..} => (VarType::Integer, width),
+		Value::Real(_) => (VarType::Real, 32)
+	}
+}
+
+/// Returns the path of the scope (below the top scope) a signal is declared in.
+///
+/// Elements of arrays, logged as `name[index]`, are grouped in a scope named after the array.
+pub fn scope_path(name: &str) -> Vec<&str> {
+	lazy_static! {
+		static ref ARRAY_ELEMENT: Regex = Regex::new(r#"^(.+)\[\d+\]$"#).unwrap();
+	}
+	match ARRAY_ELEMENT.captures(name) {
+		Some(caps) => vec![caps.get(1).unwrap().as_str()],
+		None => Vec::new()
+	}
+}
+
+/// Writes the header and the commands of a waveform, a VCD or another format with the same
+/// hierarchy and value changes.
+pub trait WaveWriter {
+	fn header(&mut self, header: &Header) -> io::Result<()>;
+	fn timestamp(&mut self, timestamp: u64) -> io::Result<()>;
+	fn comment(&mut self, text: &str) -> io::Result<()>;
+	fn begin(&mut self, command: SimulationCommand) -> io::Result<()>;
+	fn end(&mut self) -> io::Result<()>;
+	fn change_scalar(&mut self, id: IdCode, value: vcd::Value) -> io::Result<()>;
+	fn change_vector(&mut self, id: IdCode, value: &[vcd::Value]) -> io::Result<()>;
+	fn change_real(&mut self, id: IdCode, value: f64) -> io::Result<()>;
+
+	/// Writes what's left once all the commands are written.
+	fn finish(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+impl<'a> WaveWriter for Writer<'a> {
+	fn header(&mut self, header: &Header) -> io::Result<()> {
+		Writer::header(self, header)
+	}
+
+	fn timestamp(&mut self, timestamp: u64) -> io::Result<()> {
+		Writer::timestamp(self, timestamp)
+	}
+
+	fn comment(&mut self, text: &str) -> io::Result<()> {
+		Writer::comment(self, text)
+	}
+
+	fn begin(&mut self, command: SimulationCommand) -> io::Result<()> {
+		Writer::begin(self, command)
+	}
+
+	fn end(&mut self) -> io::Result<()> {
+		Writer::end(self)
+	}
+
+	fn change_scalar(&mut self, id: IdCode, value: vcd::Value) -> io::Result<()> {
+		Writer::change_scalar(self, id, value)
+	}
+
+	fn change_vector(&mut self, id: IdCode, value: &[vcd::Value]) -> io::Result<()> {
+		Writer::change_vector(self, id, value)
+	}
+
+	fn change_real(&mut self, id: IdCode, value: f64) -> io::Result<()> {
+		Writer::change_real(self, id, value)
+	}
+}
+
+pub fn write_value(writer: &mut dyn WaveWriter, id: IdCode, value: Value) -> io::Result<()> {
+	match value {
+		Value::Scalar(v) => {
+			writer.change_scalar(id, v.into())
+		},
+		Value::BinaryVector{value, ..} => {
+			let value: Vec<vcd::Value> = value.into_iter().map(vcd::Value::from).collect();
+			writer.change_vector(id, &value[..])
+		},
+		Value::Real(v) => {
+			// f64 is displayed as the shortest decimal that parses back to the same value, so reals
+			// round-trip exactly without a hex float form
+			writer.change_real(id, v)
+		}
+	}
+}
+
+/// A transform that inverts the 0 and 1 bits of scalars and vectors.
+pub fn invert(change: &mut ValueChange) {
+	let flip = |v: &mut ScalarValue| *v = match v {
+		ScalarValue::V0 => ScalarValue::V1,
+		ScalarValue::V1 => ScalarValue::V0,
+		_ => v.clone()
+	};
+	match change.value {
+		Value::Scalar(ref mut v) => flip(v),
+		Value::BinaryVector{ref mut value, width} => {
+			// the bits VCD would extend the vector with are inverted too
+			*value = value_change::left_extend(value, width);
+			value.iter_mut().for_each(flip);
+		},
+		Value::Real(_) => {}
+	}
+}
+
+/// A transform that reverses the bit order of vectors.
+pub fn reverse_bits(change: &mut ValueChange) {
+	if let Value::BinaryVector{ref mut value, width} = change.value {
+		*value = value_change::left_extend(value, width);
+		value.reverse();
+	}
+}
+
+fn check_scope(scope: &Scope, parent: &str) -> io::Result<()> {
+	let path = if parent.is_empty() { scope.identifier.clone() } else { format!("{}.{}", parent, scope.identifier) };
+	let mut references = HashSet::new();
+	for item in &scope.children {
+		match item {
+			ScopeItem::Var(var) if !references.insert(var.reference.as_str()) => {
+				let message = format!("duplicate variable '{}' in scope '{}'", var.reference, path);
+				return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+			},
+			ScopeItem::Var(_) => {},
+			ScopeItem::Scope(child) => check_scope(child, &path)?
+		}
+	}
+	Ok(())
+}
+
+/// Checks that no scope of the header declares two variables with the same reference, which
+/// makes the VCD invalid.
+pub fn check_unique_references(header: &Header) -> io::Result<()> {
+	for item in &header.items {
+		if let ScopeItem::Scope(scope) = item {
+			check_scope(scope, "")?;
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use std::str::FromStr;
+	use vcd::{ Var, ScopeType };
+
+	#[test]
+	fn duplicate_reference() {
+		let var = |code: IdCode| Var { var_type: VarType::Wire, size: 1, code, reference: String::from("a") };
+		let inner = Scope {
+			scope_type: ScopeType::Module,
+			identifier: String::from("mem"),
+			children: vec![ScopeItem::Var(var(IdCode::FIRST)), ScopeItem::Var(var(IdCode::FIRST.next()))]
+		};
+		let outer = Scope {
+			scope_type: ScopeType::Module,
+			identifier: String::from("outputs"),
+			children: vec![ScopeItem::Var(var(IdCode::FIRST.next().next())), ScopeItem::Scope(inner)]
+		};
+		let mut header = Header { comment: None, date: None, version: None, timescale: None, items: vec![ScopeItem::Scope(outer)] };
+		let error = check_unique_references(&header).unwrap_err();
+		assert_eq!("duplicate variable 'a' in scope 'outputs.mem'", error.to_string());
+
+		if let ScopeItem::Scope(ref mut outer) = header.items[0] {
+			outer.children.pop();
+		}
+		check_unique_references(&header).unwrap();
+	}
+
+	#[test]
+	fn transforms() {
+		let mut bus = ValueChange::from_str("#20 bus 1x 4").unwrap();
+		invert(&mut bus);
+		assert_eq!(Value::parse("110x", "4").unwrap(), bus.value);
+		reverse_bits(&mut bus);
+		assert_eq!(Value::parse("x011", "4").unwrap(), bus.value);
+	}
+}
@@ -0,0 +1,211 @@
+use std::io::{ self, Write };
+use std::str::FromStr;
+
+use serde::Serialize;
+use std::time::{ Duration, Instant };
+
+use log2vcd::LineError;
+use crate::stats::ConvertStats;
+
+/// How problems with the input are reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiagnosticsFormat {
+	/// Human readable warnings, printed as they happen
+	Text,
+	/// A single JSON array, printed once the conversion is done
+	Json
+}
+
+impl FromStr for DiagnosticsFormat {
+	type Err = String;
+	fn from_str(s: &str) -> Result<DiagnosticsFormat, String> {
+		match s {
+			"text" => Ok(DiagnosticsFormat::Text),
+			"json" => Ok(DiagnosticsFormat::Json),
+			_ => Err(format!("invalid diagnostics format '{}', expected 'text' or 'json'", s))
+		}
+	}
+}
+
+/// A problem found in the input.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+	/// 1-based line number, if the problem belongs to a line
+	pub line: Option<usize>,
+	/// 1-based column, if the problem belongs to a part of a line
+	pub column: Option<usize>,
+	pub code: &'static str,
+	pub message: String,
+	/// The text of the line, for the lines that were dropped
+	pub text: Option<String>,
+	/// The `--explain` explanation of a dropped line
+	pub explanation: Option<String>
+}
+
+impl Diagnostic {
+	pub fn new<S: Into<String>>(code: &'static str, message: S) -> Diagnostic {
+		Diagnostic { line: None, column: None, code, message: message.into(), text: None, explanation: None }
+	}
+
+	pub fn parse_error(error: &LineError) -> Diagnostic {
+		Diagnostic {
+			line: Some(error.line_no),
+			column: Some(error.column),
+			code: error.error.code(),
+			message: error.error.to_string(),
+			text: Some(error.text.clone()),
+			explanation: None
+		}
+	}
+}
+
+/// Collects the diagnostics and statistics of a conversion.
+pub struct Diagnostics {
+	format: DiagnosticsFormat,
+	/// The most warnings about lines printed, the others are only counted
+	max_warnings: Option<usize>,
+	/// The warnings about lines printed, and the ones that weren't
+	warnings: (usize, usize),
+	reported: Vec<Diagnostic>,
+	/// The time spent in each phase of the conversion with `--profile`, and when the current one started
+	profile: Option<(Vec<(&'static str, Duration)>, Instant)>,
+	pub stats: ConvertStats
+}
+
+impl Diagnostics {
+	pub fn new(format: DiagnosticsFormat) -> Diagnostics {
+		Diagnostics { format, max_warnings: None, warnings: (0, 0), reported: Vec::new(), profile: None, stats: ConvertStats::default() }
+	}
+
+	/// Times the phases of the conversion, printed when finishing.
+	pub fn with_profile(self, profile: bool) -> Diagnostics {
+		Diagnostics { profile: if profile { Some((Vec::new(), Instant::now())) } else { None }, ..self }
+	}
+
+	/// Ends a phase of the conversion, the next one starts now.
+	pub fn phase(&mut self, name: &'static str) {
+		if let Some((ref mut phases, ref mut start)) = self.profile {
+			phases.push((name, start.elapsed()));
+			*start = Instant::now();
+		}
+	}
+
+	/// Prints at most `max` warnings about lines, the rest are summed up when finishing.
+	pub fn with_max_warnings(self, max: Option<usize>) -> Diagnostics {
+		Diagnostics { max_warnings: max, ..self }
+	}
+
+	pub fn report(&mut self, diagnostic: Diagnostic) {
+		let printed = match diagnostic.line {
+			Some(_) if self.max_warnings.is_some_and(|max| self.warnings.0 >= max) => {
+				self.warnings.1 += 1;
+				false
+			},
+			Some(_) => {
+				self.warnings.0 += 1;
+				true
+			},
+			None => true
+		};
+		if self.format == DiagnosticsFormat::Text && printed {
+			match (diagnostic.line, diagnostic.column, &diagnostic.text) {
+				(Some(line), Some(column), Some(text)) => {
+					eprintln!("warning: line {}, column {}: {} [{}]: '{}'", line, column, diagnostic.message, diagnostic.code, text);
+				},
+				(Some(line), Some(column), None) => eprintln!("warning: line {}, column {}: {}", line, column, diagnostic.message),
+				(Some(line), None, _) => eprintln!("warning: line {}: {}", line, diagnostic.message),
+				_ => eprintln!("warning: {}", diagnostic.message)
+			}
+			if let Some(ref explanation) = diagnostic.explanation {
+				eprintln!("{}", explanation);
+			}
+		}
+		self.reported.push(diagnostic);
+	}
+
+	/// The diagnostics that were reported, in order.
+	pub fn into_reported(self) -> Vec<Diagnostic> {
+		self.reported
+	}
+
+	pub fn to_json(&self) -> serde_json::Value {
+		serde_json::Value::Array(self.reported.iter().map(|d| {
+			let mut diagnostic = json!({
+				"line": d.line,
+				"column": d.column,
+				"code": d.code,
+				"message": d.message,
+				"text": d.text
+			});
+			if let Some(ref explanation) = d.explanation {
+				diagnostic["explanation"] = json!(explanation);
+			}
+			diagnostic
+		}).collect())
+	}
+
+	/// Writes the diagnostics that aren't printed as they are reported, and how many lines were
+	/// skipped.
+	pub fn finish<W: Write>(&self, mut out: W) -> io::Result<()> {
+		for (name, duration) in self.profile.iter().flat_map(|(phases, _)| phases) {
+			writeln!(out, "profile: {} {:?}", name, duration)?;
+		}
+		if self.format == DiagnosticsFormat::Json {
+			return writeln!(out, "{}", self.to_json());
+		}
+		if self.warnings.1 > 0 {
+			writeln!(out, "warning: ... and {} more", self.warnings.1)?;
+		}
+		let skipped: usize = self.stats.dropped.values().sum();
+		if skipped > 0 {
+			writeln!(out, "warning: skipped {} of {} lines", skipped, self.stats.lines)?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn max_warnings() {
+		let mut diagnostics = Diagnostics::new(DiagnosticsFormat::Text).with_max_warnings(Some(2));
+		for line in 1..=5 {
+			diagnostics.report(Diagnostic { line: Some(line), ..Diagnostic::new("invalid-line", "invalid line") });
+		}
+		diagnostics.report(Diagnostic::new("no-value-changes", "no value changes found"));
+		assert_eq!((2, 3), diagnostics.warnings);
+		let mut out = Vec::new();
+		diagnostics.finish(&mut out).unwrap();
+		assert_eq!("warning: ... and 3 more\n", String::from_utf8(out).unwrap());
+
+		// the JSON diagnostics are all there
+		let mut diagnostics = Diagnostics::new(DiagnosticsFormat::Json).with_max_warnings(Some(2));
+		for line in 1..=5 {
+			diagnostics.report(Diagnostic { line: Some(line), ..Diagnostic::new("invalid-line", "invalid line") });
+		}
+		assert_eq!(5, diagnostics.to_json().as_array().unwrap().len());
+
+		let mut diagnostics = Diagnostics::new(DiagnosticsFormat::Json);
+		diagnostics.report(Diagnostic { explanation: Some(String::from("line 1: junk")), ..Diagnostic::new("invalid-format", "invalid format") });
+		assert_eq!(json!("line 1: junk"), diagnostics.to_json()[0]["explanation"]);
+
+		let mut out = Vec::new();
+		Diagnostics::new(DiagnosticsFormat::Text).finish(&mut out).unwrap();
+		assert!(out.is_empty());
+	}
+
+	#[test]
+	fn skipped_lines() {
+		let mut diagnostics = Diagnostics::new(DiagnosticsFormat::Text);
+		diagnostics.stats.lines = 2_000_000;
+		for _ in 0..37 {
+			diagnostics.stats.drop_line("invalid-format");
+		}
+		let mut out = Vec::new();
+		diagnostics.finish(&mut out).unwrap();
+		assert_eq!("warning: skipped 37 of 2000000 lines\n", String::from_utf8(out).unwrap());
+	}
+}
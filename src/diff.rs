@@ -0,0 +1,74 @@
+use std::collections::{ HashMap, HashSet };
+
+use crate::value_change::{ ScalarValue, Value, ValueChange };
+
+/// The value changes of a `<signal>_diff` companion of every signal of either log, high while the
+/// signal has different values in the two logs.
+///
+/// Both logs have to be sorted by timestamp. A signal missing from one of the logs differs.
+pub fn diff_signals(changes: &[ValueChange], other: &[ValueChange]) -> Vec<ValueChange> {
+	let mut values: [HashMap<&str, &Value>; 2] = [HashMap::new(), HashMap::new()];
+	let mut differing: HashMap<&str, bool> = HashMap::new();
+	let mut diffs = Vec::new();
+	let (mut a, mut b) = (changes.iter().peekable(), other.iter().peekable());
+	loop {
+		let timestamp = match (a.peek(), b.peek()) {
+			(Some(x), Some(y)) => x.timestamp.min(y.timestamp),
+			(Some(x), None) => x.timestamp,
+			(None, Some(y)) => y.timestamp,
+			(None, None) => break
+		};
+		// the signals changing at this timestamp, in the order of their first change
+		let mut changed = Vec::new();
+		let mut seen = HashSet::new();
+		for (log, changes) in [&mut a, &mut b].iter_mut().enumerate() {
+			while let Some(change) = changes.next_if(|change| change.timestamp == timestamp) {
+				values[log].insert(&change.signal_name, &change.value);
+				if seen.insert(change.signal_name.as_str()) {
+					changed.push(change.signal_name.as_str());
+				}
+			}
+		}
+		for name in changed {
+			let differs = match (values[0].get(name), values[1].get(name)) {
+				(Some(value), Some(other)) => !value.same(other),
+				(value, other) => value.is_some() != other.is_some()
+			};
+			if differing.insert(name, differs) != Some(differs) {
+				diffs.push(ValueChange {
+					timestamp,
+					signal_name: format!("{}_diff", name),
+					value: Value::Scalar(if differs { ScalarValue::V1 } else { ScalarValue::V0 }),
+					line: None,
+					scope: None,
+					strength: None
+				});
+			}
+		}
+	}
+	diffs
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn change(timestamp: u64, name: &str, value: ScalarValue) -> ValueChange {
+		ValueChange { timestamp, signal_name: String::from(name), value: Value::Scalar(value), line: None, scope: None, strength: None }
+	}
+
+	#[test]
+	fn pulses() {
+		let changes = vec![change(0, "a", ScalarValue::V0), change(10, "a", ScalarValue::V1), change(20, "a", ScalarValue::V0)];
+		let other = vec![change(0, "a", ScalarValue::V0), change(10, "a", ScalarValue::V0), change(15, "b", ScalarValue::V1)];
+		let diffs: Vec<String> = diff_signals(&changes, &other).iter().map(|c| c.to_string()).collect();
+		assert_eq!(vec!["#0 a_diff 0 1", "#10 a_diff 1 1", "#15 b_diff 1 1", "#20 a_diff 0 1"], diffs);
+	}
+
+	#[test]
+	fn extended_vectors() {
+		let vector = |bits: &str| ValueChange { value: Value::parse(bits, "4").unwrap(), ..change(0, "bus", ScalarValue::V0) };
+		let diffs: Vec<String> = diff_signals(&[vector("10")], &[vector("0010")]).iter().map(|c| c.to_string()).collect();
+		assert_eq!(vec!["#0 bus_diff 0 1"], diffs);
+	}
+}
@@ -0,0 +1,46 @@
+use log2vcd::LineError;
+use log2vcd::value_change::ParseValueChangeError;
+
+/// Explains why a line was dropped: the line, the error with the part of the line it's about,
+/// and a hint on how to fix it.
+pub fn explain(error: &LineError) -> String {
+	let line = &error.text;
+	// the column counts characters
+	let token = line.char_indices().nth(error.column.saturating_sub(1))
+		.and_then(|(i, _)| line[i..].split_whitespace().next())
+		.filter(|_| error.error != ParseValueChangeError::InvalidFormat);
+	let error_line = match token {
+		Some(token) => format!("{}; got '{}'", error.error, token),
+		None => error.error.to_string()
+	};
+	format!("line {}: {}\n  error: {}\n  hint: {}", error.line_no, line, error_line, hint(&error.error))
+}
+
+fn hint(error: &ParseValueChangeError) -> &'static str {
+	match error {
+		ParseValueChangeError::InvalidFormat => "lines look like '#10 signal 1 1': a timestamp, a signal name, a value and its width",
+		ParseValueChangeError::ParseTimestampErr => "timestamps are whole numbers of ticks, --input-time-unit allows fractional ones",
+		ParseValueChangeError::InvalidValueType | ParseValueChangeError::UnknownMarker => "the value type is a width such as '8', a signed width such as '8s', or 'f' for reals",
+		ParseValueChangeError::InvalidValue => "vectors are written in binary such as '1010', reals need the 'f' value type",
+		ParseValueChangeError::MultiBitScalar => "a signal of width 1 takes a single bit, give the width of vectors such as '1010 4'",
+		ParseValueChangeError::ValueTooLargeForVecWidth => "the width has to be at least the number of bits of the value",
+		ParseValueChangeError::InvalidSignalName => "start the name with a letter, or pass --allow-numeric-names; '/' and ':' are only allowed in a --scope-separator, and a nested name can't start or end with the separator or repeat it, pass --flat to keep it whole",
+		ParseValueChangeError::UnknownDecimalDigit => "write values with unknown bits in hex or binary such as 'h1x or 0001xxxx"
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use log2vcd::parse_line;
+
+	#[test]
+	fn float_marker() {
+		let line = "#10 temp 21.5 float";
+		let error = parse_line(line, 3).unwrap_err();
+		assert_eq!(
+			"line 3: #10 temp 21.5 float\n  error: value type is an unknown marker; got 'float'\n  hint: the value type is a width such as '8', a signed width such as '8s', or 'f' for reals",
+			explain(&error)
+		);
+	}
+}
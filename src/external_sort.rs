@@ -0,0 +1,146 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{ self, File };
+use std::io::{ self, BufRead, BufReader, BufWriter, Lines, Write };
+use std::path::PathBuf;
+
+use log2vcd::value_change::{ Value, ValueChange };
+
+/// Sorts value changes by timestamp with bounded memory: the changes are sorted in chunks that are
+/// spilled to temporary files, which are then merged.
+///
+/// Like `sort_by_key`, changes with the same timestamp stay in the order they were pushed.
+pub struct ExternalSort {
+	chunk_size: usize,
+	chunk: Vec<ValueChange>,
+	files: Vec<PathBuf>
+}
+
+/// Writes a change as a tab separated `timestamp name value type scope` line, the scope is empty
+/// for the default one.
+fn write_change<W: Write>(out: &mut W, change: &ValueChange) -> io::Result<()> {
+	let value_type = match change.value {
+		Value::Scalar(_) => String::from("1"),
+		Value::BinaryVector{width, ..} => width.to_string(),
+		Value::Real(_) => String::from("f")
+	};
+	writeln!(out, "{}\t{}\t{}\t{}\t{}", change.timestamp, change.signal_name, change.value, value_type, change.scope.as_deref().unwrap_or(""))
+}
+
+fn read_change(line: &str) -> io::Result<ValueChange> {
+	let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("corrupt sort chunk line '{}'", line));
+	let fields: Vec<&str> = line.split('\t').collect();
+	if fields.len() != 5 {
+		return Err(invalid());
+	}
+	Ok(ValueChange {
+		timestamp: fields[0].parse().map_err(|_| invalid())?,
+		signal_name: String::from(fields[1]),
+		value: Value::parse(fields[2], fields[3]).map_err(|_| invalid())?,
+		line: None,
+		scope: Some(String::from(fields[4])).filter(|scope| !scope.is_empty()),
+		strength: None
+	})
+}
+
+impl ExternalSort {
+	pub fn new(chunk_size: usize) -> ExternalSort {
+		ExternalSort { chunk_size: chunk_size.max(1), chunk: Vec::new(), files: Vec::new() }
+	}
+
+	pub fn push(&mut self, change: ValueChange) -> io::Result<()> {
+		self.chunk.push(change);
+		if self.chunk.len() >= self.chunk_size {
+			self.spill()?;
+		}
+		Ok(())
+	}
+
+	fn spill(&mut self) -> io::Result<()> {
+		if self.chunk.is_empty() {
+			return Ok(());
+		}
+		self.chunk.sort_by_key(|change| change.timestamp);
+		let path = std::env::temp_dir().join(format!("log2vcd-{}-{:p}-{}.chunk", std::process::id(), self, self.files.len()));
+		let mut out = BufWriter::new(File::create(&path)?);
+		self.files.push(path);
+		for change in self.chunk.drain(..) {
+			write_change(&mut out, &change)?;
+		}
+		out.flush()
+	}
+
+	/// Returns the pushed changes in order, it can be called more than once.
+	pub fn merged(&mut self) -> io::Result<Merged> {
+		self.spill()?;
+		let mut merged = Merged { chunks: Vec::new(), heads: Vec::new(), heap: BinaryHeap::new() };
+		for path in &self.files {
+			merged.chunks.push(BufReader::new(File::open(path)?).lines());
+			merged.heads.push(None);
+			merged.advance(merged.chunks.len() - 1)?;
+		}
+		Ok(merged)
+	}
+}
+
+impl Drop for ExternalSort {
+	fn drop(&mut self) {
+		for path in &self.files {
+			let _ = fs::remove_file(path);
+		}
+	}
+}
+
+/// The k-way merge of the sorted chunks.
+pub struct Merged {
+	chunks: Vec<Lines<BufReader<File>>>,
+	heads: Vec<Option<ValueChange>>,
+	/// (timestamp, chunk) of the first change of every chunk that isn't exhausted, the chunk
+	/// index breaks ties so earlier changes come first
+	heap: BinaryHeap<Reverse<(u64, usize)>>
+}
+
+impl Merged {
+	fn advance(&mut self, chunk: usize) -> io::Result<()> {
+		self.heads[chunk] = match self.chunks[chunk].next() {
+			Some(line) => Some(read_change(&line?)?),
+			None => None
+		};
+		if let Some(ref change) = self.heads[chunk] {
+			self.heap.push(Reverse((change.timestamp, chunk)));
+		}
+		Ok(())
+	}
+}
+
+impl Iterator for Merged {
+	type Item = io::Result<ValueChange>;
+
+	fn next(&mut self) -> Option<io::Result<ValueChange>> {
+		let Reverse((_, chunk)) = self.heap.pop()?;
+		let change = self.heads[chunk].take().unwrap();
+		Some(self.advance(chunk).map(|_| change))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use std::str::FromStr;
+
+	#[test]
+	fn stable_merge() {
+		let lines = ["#30 a 1 1", "#10 b 1010 4", "#20 r 1.5 f", "#10 a 0 1", "#20 a x 1", "#10 c z 1", "#5 a 1 1"];
+		let changes: Vec<ValueChange> = lines.iter().map(|l| ValueChange::from_str(l).unwrap()).collect();
+		let mut sort = ExternalSort::new(2);
+		for change in changes.clone() {
+			sort.push(change).unwrap();
+		}
+		let mut expected = changes;
+		expected.sort_by_key(|change| change.timestamp);
+		for _ in 0..2 {
+			let merged: Vec<ValueChange> = sort.merged().unwrap().map(|c| c.unwrap()).collect();
+			assert_eq!(expected, merged);
+		}
+	}
+}
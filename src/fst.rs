@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::fs::{ self, File };
+use std::io::{ self, BufWriter, Write };
+use std::path::PathBuf;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+
+use fst_writer::{ FstBodyWriter, FstFileType, FstHeaderWriter, FstInfo, FstScopeType, FstSignalId, FstSignalType, FstVarDirection, FstVarType, FstWriteError };
+use vcd::{ Header, IdCode, ScopeItem, ScopeType, SimulationCommand, TimescaleUnit, VarType };
+
+use log2vcd::convert::WaveWriter;
+
+fn error(e: FstWriteError) -> io::Error {
+	match e {
+		FstWriteError::Io(e) => e,
+		e => io::Error::new(io::ErrorKind::InvalidData, format!("failed to write the FST: {}", e))
+	}
+}
+
+/// The exponent of the power of ten seconds of a unit.
+fn exponent(unit: TimescaleUnit) -> i8 {
+	match unit {
+		TimescaleUnit::S => 0,
+		TimescaleUnit::MS => -3,
+		TimescaleUnit::US => -6,
+		TimescaleUnit::NS => -9,
+		TimescaleUnit::PS => -12,
+		TimescaleUnit::FS => -15
+	}
+}
+
+fn scope_type(scope_type: ScopeType) -> FstScopeType {
+	match scope_type {
+		ScopeType::Module => FstScopeType::Module,
+		ScopeType::Task => FstScopeType::Task,
+		ScopeType::Function => FstScopeType::Function,
+		ScopeType::Begin => FstScopeType::Begin,
+		ScopeType::Fork => FstScopeType::Fork
+	}
+}
+
+fn var_type(var_type: VarType) -> FstVarType {
+	match var_type {
+		VarType::Event => FstVarType::Event,
+		VarType::Integer => FstVarType::Integer,
+		VarType::Parameter => FstVarType::Parameter,
+		VarType::Real => FstVarType::Real,
+		VarType::Reg => FstVarType::Reg,
+		VarType::Supply0 => FstVarType::Supply0,
+		VarType::Supply1 => FstVarType::Supply1,
+		VarType::Time => FstVarType::Time,
+		VarType::Tri => FstVarType::Tri,
+		VarType::TriAnd => FstVarType::TriAnd,
+		VarType::TriOr => FstVarType::TriOr,
+		VarType::TriReg => FstVarType::TriReg,
+		VarType::Tri0 => FstVarType::Tri0,
+		VarType::Tri1 => FstVarType::Tri1,
+		VarType::WAnd => FstVarType::Wand,
+		VarType::Wire => FstVarType::Wire,
+		VarType::WOr => FstVarType::Wor
+	}
+}
+
+/// The FST signal of each id of the VCD, and whether it's a real.
+type Signals = HashMap<IdCode, (FstSignalId, bool)>;
+
+/// Declares the scopes and variables of the VCD, the variables sharing an id are aliases.
+fn declare<W: Write + io::Seek>(writer: &mut FstHeaderWriter<W>, items: &[ScopeItem], signals: &mut Signals) -> io::Result<()> {
+	for item in items {
+		match item {
+			ScopeItem::Scope(scope) => {
+				writer.scope(&scope.identifier, "", scope_type(scope.scope_type)).map_err(error)?;
+				declare(writer, &scope.children, signals)?;
+				writer.up_scope().map_err(error)?;
+			},
+			ScopeItem::Var(var) => {
+				let real = var.var_type == VarType::Real;
+				let signal_type = if real { FstSignalType::real() } else { FstSignalType::bit_vec(var.size) };
+				let alias = signals.get(&var.code).map(|(id, _)| *id);
+				let id = writer.var(&var.reference, signal_type, var_type(var.var_type), FstVarDirection::Implicit, alias).map_err(error)?;
+				signals.insert(var.code, (id, real));
+			}
+		}
+	}
+	Ok(())
+}
+
+/// A file of the temporary directory to write the FST to, the writer only writes to files.
+fn temporary_path() -> PathBuf {
+	static COUNT: AtomicUsize = AtomicUsize::new(0);
+	let count = COUNT.fetch_add(1, Ordering::Relaxed);
+	std::env::temp_dir().join(format!("log2vcd-{}-{}.fst", std::process::id(), count))
+}
+
+/// Writes a FST with the hierarchy of a VCD header and the value changes written after it.
+///
+/// Timestamps are multiplied by the step of the timescale, FST timescales only have a unit. Reals
+/// start at 0, there's no unknown real in a FST. Comments aren't kept.
+pub struct FstWriter<'w> {
+	output: &'w mut dyn Write,
+	/// The file the FST is written to before it's copied to the output
+	path: PathBuf,
+	body: Option<FstBodyWriter<BufWriter<File>>>,
+	signals: Signals,
+	step: u64
+}
+
+impl<'w> FstWriter<'w> {
+	pub fn new(output: &'w mut dyn Write) -> FstWriter<'w> {
+		FstWriter { output, path: temporary_path(), body: None, signals: Signals::new(), step: 1 }
+	}
+
+	fn body(&mut self) -> io::Result<&mut FstBodyWriter<BufWriter<File>>> {
+		self.body.as_mut().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "the FST header wasn't written"))
+	}
+
+	fn signal(&self, id: IdCode) -> io::Result<FstSignalId> {
+		match self.signals.get(&id) {
+			Some((signal, _)) => Ok(*signal),
+			None => Err(io::Error::new(io::ErrorKind::InvalidData, format!("value change of the undeclared id '{}'", id)))
+		}
+	}
+
+	fn change(&mut self, id: IdCode, value: &[u8]) -> io::Result<()> {
+		let signal = self.signal(id)?;
+		self.body()?.signal_change(signal, value).map_err(error)
+	}
+}
+
+impl<'w> WaveWriter for FstWriter<'w> {
+	fn header(&mut self, header: &Header) -> io::Result<()> {
+		let (step, unit) = header.timescale.unwrap_or((1, TimescaleUnit::NS));
+		self.step = u64::from(step);
+		let info = FstInfo {
+			start_time: 0,
+			timescale_exponent: exponent(unit),
+			version: format!("log2vcd {}", env!("CARGO_PKG_VERSION")),
+			date: header.date.clone().unwrap_or_default(),
+			file_type: FstFileType::Verilog
+		};
+		let mut writer = fst_writer::open_fst(&self.path, &info).map_err(error)?;
+		declare(&mut writer, &header.items, &mut self.signals)?;
+		let mut body = writer.finish().map_err(error)?;
+		for (id, _) in self.signals.values().filter(|(_, real)| *real) {
+			body.signal_change(*id, &0f64.to_le_bytes()).map_err(error)?;
+		}
+		self.body = Some(body);
+		Ok(())
+	}
+
+	fn timestamp(&mut self, timestamp: u64) -> io::Result<()> {
+		let step = self.step;
+		let time = timestamp.checked_mul(step).ok_or_else(|| {
+			io::Error::new(io::ErrorKind::InvalidData, format!("timestamp {} times the step {} of the timescale doesn't fit a FST time", timestamp, step))
+		})?;
+		self.body()?.time_change(time).map_err(error)
+	}
+
+	fn comment(&mut self, _: &str) -> io::Result<()> {
+		Ok(())
+	}
+
+	fn begin(&mut self, _: SimulationCommand) -> io::Result<()> {
+		Ok(())
+	}
+
+	fn end(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+
+	fn change_scalar(&mut self, id: IdCode, value: vcd::Value) -> io::Result<()> {
+		self.change(id, value.to_string().as_bytes())
+	}
+
+	fn change_vector(&mut self, id: IdCode, value: &[vcd::Value]) -> io::Result<()> {
+		let bits: String = value.iter().map(|v| v.to_string()).collect();
+		self.change(id, bits.as_bytes())
+	}
+
+	fn change_real(&mut self, id: IdCode, value: f64) -> io::Result<()> {
+		self.change(id, &value.to_le_bytes())
+	}
+
+	fn finish(&mut self) -> io::Result<()> {
+		if let Some(body) = self.body.take() {
+			body.finish().map_err(error)?;
+			io::copy(&mut File::open(&self.path)?, self.output)?;
+		}
+		Ok(())
+	}
+}
+
+impl<'w> Drop for FstWriter<'w> {
+	fn drop(&mut self) {
+		// the writer may have created the file before failing
+		let _ = fs::remove_file(&self.path);
+	}
+}
+
+/// The `time name value` of every value change of a FST, sorted.
+#[cfg(test)]
+pub fn read_fst(fst: Vec<u8>) -> Vec<String> {
+	use fst_reader::{ FstFilter, FstHierarchyEntry, FstReader, FstSignalValue };
+
+	let mut reader = FstReader::open_and_read_time_table(io::Cursor::new(fst)).unwrap();
+	let mut names = HashMap::new();
+	reader.read_hierarchy(|entry| if let FstHierarchyEntry::Var { name, handle, .. } = entry {
+		names.insert(handle.get_index(), name);
+	}).unwrap();
+	let mut changes = Vec::new();
+	reader.read_signals(&FstFilter::all(), |time, handle, value| {
+		let value = match value {
+			FstSignalValue::String(bits) => String::from_utf8_lossy(bits).into_owned(),
+			FstSignalValue::Real(v) => v.to_string()
+		};
+		changes.push(format!("{} {} {}", time, names[&handle.get_index()], value));
+	}).unwrap();
+	changes.sort();
+	changes
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use std::io::Cursor;
+
+	use fst_reader::FstReader;
+	use vcd::Command;
+
+	/// Writes the FST of a VCD.
+	fn write_fst(vcd: &str, fst: &mut Vec<u8>) -> io::Result<()> {
+		let mut parser = vcd::Parser::new(vcd.as_bytes());
+		let header = parser.parse_header()?;
+		let mut writer = FstWriter::new(fst);
+		writer.header(&header)?;
+		for command in parser {
+			match command? {
+				Command::Timestamp(t) => writer.timestamp(t)?,
+				Command::ChangeScalar(id, v) => writer.change_scalar(id, v)?,
+				Command::ChangeVector(id, v) => writer.change_vector(id, &v)?,
+				Command::ChangeReal(id, v) => writer.change_real(id, v)?,
+				_ => {}
+			}
+		}
+		writer.finish()
+	}
+
+	#[test]
+	fn hierarchy_and_changes() {
+		let vcd = "$timescale 10 us $end\n$scope module outputs $end\n$var wire 1 ! clk $end\n\
+			$scope task cpu $end\n$var integer 4 \" pc $end\n$var real 64 # load $end\n$upscope $end\n$upscope $end\n\
+			$enddefinitions $end\n#0\n0!\nb0 \"\n#1\n1!\nb101 \"\n#2\nr1.5 #\n0!\n";
+		let mut fst = Vec::new();
+		write_fst(vcd, &mut fst).unwrap();
+
+		let reader = FstReader::open_and_read_time_table(Cursor::new(fst.clone())).unwrap();
+		assert_eq!(-6, reader.get_header().timescale_exponent);
+		assert_eq!(Some(&[0, 10, 20][..]), reader.get_time_table());
+
+		assert_eq!(vec!["0 clk 0", "0 load 0", "0 pc 0000", "10 clk 1", "10 pc 0101", "20 clk 0", "20 load 1.5"], read_fst(fst));
+	}
+
+	#[test]
+	fn invalid_commands() {
+		let header = "$timescale 10 us $end\n$scope module top $end\n$var wire 1 ! clk $end\n$upscope $end\n$enddefinitions $end\n";
+		let error = write_fst(&format!("{}#{}\n", header, u64::MAX / 2), &mut Vec::new()).unwrap_err();
+		assert_eq!(io::ErrorKind::InvalidData, error.kind());
+		assert!(error.to_string().contains("times the step 10"));
+		let error = write_fst(&format!("{}#0\n1\"\n", header), &mut Vec::new()).unwrap_err();
+		assert_eq!("value change of the undeclared id '\"'", error.to_string());
+	}
+}
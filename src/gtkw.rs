@@ -0,0 +1,61 @@
+use std::io::{ self, Write };
+use std::path::Path;
+
+use vcd::{ Header, Scope, ScopeItem, Var, VarType };
+
+/// The trace colors of GTKWave, in the order of their `[color]` index.
+pub const COLORS: [&str; 8] = ["normal", "red", "orange", "yellow", "green", "blue", "indigo", "violet"];
+
+/// Display hints of the signal of a variable: its radix ('hex', 'dec' or 'bin') and color, if any.
+pub type Hints<'a> = &'a dyn Fn(&Var) -> (Option<String>, Option<&'static str>);
+
+/// GTKWave trace flags for a display radix.
+fn flags(var: &Var, radix: Option<&str>) -> &'static str {
+	match (radix, var.var_type) {
+		(_, VarType::Real) => "@420",
+		(Some("bin"), _) => "@28",
+		(Some("dec"), _) => "@24",
+		(Some("hex"), _) => "@22",
+		_ if var.size == 1 => "@28",
+		_ => "@22"
+	}
+}
+
+fn write_scope<W: Write>(out: &mut W, scope: &Scope, path: &str, hints: Hints) -> io::Result<()> {
+	let path = if path.is_empty() { scope.identifier.clone() } else { format!("{}.{}", path, scope.identifier) };
+	writeln!(out, "[treeopen] {}.", path)?;
+	for item in &scope.children {
+		match item {
+			ScopeItem::Scope(child) => write_scope(out, child, &path, hints)?,
+			ScopeItem::Var(var) => {
+				let (radix, color) = hints(var);
+				if let Some(index) = color.and_then(|color| COLORS.iter().position(|&c| c == color)) {
+					writeln!(out, "[color] {}", index)?;
+				}
+				writeln!(out, "{}", flags(var, radix.as_deref()))?;
+				// vectors are referenced with their bit range
+				if var.size > 1 && var.var_type != VarType::Real {
+					writeln!(out, "{}.{}[{}:0]", path, var.reference, var.size - 1)?;
+				}
+				else {
+					writeln!(out, "{}.{}", path, var.reference)?;
+				}
+			}
+		}
+	}
+	Ok(())
+}
+
+/// Writes a minimal GTKWave save file that loads every variable of the header, with its full
+/// hierarchical path and the display hints of a signal.
+pub fn write<W: Write>(mut out: W, dumpfile: Option<&Path>, header: &Header, hints: Hints) -> io::Result<()> {
+	if let Some(path) = dumpfile {
+		writeln!(out, "[dumpfile] \"{}\"", path.display())?;
+	}
+	for item in &header.items {
+		if let ScopeItem::Scope(scope) = item {
+			write_scope(&mut out, scope, "", hints)?;
+		}
+	}
+	Ok(())
+}
@@ -0,0 +1,133 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{ self, Read };
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::sync::mpsc::{ self, Receiver, RecvTimeoutError };
+use std::thread;
+use std::time::Duration;
+
+/// The error of reading an `Interruptible` input after it was interrupted.
+#[derive(Debug)]
+pub struct Interrupted;
+
+impl fmt::Display for Interrupted {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "interrupted")
+	}
+}
+
+impl Error for Interrupted {}
+
+/// Whether an error is the `Interrupted` error of an `Interruptible` input.
+pub fn is_interrupted(error: &io::Error) -> bool {
+	error.get_ref().is_some_and(|e| e.is::<Interrupted>())
+}
+
+/// How often a pending read checks whether it was interrupted.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Wraps the input, failing reads with `Interrupted` once `flag` is set and what was read is used up.
+///
+/// The input is read on its own thread, so that an interrupt also ends a read that is still waiting
+/// for input, such as one of a terminal. This isn't `io::ErrorKind::Interrupted`, which readers retry.
+pub struct Interruptible {
+	chunks: Receiver<io::Result<Vec<u8>>>,
+	chunk: Vec<u8>,
+	pos: usize,
+	flag: Arc<AtomicBool>
+}
+
+impl Interruptible {
+	pub fn new<R: Read + Send + 'static>(mut inner: R, flag: Arc<AtomicBool>) -> Interruptible {
+		// a chunk is only read once the previous one was taken
+		let (sender, chunks) = mpsc::sync_channel(0);
+		thread::spawn(move || loop {
+			let mut chunk = vec![0; 8 * 1024];
+			let chunk = inner.read(&mut chunk).map(|n| { chunk.truncate(n); chunk });
+			let end = match chunk { Ok(ref chunk) => chunk.is_empty(), Err(_) => true };
+			if sender.send(chunk).is_err() || end {
+				break;
+			}
+		});
+		Interruptible { chunks, chunk: Vec::new(), pos: 0, flag }
+	}
+}
+
+impl Read for Interruptible {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		while self.pos == self.chunk.len() {
+			if self.flag.load(Ordering::SeqCst) {
+				return Err(io::Error::new(io::ErrorKind::Other, Interrupted));
+			}
+			match self.chunks.recv_timeout(POLL_INTERVAL) {
+				Ok(chunk) => {
+					self.chunk = chunk?;
+					self.pos = 0;
+					if self.chunk.is_empty() {
+						return Ok(0);
+					}
+				},
+				Err(RecvTimeoutError::Timeout) => {},
+				// the input already ended
+				Err(RecvTimeoutError::Disconnected) => return Ok(0)
+			}
+		}
+		// what was already read is still converted
+		let n = buf.len().min(self.chunk.len() - self.pos);
+		buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+		self.pos += n;
+		Ok(n)
+	}
+}
+
+/// Sets the returned flag on SIGINT (Ctrl-C), instead of exiting.
+pub fn on_sigint() -> Arc<AtomicBool> {
+	let flag = Arc::new(AtomicBool::new(false));
+	let handler_flag = flag.clone();
+	// without the handler Ctrl-C keeps exiting right away
+	let _ = ctrlc::set_handler(move || handler_flag.store(true, Ordering::SeqCst));
+	flag
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn interrupt() {
+		let flag = Arc::new(AtomicBool::new(false));
+		let mut input = Interruptible::new("abc".as_bytes(), flag.clone());
+		let mut buf = [0; 3];
+		assert_eq!(3, input.read(&mut buf).unwrap());
+		flag.store(true, Ordering::SeqCst);
+		assert!(is_interrupted(&input.read(&mut buf).unwrap_err()));
+
+		let mut input = Interruptible::new("abc".as_bytes(), Arc::new(AtomicBool::new(false)));
+		let mut read = String::new();
+		input.read_to_string(&mut read).unwrap();
+		assert_eq!("abc", read);
+	}
+
+	#[test]
+	fn pending_read() {
+		/// Never returns from a read, like a terminal nothing is typed in.
+		struct Pending;
+		impl Read for Pending {
+			fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+				loop {
+					thread::park();
+				}
+			}
+		}
+
+		let flag = Arc::new(AtomicBool::new(false));
+		let mut input = Interruptible::new(Pending, flag.clone());
+		let interrupt = thread::spawn(move || {
+			thread::sleep(Duration::from_millis(100));
+			flag.store(true, Ordering::SeqCst);
+		});
+		assert!(is_interrupted(&input.read(&mut [0; 2]).unwrap_err()));
+		interrupt.join().unwrap();
+	}
+}
@@ -0,0 +1,102 @@
+//! Parsing of the log format read by log2vcd, and writing VCDs from parsed value changes.
+
+#[macro_use]
+extern crate lazy_static;
+
+extern crate regex;
+extern crate serde;
+extern crate serde_json;
+extern crate vcd;
+
+use std::fmt;
+
+pub mod value_change;
+pub mod convert;
+
+use self::value_change::{ ValueChange, ParseValueChangeError, ParseError, ParseOptions, InputFormat };
+
+/// A line of the log that isn't a valid value change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineError {
+	/// 1-based line number
+	pub line_no: usize,
+	/// The line as it was read
+	pub text: String,
+	pub error: ParseValueChangeError,
+	/// 1-based column of the field that caused the error, in characters of the line
+	pub column: usize
+}
+
+impl LineError {
+	pub fn new(line_no: usize, text: &str, error: ParseError) -> LineError {
+		LineError { line_no, text: String::from(text), error: error.error, column: error.column }
+	}
+}
+
+impl fmt::Display for LineError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "line {}: {}: '{}'", self.line_no, self.error, self.text)
+	}
+}
+
+impl std::error::Error for LineError {}
+
+/// Parses a single line of the log, keeping the line number and text of the line if it's invalid.
+pub fn parse_line(line: &str, line_no: usize) -> Result<ValueChange, LineError> {
+	ValueChange::parse_located(line, &ParseOptions::default()).map_err(|error| LineError::new(line_no, line, error))
+}
+
+/// Parses a single line of the log in the input format of `options`. A JSON line can have the
+/// changes of several signals.
+pub fn parse_line_with(line: &str, line_no: usize, options: &ParseOptions) -> Result<Vec<ValueChange>, LineError> {
+	let parsed = match options.input_format {
+		InputFormat::JsonLines => ValueChange::parse_json(line, options),
+		_ => ValueChange::parse_located(line, options).map(|change| vec![change])
+	};
+	parsed.map_err(|error| LineError::new(line_no, line, error))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use super::value_change::Value;
+
+	fn error(line: &str) -> LineError {
+		parse_line(line, 7).unwrap_err()
+	}
+
+	#[test]
+	fn valid_line() {
+		let change = parse_line("#10 a 1 1", 1).unwrap();
+		assert_eq!(10, change.timestamp);
+		assert_eq!("a", change.signal_name);
+		assert_eq!(Value::parse("1", "1").unwrap(), change.value);
+	}
+
+	#[test]
+	fn line_errors() {
+		let cases = [
+			("hello world", ParseValueChangeError::InvalidFormat),
+			("#99999999999999999999999 a 1 1", ParseValueChangeError::ParseTimestampErr),
+			("#10 a 1 99999999999999999999999", ParseValueChangeError::InvalidValueType),
+			("#10 a 1.5 4", ParseValueChangeError::InvalidValue),
+			("#10 a 101 1", ParseValueChangeError::MultiBitScalar),
+			("#10 a 10101 4", ParseValueChangeError::ValueTooLargeForVecWidth)
+		];
+		for (line, expected) in cases.iter() {
+			let e = error(line);
+			assert_eq!((7, String::from(*line), expected.clone()), (e.line_no, e.text, e.error));
+		}
+		assert_eq!(7, error("#10 a 1.5 4").column);
+		assert_eq!(format!("line 7: {}: 'hello world'", ParseValueChangeError::InvalidFormat), error("hello world").to_string());
+	}
+
+	#[test]
+	fn line_formats() {
+		let options = ParseOptions { input_format: InputFormat::JsonLines, ..ParseOptions::default() };
+		let changes = parse_line_with(r#"{"t": 10, "a": 1, "b": 0}"#, 1, &options).unwrap();
+		assert_eq!(2, changes.len());
+		assert_eq!(1, parse_line_with("#10 a 1 1", 1, &ParseOptions::default()).unwrap().len());
+		assert_eq!(ParseValueChangeError::InvalidFormat, parse_line_with("#10 a 1 1", 4, &options).unwrap_err().error);
+	}
+}
@@ -0,0 +1,152 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{ self, BufRead, Write };
+
+/// Fails writes that would take the output past `limit` bytes.
+pub struct Limited<W: Write> {
+	inner: W,
+	limit: u64,
+	written: u64
+}
+
+impl<W: Write> Limited<W> {
+	pub fn new(inner: W, limit: u64) -> Limited<W> {
+		Limited { inner, limit, written: 0 }
+	}
+}
+
+impl<W: Write> Write for Limited<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		if self.written + buf.len() as u64 > self.limit {
+			let message = format!("the output exceeds the limit of {} bytes", self.limit);
+			return Err(io::Error::new(io::ErrorKind::Other, message));
+		}
+		let n = self.inner.write(buf)?;
+		self.written += n as u64;
+		Ok(n)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
+
+/// The error of a line of `Lines` longer than its maximum length.
+#[derive(Debug)]
+pub struct LineTooLong {
+	pub len: usize,
+	pub max: usize
+}
+
+impl fmt::Display for LineTooLong {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "line is {} bytes long, longer than the maximum of {}", self.len, self.max)
+	}
+}
+
+impl Error for LineTooLong {}
+
+/// The `LineTooLong` error of a line, if it is one.
+pub fn too_long(error: &io::Error) -> Option<&LineTooLong> {
+	error.get_ref().and_then(|e| e.downcast_ref::<LineTooLong>())
+}
+
+/// The lines of a reader, like `BufRead::lines`, except that lines longer than `max` bytes are
+/// skipped over without being kept in memory, and read as a `LineTooLong` error.
+pub struct Lines<R: BufRead> {
+	reader: R,
+	max: Option<usize>
+}
+
+impl<R: BufRead> Lines<R> {
+	pub fn new(reader: R, max: Option<usize>) -> Lines<R> {
+		Lines { reader, max }
+	}
+
+	/// Reads the next line into `line`, only counting the bytes past the maximum. Returns the
+	/// length of the line, without the line ending.
+	fn read_line(&mut self, line: &mut Vec<u8>) -> io::Result<Option<usize>> {
+		let mut len = 0;
+		let mut read_any = false;
+		let mut last = [0; 2];
+		loop {
+			let (done, used) = {
+				let buf = self.reader.fill_buf()?;
+				if buf.is_empty() {
+					break;
+				}
+				read_any = true;
+				let (chunk, done) = match buf.iter().position(|&b| b == b'\n') {
+					Some(end) => (&buf[..end + 1], true),
+					None => (buf, false)
+				};
+				if !matches!(self.max, Some(max) if len + chunk.len() > max + 2) {
+					line.extend_from_slice(chunk);
+				}
+				if chunk.len() > 1 {
+					last.copy_from_slice(&chunk[chunk.len() - 2..]);
+				}
+				else {
+					last = [last[1], chunk[0]];
+				}
+				len += chunk.len();
+				(done, chunk.len())
+			};
+			self.reader.consume(used);
+			if done {
+				break;
+			}
+		}
+		if !read_any {
+			return Ok(None);
+		}
+		if last[1] == b'\n' {
+			len -= if last[0] == b'\r' { 2 } else { 1 };
+			line.truncate(len);
+		}
+		Ok(Some(len))
+	}
+}
+
+impl<R: BufRead> Iterator for Lines<R> {
+	type Item = io::Result<String>;
+
+	fn next(&mut self) -> Option<io::Result<String>> {
+		let mut line = Vec::new();
+		let len = match self.read_line(&mut line) {
+			Ok(len) => len?,
+			Err(e) => return Some(Err(e))
+		};
+		match self.max {
+			Some(max) if len > max => Some(Err(io::Error::new(io::ErrorKind::InvalidData, LineTooLong { len, max }))),
+			_ => Some(String::from_utf8(line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)))
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn limit() {
+		let mut output = Limited::new(Vec::new(), 4);
+		output.write_all(b"abc").unwrap();
+		assert!(output.write_all(b"de").is_err());
+		output.write_all(b"d").unwrap();
+		assert_eq!(b"abcd", &output.inner[..]);
+	}
+
+	#[test]
+	fn lines() {
+		let input = format!("short\r\n{}\nlast", "x".repeat(100));
+		let mut lines = Lines::new(io::BufReader::with_capacity(8, input.as_bytes()), Some(10));
+		assert_eq!("short", lines.next().unwrap().unwrap());
+		let error = lines.next().unwrap().unwrap_err();
+		assert_eq!(100, too_long(&error).unwrap().len);
+		assert_eq!("last", lines.next().unwrap().unwrap());
+		assert!(lines.next().is_none());
+		let lines: Vec<String> = Lines::new(input.as_bytes(), None).map(Result::unwrap).collect();
+		assert_eq!(3, lines.len());
+	}
+}
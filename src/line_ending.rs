@@ -0,0 +1,68 @@
+use std::io::{ self, Write };
+use std::str::FromStr;
+
+use serde::Serialize;
+
+/// The line ending used in the written VCD.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LineEnding {
+	Lf,
+	Crlf
+}
+
+impl FromStr for LineEnding {
+	type Err = String;
+	fn from_str(s: &str) -> Result<LineEnding, String> {
+		match s {
+			"lf" => Ok(LineEnding::Lf),
+			"crlf" => Ok(LineEnding::Crlf),
+			_ => Err(format!("invalid line ending '{}', expected 'lf' or 'crlf'", s))
+		}
+	}
+}
+
+/// Rewrites the `\n` line endings written to it as `\r\n`.
+pub struct CrlfWriter<W: Write> {
+	inner: W
+}
+
+impl<W: Write> CrlfWriter<W> {
+	pub fn new(inner: W) -> CrlfWriter<W> {
+		CrlfWriter { inner }
+	}
+}
+
+impl<W: Write> Write for CrlfWriter<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let mut lines = buf.split(|b| *b == b'\n');
+		if let Some(first) = lines.next() {
+			self.inner.write_all(first)?;
+		}
+		for line in lines {
+			self.inner.write_all(b"\r\n")?;
+			self.inner.write_all(line)?;
+		}
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn crlf() {
+		let mut output = Vec::new();
+		{
+			let mut writer = CrlfWriter::new(&mut output);
+			writer.write_all(b"#0\n1!").unwrap();
+			writer.write_all(b"\n\n#1\n").unwrap();
+		}
+		assert_eq!(b"#0\r\n1!\r\n\r\n#1\r\n".to_vec(), output);
+	}
+}
@@ -0,0 +1,51 @@
+use log::{ LevelFilter, Log, Metadata, Record };
+
+/// Logs the records of the conversion on stderr.
+struct StderrLogger;
+
+impl Log for StderrLogger {
+	fn enabled(&self, metadata: &Metadata) -> bool {
+		metadata.level() <= log::max_level()
+	}
+
+	fn log(&self, record: &Record) {
+		if self.enabled(record.metadata()) {
+			eprintln!("{}: {}", record.level().to_string().to_lowercase(), record.args());
+		}
+	}
+
+	fn flush(&self) {}
+}
+
+/// The level of `-v` given `verbose` times, only warnings are logged without it.
+pub fn level(verbose: u64) -> LevelFilter {
+	match verbose {
+		0 => LevelFilter::Warn,
+		1 => LevelFilter::Info,
+		2 => LevelFilter::Debug,
+		_ => LevelFilter::Trace
+	}
+}
+
+/// Installs the stderr logger with the level of `-v` given `verbose` times.
+pub fn init(verbose: u64) {
+	static LOGGER: StderrLogger = StderrLogger;
+	// there's only ever one logger, failing to set it means it already is
+	if log::set_logger(&LOGGER).is_ok() {
+		log::set_max_level(level(verbose));
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use log::Level;
+
+	#[test]
+	fn levels() {
+		assert_eq!(LevelFilter::Warn, level(0));
+		assert_eq!(LevelFilter::Info, level(1));
+		assert_eq!(LevelFilter::Trace, level(5));
+		assert!(Level::Debug <= level(2));
+	}
+}
@@ -1,136 +1,4994 @@
 // vcd specification:
 // http://staff.ustc.edu.cn/~songch/download/IEEE.1364-2005.pdf
 
-#[macro_use]
 extern crate structopt;
 
 #[macro_use]
-extern crate lazy_static;
+extern crate serde_json;
 
 extern crate chrono;
 extern crate vcd;
+extern crate log2vcd;
+extern crate zstd;
+extern crate flate2;
+#[macro_use]
+extern crate log;
+extern crate sha2;
+extern crate ctrlc;
+extern crate bincode;
 extern crate regex;
 
-use std::path::PathBuf;
+use std::convert::TryFrom;
+use std::path::{ Path, PathBuf };
 use std::str::FromStr;
 use std::fs::File;
-use std::io::{ Read, Write, BufReader, BufRead, stdin, stdout };
-use std::collections::HashMap;
+use std::io::{ self, Read, Write, BufReader, BufRead, stdin, stdout };
+use std::collections::{ HashMap, HashSet };
+use std::iter::Peekable;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
 
+use serde::Serialize;
 use structopt::StructOpt;
-use vcd::TimescaleUnit;
+use regex::Regex;
+use vcd::{ Writer, IdCode, Var, VarType, Header, Scope, ScopeItem, ScopeType, SimulationCommand, TimescaleUnit };
 
-mod value_change;
+use log2vcd::LineError;
+use log2vcd::value_change;
+use log2vcd::convert::{ self, var_kind, scope_path, write_value, check_unique_references, WaveWriter };
 
-use self::value_change::ValueChange;
+mod diagnostics;
+mod progress;
+mod atomic;
+mod tee;
+mod self_check;
+mod line_ending;
+mod gtkw;
+mod stats;
+mod compress;
+mod external_sort;
+mod manifest;
+mod interrupt;
+mod rescale;
+mod limit;
+mod watch;
+mod window;
+mod metadata;
+mod explain;
+mod logger;
+mod conformance;
+mod wall_clock;
+mod time_index;
+mod realtime;
+mod reverse;
+mod fst;
+mod diff;
+mod thumbnail;
+#[cfg(test)]
+mod temp;
 
-#[derive(StructOpt, Debug)]
+use self::value_change::{ ValueChange, Columns, FieldOrder, InputFormat, Level, ParseOptions, ParseValueChangeError, ScalarValue, SnapMode };
+use self::diagnostics::{ Diagnostic, Diagnostics, DiagnosticsFormat };
+use self::progress::Progress;
+use self::tee::Tee;
+use self::line_ending::{ LineEnding, CrlfWriter };
+use self::compress::Compression;
+use self::external_sort::ExternalSort;
+use self::manifest::{ HashingReader, HashingWriter };
+use self::interrupt::Interruptible;
+use self::limit::Limited;
+use self::metadata::Metadata;
+use self::wall_clock::{ WallClock, WallClockError };
+use self::time_index::{ CountingWriter, TimeIndex };
+use self::realtime::Pacer;
+
+#[derive(StructOpt, Debug, Serialize)]
 struct Options {
-	#[structopt(short = "i", long = "input_file", parse(from_os_str))]
+	#[structopt(short = "i", long = "input_file", parse(from_os_str), raw(global = "true"))]
 	/// Log file to read from, if no input file is provided, input will be read from stdin
 	input_path: Option<PathBuf>,
 
-	#[structopt(short = "o", long = "output_file", parse(from_os_str))]
+	#[structopt(short = "o", long = "output_file", parse(from_os_str), raw(global = "true"))]
 	/// The file to write the output to, if no file is provided, the output will be printed to stdout
 	output_path: Option<PathBuf>,
 
-	#[structopt(short = "u", long = "unit", parse(try_from_str))]
-	/// Timescale unit, must be one of: { 'S', 'MS', 'US', 'NS', 'PS', 'FS' }
-	unit: TimescaleUnit,
+	#[structopt(long = "input-dir", parse(from_os_str), requires = "output_dir", raw(conflicts_with_all = r#"&["input_path", "output_path", "watch", "labels"]"#), raw(global = "true"))]
+	/// Convert every `*.log` of this directory to a `.vcd` of the --output-dir, a file failing to
+	/// convert only fails the whole batch with --strict
+	input_dir: Option<PathBuf>,
+
+	#[structopt(long = "output-dir", parse(from_os_str), requires = "input_dir", raw(global = "true"))]
+	/// Directory the VCDs of --input-dir are written to, created if it doesn't exist
+	output_dir: Option<PathBuf>,
 
-	#[structopt(long = "step_size", parse(try_from_str), default_value = "1")]
+	#[structopt(short = "u", long = "unit", parse(try_from_str = "parse_unit"), raw(global = "true"))]
+	/// Timescale unit, must be one of: { 'S', 'MS', 'US', 'NS', 'PS', 'FS' } (in any case), or the unit
+	/// written out (e.g. 'nanoseconds'). Defaults to the timescale of the log's header block, or nanoseconds.
+	#[serde(serialize_with = "manifest::display_option")]
+	unit: Option<TimescaleUnit>,
+
+	#[structopt(short = "v", long = "verbose", parse(from_occurrences), raw(global = "true"))]
+	/// Log the steps of the conversion on stderr, repeat for more detail (`-vv`, `-vvv`)
+	verbose: u64,
+
+	#[structopt(long = "step_size", parse(try_from_str), default_value = "1", raw(global = "true"))]
 	/// Timescale step size
-	step_size: u32
+	step_size: u32,
+
+	#[structopt(long = "head", parse(try_from_str), raw(global = "true"))]
+	/// Only convert the value changes belonging to the first n distinct timestamps of the output. A
+	/// log in output order (--vars or --preserve-order) is only read up to the first line past them
+	head: Option<usize>,
+
+	#[structopt(long = "vars", parse(from_os_str), raw(global = "true"))]
+	/// File declaring every variable up front, one `name type width` per line.
+	/// The header is written immediately and the (already sorted) log is streamed without a pre-pass.
+	/// Ctrl-C then ends the VCD at the last value change, instead of cutting it short.
+	vars_path: Option<PathBuf>,
+
+	#[structopt(long = "real-as-fixed", parse(try_from_str), raw(global = "true"))]
+	/// Encode real values as fixed point integer vectors with this many fractional bits
+	real_as_fixed: Option<u32>,
+
+	#[structopt(long = "real-scale", parse(try_from_str = "parse_real_scale"), raw(global = "true"))]
+	/// Encode the real values of a signal as integer vectors, as `name=factor` they're multiplied by before rounding,
+	/// written to the header as `real-scale <name> <factor>` comment lines
+	real_scales: Vec<(String, f64)>,
+
+	#[structopt(long = "real-sentinel", parse(try_from_str = "parse_real_sentinel"), raw(global = "true"))]
+	/// A real value that means a signal has no data, as `name=value` (`name=nan` for nan), its changes
+	/// to the value aren't written
+	real_sentinels: Vec<(String, f64)>,
+
+	#[structopt(long = "fixed-width", parse(try_from_str), default_value = "32", raw(global = "true"))]
+	/// Declared width of real values encoded with --real-as-fixed or --real-scale
+	fixed_width: usize,
+
+	#[structopt(long = "trailing-timestamp", raw(global = "true"))]
+	/// Write a final timestamp one tick after the last value change, so the final values have a width
+	trailing_timestamp: bool,
+
+	#[structopt(long = "pad-end", raw(global = "true"))]
+	/// Write the value of every signal again at the --end-time or the --trailing-timestamp, so they all end
+	/// there. Without either the values already end at the last timestamp, which isn't written again
+	pad_end: bool,
+
+	#[structopt(long = "snapshot", raw(conflicts_with_all = r#"&["pad_end", "self_check"]"#), raw(global = "true"))]
+	/// Only write the final value of every signal, in a single $dumpvars block at the last timestamp (or the --end-time)
+	snapshot: bool,
+
+	#[structopt(long = "end-time", parse(try_from_str), raw(global = "true"))]
+	/// Write a final timestamp at this time, after all value changes
+	end_time: Option<u64>,
+
+	#[structopt(long = "emit-end-comment", raw(global = "true"))]
+	/// Write a `$comment end_time=<timestamp> $end` with the timestamp of the last value change at the end
+	emit_end_comment: bool,
+
+	#[structopt(long = "emit-tick-comment", raw(global = "true"))]
+	/// Write a `$comment 1 tick = <step size> <unit> $end` after the header
+	emit_tick_comment: bool,
+
+	#[structopt(long = "window-split", raw(requires = r#""output_path""#), conflicts_with = "compress", raw(global = "true"))]
+	/// Also split the output into VCDs covering this many ticks each, `out.0.vcd`, `out.1.vcd`, ...
+	window_split: Option<u64>,
+
+	#[structopt(long = "realtime", parse(try_from_str = "parse_realtime_scale"), raw(conflicts_with_all = r#"&["output_path", "self_check", "compress"]"#), raw(global = "true"))]
+	/// Replay the value changes on stdout paced to the wall clock, with timestamps this many times as far apart as their VCD time (1 is real time, 2 half speed)
+	realtime: Option<f64>,
+
+	#[structopt(long = "time-index", parse(from_os_str), conflicts_with = "compress", raw(global = "true"))]
+	/// Also write an index of the byte offset in the output of each timestamp, as `timestamp offset` lines
+	time_index_path: Option<PathBuf>,
+
+	#[structopt(long = "dump-ids", raw(global = "true"))]
+	/// Print the id of every signal to stderr as `name id` lines, which --id-map reads back
+	dump_ids: bool,
+
+	#[structopt(long = "emit-toc", raw(global = "true"))]
+	/// Write a comment after the header listing every signal as `name id width`, for tools that don't parse the header
+	emit_toc: bool,
+
+	#[structopt(long = "flat", raw(global = "true"))]
+	/// Declare every signal under its full name in the top scope, instead of `a.b.c` as `c` in scope
+	/// `b` in scope `a`. The scopes are nested in the top scope either way, `outputs` or the --label
+	/// of the log
+	flat: bool,
+
+	#[structopt(long = "no-nest-pattern", conflicts_with = "flat", parse(try_from_str), raw(global = "true"))]
+	/// Regex of signal names that are declared flat, such as ip addresses
+	#[serde(serialize_with = "manifest::display_all")]
+	no_nest_patterns: Vec<Regex>,
+
+	#[structopt(long = "strip-prefix", parse(try_from_str = "parse_prefix"), raw(global = "true"))]
+	/// Regex of a prefix removed from the start of every line before it's parsed, such as the `[core] `
+	/// tag of a logger
+	#[serde(serialize_with = "manifest::display_option")]
+	strip_prefix: Option<Regex>,
+
+	#[structopt(long = "scope-separator", conflicts_with = "flat", raw(global = "true"))]
+	/// Separator of the scopes in signal names, such as `/` or `::`, instead of `.`
+	scope_separator: Option<String>,
+
+	#[structopt(long = "scope-type", parse(try_from_str = "parse_scope_type"), raw(global = "true"))]
+	/// Type of a scope, as `path=type` where path is the dot separated scope path (e.g. `outputs=task`)
+	/// and type is one of { 'module', 'task', 'function', 'begin', 'fork' }
+	#[serde(serialize_with = "manifest::display_pairs")]
+	scope_types: Vec<(String, ScopeType)>,
+
+	#[structopt(long = "var-type", parse(try_from_str = "parse_var_type"), raw(global = "true"))]
+	/// Declare every variable, or a single one as `name=type`, with a type such as `reg` instead of
+	/// the inferred one. Only reals can be declared `real`
+	#[serde(serialize_with = "manifest::display_pairs")]
+	var_types: Vec<(Option<String>, VarType)>,
+
+	#[structopt(long = "strict", raw(global = "true"))]
+	/// Treat problems with the input (such as a line that isn't a value change, or finding no value
+	/// changes at all) as errors
+	strict: bool,
+
+	#[structopt(long = "id-map", parse(from_os_str), raw(global = "true"))]
+	/// File pinning the ids of signals, one `name id` per line. Unlisted signals get generated ids.
+	id_map_path: Option<PathBuf>,
+
+	#[structopt(long = "expect-signals", parse(from_os_str), raw(conflicts_with = r#""vars_path""#), raw(global = "true"))]
+	/// File listing the signal names expected in the log, one per line. The conversion fails if the log has any other signal.
+	expect_signals_path: Option<PathBuf>,
+
+	#[structopt(long = "expect-exact", raw(requires = r#""expect_signals_path""#), raw(global = "true"))]
+	/// Also fail if any of the --expect-signals is missing from the log
+	expect_exact: bool,
+
+	#[structopt(long = "order", parse(from_os_str), raw(conflicts_with_all = r#"&["vars_path", "external_sort"]"#), raw(global = "true"))]
+	/// File listing signal names in the order they are declared, one per line. Unlisted signals follow alphabetically.
+	order_path: Option<PathBuf>,
+
+	#[structopt(long = "order-by", raw(conflicts_with_all = r#"&["order_path", "vars_path", "external_sort"]"#), raw(global = "true"))]
+	/// Order the signals are declared and given ids in, one of { 'first-seen', 'alphabetical' }.
+	/// The default is `first-seen`, not alphabetical: without this option the signals are declared
+	/// in the order of their first change, `alphabetical` declares them in name order.
+	order_by: Option<OrderBy>,
+
+	#[structopt(long = "canonical", raw(conflicts_with_all = r#"&["order_path", "order_by", "vars_path", "external_sort", "preserve_order", "annotate_lines"]"#), raw(global = "true"))]
+	/// Write the same bytes for logs with the same value changes, whatever their order: signals are
+	/// declared and given ids alphabetically, changes at a timestamp are sorted by signal and each
+	/// timestamp is written once
+	canonical: bool,
+
+	#[structopt(long = "dumpvars", raw(global = "true"))]
+	/// Write a $dumpvars block at time 0 that initializes every signal to x
+	dumpvars: bool,
+
+	#[structopt(long = "assume-initial", parse(try_from_str = "parse_assignment"), raw(global = "true"))]
+	/// Initial value of a signal written in the $dumpvars block at time 0, as `name=value`. A `!` value
+	/// resets a signal to its initial value, x if it has none
+	assume_initial: Vec<(String, String)>,
+
+	#[structopt(long = "split-vectors", raw(global = "true"))]
+	/// Split every vector signal `name` into scalar signals `name[0]` (the least significant bit) to `name[n-1]`
+	split_vectors: bool,
+
+	#[structopt(long = "merge-bits", conflicts_with = "split_vectors", parse(try_from_str), raw(global = "true"))]
+	/// Regex of bus names whose bits are logged as separate signals `name.0` (the least significant bit)
+	/// to `name.<n-1>`, merged into a single vector signal `name`
+	#[serde(serialize_with = "manifest::display_all")]
+	merge_bits: Vec<Regex>,
+
+	#[structopt(long = "no-time-zero", raw(conflicts_with_all = r#"&["dumpvars", "assume_initial"]"#), raw(global = "true"))]
+	/// Don't write a timestamp at time 0, the output starts at the first value change
+	no_time_zero: bool,
+
+	#[structopt(long = "field-order", parse(try_from_str), default_value = "value-width", raw(global = "true"))]
+	/// Order of the last two fields of a log line, either 'value-width' (`#100 sig 11110010 8`)
+	/// or 'width-value' (`#100 sig 8 11110010`)
+	field_order: FieldOrder,
+
+	#[structopt(long = "columns", parse(try_from_str), raw(global = "true"))]
+	/// Pick the fields of positional log lines by their 1-based columns, the columns of the timestamp, name, value and type
+	/// (e.g. `1,3,4,5` for `100 host bus 1010 4 extra`)
+	columns: Option<Columns>,
+
+	#[structopt(long = "diagnostics", parse(try_from_str), default_value = "text", raw(global = "true"))]
+	/// How problems with the input are reported on stderr, either 'text' or 'json'
+	/// (a JSON array of objects with a line, column, code and message)
+	diagnostics: DiagnosticsFormat,
+
+	#[structopt(long = "max-warnings", parse(try_from_str), raw(global = "true"))]
+	/// Print at most this many warnings about lines of the input, followed by how many more there were
+	max_warnings: Option<usize>,
+
+	#[structopt(long = "profile", raw(global = "true"))]
+	/// Print the time spent parsing, sorting and writing on stderr once done
+	profile: bool,
+
+	#[structopt(long = "alias", parse(try_from_str = "parse_assignment"), raw(global = "true"))]
+	/// Log changes of another name to the same variable, as `name=alias`
+	aliases: Vec<(String, String)>,
+
+	#[structopt(long = "real-epsilon", parse(try_from_str), raw(global = "true"))]
+	/// Only write a change of a real signal if it differs from the last written value by more than this
+	real_epsilon: Option<f64>,
+
+	#[structopt(long = "real-precision", conflicts_with = "real_as_fixed", raw(global = "true"))]
+	/// Round the values of real signals to this many decimal places
+	real_precision: Option<usize>,
+
+	#[structopt(long = "tee", raw(requires = r#""output_path""#), raw(global = "true"))]
+	/// Also write the VCD to stdout when writing it to an output file
+	tee: bool,
+
+	#[structopt(long = "radix", parse(try_from_str = "parse_radix"), raw(global = "true"))]
+	/// Display radix hint for a signal, as `name=radix` where radix is one of { 'hex', 'dec', 'bin' },
+	/// written to the header as `radix <name> <radix>` comment lines
+	radixes: Vec<(String, String)>,
+
+	#[structopt(long = "pull", parse(try_from_str = "parse_pull"), raw(global = "true"))]
+	/// Value a signal is pulled to when it's high impedance, as `name=value` where value is 0 or 1.
+	/// Its `z` bits are written as this value.
+	pulls: Vec<(String, ScalarValue)>,
+
+	#[structopt(long = "self-check", raw(global = "true"))]
+	/// Read the written VCD back and check that it decodes to the converted value changes
+	self_check: bool,
+
+	#[structopt(long = "compact", raw(global = "true"))]
+	/// Write vectors that are entirely `x` or entirely `z` with the single character shorthand (e.g. `bz`)
+	compact: bool,
+
+	#[structopt(long = "max-line-len", raw(global = "true"))]
+	/// Skip lines longer than this many bytes without parsing them (an error with --strict)
+	max_line_len: Option<usize>,
+
+	#[structopt(long = "line-ending", default_value = "lf", raw(global = "true"))]
+	/// Line ending of the written VCD, one of { 'lf', 'crlf' }
+	line_ending: LineEnding,
+
+	#[structopt(long = "allow-numeric-names", raw(global = "true"))]
+	/// Allow signal names starting with a digit (e.g. numbered channels such as `#10 3 1 1`)
+	allow_numeric_names: bool,
+
+	#[structopt(long = "assume-width", raw(global = "true"))]
+	/// Width of the values of lines without a width field (e.g. 1 for `#100 sig 1`), which are invalid without it
+	assume_width: Option<usize>,
+
+	#[structopt(long = "gtkw", parse(from_os_str), raw(global = "true"))]
+	/// Also write a GTKWave save file that loads every signal
+	gtkw_path: Option<PathBuf>,
+
+	#[structopt(long = "color", parse(try_from_str = "parse_color"), raw(requires = r#""gtkw_path""#), raw(global = "true"))]
+	/// Color of a signal in the --gtkw save file, as `name=color` where color is one of { 'normal', 'red',
+	/// 'orange', 'yellow', 'green', 'blue', 'indigo', 'violet' }
+	colors: Vec<(String, &'static str)>,
+
+	#[structopt(long = "promote-width", raw(global = "true"))]
+	/// Declare a signal logged as both scalars and vectors as a vector of the widest width seen,
+	/// instead of keeping the type it was first logged with
+	promote_width: bool,
+
+	#[structopt(long = "growing-widths", raw(global = "true"))]
+	/// Fail when a signal is logged with a narrower width than one of its earlier changes, for buses
+	/// that may only grow
+	growing_widths: bool,
+
+	#[structopt(long = "also-emit", parse(try_from_str = "parse_unit"), requires = "output_path", conflicts_with = "compress", raw(global = "true"))]
+	/// Also write the VCD with this timescale unit, rounding the timestamps, next to the output
+	/// (`out.vcd` also gets `out.us.vcd`)
+	#[serde(serialize_with = "manifest::display_option")]
+	also_emit: Option<TimescaleUnit>,
+
+	#[structopt(long = "preserve-order", raw(global = "true"))]
+	/// Don't sort the value changes, the log already being in order. A timestamp going backwards is
+	/// reported (an error with --strict), lines with the same timestamp keep their order
+	preserve_order: bool,
+
+	#[structopt(long = "split-by-width", conflicts_with = "promote_width", raw(global = "true"))]
+	/// Declare a variable per width for signals logged with different widths, named `signal@width`
+	/// (or `signal@f` for reals)
+	split_by_width: bool,
+
+	#[structopt(long = "stats", raw(global = "true"))]
+	/// Print statistics of the conversion (lines read, parsed and dropped by error) to stderr
+	stats: bool,
+
+	#[structopt(long = "summary-json", parse(from_os_str), raw(global = "true"))]
+	/// Write statistics of the conversion (lines read, parsed and dropped by error) as JSON to this file
+	summary_json_path: Option<PathBuf>,
+
+	#[structopt(long = "unknown-marker", default_value = "warn", raw(global = "true"))]
+	/// What to do with lines whose value type is a marker other than a width, `<width>s` or `f`,
+	/// one of { 'error', 'skip', 'warn' }
+	unknown_marker: UnknownMarker,
+
+	#[structopt(long = "timestamp-ranges", raw(conflicts_with_all = r#"&["vars_path", "external_sort", "preserve_order"]"#), raw(global = "true"))]
+	/// Read `#start-#end` timestamps, a run of the value from start to end. After the end the signal
+	/// keeps the value with 'hold', or is x with 'unknown'
+	timestamp_ranges: Option<RangeEnd>,
+
+	#[structopt(long = "dump-parsed", parse(from_os_str), raw(conflicts_with_all = r#"&["vars_path", "external_sort"]"#), raw(global = "true"))]
+	/// Also save the parsed value changes to this file, to convert them again with --load-parsed.
+	/// Comments and raw VCD lines aren't saved
+	dump_parsed_path: Option<PathBuf>,
+
+	#[structopt(long = "load-parsed", parse(from_os_str), raw(conflicts_with_all = r#"&["vars_path", "external_sort", "input_path", "labels"]"#), raw(global = "true"))]
+	/// Convert the value changes saved by --dump-parsed instead of reading a log
+	load_parsed_path: Option<PathBuf>,
+
+	#[structopt(long = "conformance", raw(global = "true"))]
+	/// Convert the built-in conformance corpus and compare the results to the expected VCDs, instead of converting the input
+	conformance: bool,
+
+	#[structopt(long = "watch", requires = "input_path", raw(conflicts_with_all = r#"&["vars_path", "load_parsed_path"]"#), raw(global = "true"))]
+	/// Convert the input again every time it's modified, until interrupted
+	watch: bool,
+
+	#[structopt(long = "hotspots", raw(conflicts_with_all = r#"&["vars_path", "external_sort"]"#), raw(global = "true"))]
+	/// Print this many signals with the most transitions to stderr
+	hotspots: Option<usize>,
+
+	#[structopt(long = "gap-report", raw(conflicts_with_all = r#"&["vars_path", "external_sort"]"#), raw(global = "true"))]
+	/// Print the intervals between consecutive timestamps longer than this many ticks to stderr
+	gap_report: Option<u64>,
+
+	#[structopt(long = "thumbnail", parse(from_os_str), raw(conflicts_with_all = r#"&["vars_path", "external_sort"]"#), raw(global = "true"))]
+	/// Also write a small SVG of the first signals over the whole trace
+	thumbnail_path: Option<PathBuf>,
+
+	#[structopt(long = "annotate-counts", raw(conflicts_with_all = r#"&["vars_path", "external_sort"]"#), raw(global = "true"))]
+	/// Write a comment after the header listing the transition count of every signal as `name count`
+	annotate_counts: bool,
+
+	#[structopt(long = "annotate-lines", conflicts_with = "external_sort", raw(global = "true"))]
+	/// Write a `line N` comment before every value change, with the line of the log it was read from
+	annotate_lines: bool,
+
+	#[structopt(long = "report-dup-lines", raw(global = "true"))]
+	/// Count the lines identical to the line before them, which usually are logged twice, and report them
+	report_dup_lines: bool,
+
+	#[structopt(long = "max-drop-rate", raw(global = "true"))]
+	/// Fail the conversion if more than this percentage of the lines are dropped as invalid
+	max_drop_rate: Option<f64>,
+
+	#[structopt(long = "max-output-bytes", raw(global = "true"))]
+	/// Fail the conversion once the output would be larger than this many bytes
+	max_output_bytes: Option<u64>,
+
+	#[structopt(long = "manifest", parse(from_os_str), raw(global = "true"))]
+	/// Write the SHA-256 of the input and output, the options and the log2vcd version and statistics
+	/// of the conversion as JSON to this file
+	manifest_path: Option<PathBuf>,
+
+	#[structopt(long = "inline-time-units", raw(global = "true"))]
+	/// Allow timestamps with a unit suffix (e.g. `#100ns` or `#1us`), converted to the output time unit
+	inline_time_units: bool,
+
+	#[structopt(long = "min-width", raw(global = "true"))]
+	/// Zero-extend vectors narrower than this many bits to this width
+	min_width: Option<usize>,
+
+	#[structopt(long = "validate-widths-strict", conflicts_with = "min_width", raw(global = "true"))]
+	/// Fail on vector values with fewer bits than their width, instead of padding them
+	validate_widths_strict: bool,
+
+	#[structopt(long = "label", parse(try_from_str = "parse_assignment"), raw(conflicts_with = r#""vars_path""#), raw(global = "true"))]
+	/// Also read the log `file`, declaring its signals in a top scope of their own, as `file=scope`
+	labels: Vec<(String, String)>,
+
+	#[structopt(long = "prefix", parse(try_from_str = "parse_assignment"), raw(conflicts_with = r#""vars_path""#), raw(global = "true"))]
+	/// Prefix of the signal names of the input or a --label log, as `file=prefix`, so logs sharing signal
+	/// names don't alias each other
+	prefixes: Vec<(String, String)>,
+
+	#[structopt(long = "diff", parse(from_os_str), raw(conflicts_with_all = r#"&["vars_path", "external_sort", "preserve_order"]"#), raw(global = "true"))]
+	/// Compare with the log `file`, adding a `<signal>_diff` signal that is high while the signal has a
+	/// different value in it
+	diff_path: Option<PathBuf>,
+
+	#[structopt(long = "compress-sidecar", raw(global = "true"))]
+	/// Gzip the --summary-json, --manifest and --time-index files, written with `.gz` appended to their path
+	compress_sidecar: bool,
+
+	#[structopt(long = "compress", raw(global = "true"))]
+	/// Compress the VCD, one of { 'zstd' }. Output files ending in `.zst` are compressed by default
+	compress: Option<Compression>,
+
+	#[structopt(long = "normalize-names", raw(conflicts_with = r#""vars_path""#), raw(global = "true"))]
+	/// Replace whitespace in signal names with `_` and remove characters that aren't valid in VCD references
+	normalize_names: bool,
+
+	#[structopt(long = "input-format", default_value = "log", raw(global = "true"))]
+	/// Syntax of the log lines, one of { 'log', 'kv', 'json-lines' } where kv lines are
+	/// `t=100 sig=foo val=1 w=1` with the fields in any order, and json-lines lines are objects
+	/// `{"t": 100, "foo": true, ...}` of which only the fields that changed are written, and leveled
+	/// lines are log lines after a severity (`INFO #100 foo 1 1`). vcd-like logs have `#100` lines,
+	/// each followed by the `foo 1 1` lines of the timestamp
+	input_format: InputFormat,
+
+	#[structopt(long = "min-level", raw(global = "true"))]
+	/// Ignore the lines of a leveled log below this level, one of { 'trace', 'debug', 'info', 'warn', 'error' }
+	min_level: Option<Level>,
+
+	#[structopt(long = "real-merge", raw(global = "true"))]
+	/// Merge the changes of a real signal at the same timestamp into one, one of { 'last', 'mean' }
+	real_merge: Option<RealMerge>,
+
+	#[structopt(long = "real-special", default_value = "drop", raw(global = "true"))]
+	/// What to do with `inf` and `nan` real values, one of { 'drop', 'error', 'clamp', 'pass' }. `drop` skips
+	/// them with a warning, `clamp` writes the largest finite reals instead of infinities and 0 instead
+	/// of nan, `pass` writes them as they are
+	real_special: RealSpecial,
+
+	#[structopt(long = "explain", parse(try_from_str), raw(global = "true"))]
+	/// Explain why the first n dropped lines couldn't be parsed along with their diagnostics, with a hint
+	/// on how to fix them
+	explain: Option<usize>,
+
+	#[structopt(long = "decimate", parse(try_from_str), raw(global = "true"))]
+	/// Keep only the last change of each signal in every bucket of this many ticks
+	decimate: Option<u64>,
+
+	#[structopt(long = "resample", parse(try_from_str), conflicts_with = "decimate", raw(global = "true"))]
+	/// Write the value of every signal at each multiple of this many ticks instead of the changes
+	resample: Option<u64>,
+
+	#[structopt(long = "output-format", default_value = "vcd", raw(global = "true"))]
+	/// What to write, one of { 'vcd', 'edges', 'deltas', 'fst' } where edges is a `timestamp signal old->new`
+	/// line per transition, deltas a `delta signal value` line per change, with the time since the
+	/// previous change, and fst the binary format of GTKWave
+	output_format: OutputFormat,
+
+	#[structopt(long = "external-sort", raw(conflicts_with_all = r#"&["normalize_names", "real_merge", "promote_width", "split_by_width", "preserve_order", "trim_signals", "decimate", "resample", "merge_bits"]"#), raw(global = "true"))]
+	/// Sort the log on disk in chunks of --chunk-size value changes, for logs that don't fit in memory
+	external_sort: bool,
+
+	#[structopt(long = "chunk-size", requires = "external_sort", raw(global = "true"))]
+	/// Number of value changes of a chunk of --external-sort, a million by default
+	chunk_size: Option<usize>,
+
+	#[structopt(long = "max-memory", parse(try_from_str = "parse_memory"), conflicts_with = "external_sort", raw(global = "true"))]
+	/// Fail instead of holding more than this many bytes (or `K`, `M` and `G` bytes) of value changes
+	/// in memory, an estimate
+	max_memory: Option<usize>,
+
+	#[structopt(long = "trim-signals", raw(global = "true"))]
+	/// Leave out the signals whose value never changes
+	trim_signals: bool,
+
+	#[structopt(long = "enum", parse(try_from_str = "parse_enum"), raw(global = "true"))]
+	/// States of an enumerated signal, as `name=file` where each line of the file is a `value state`
+	/// pair. The signal may be logged with its state names, and the states are written to the header
+	/// as an `enum <name> <value>=<state> ...` comment line
+	enums: Vec<(String, Vec<(u64, String)>)>,
+
+	#[structopt(long = "transform", parse(try_from_str = "parse_transform"), raw(global = "true"))]
+	/// Transform the values of a signal before they're written, as `name=transform` where transform
+	/// is one of { 'invert', 'reverse' }
+	transforms: Vec<(String, String)>,
+
+	#[structopt(long = "vcd-passthrough", raw(global = "true"))]
+	/// Copy raw VCD value changes (e.g. `1!` or `b1010 "`) in the log to the output, at the time of the
+	/// last timestamp before them. A `#timestamp` line on its own sets that time
+	vcd_passthrough: bool,
+
+	#[structopt(long = "collapse-x", raw(global = "true"))]
+	/// Only write the first unknown (`x` or `z`) value of a signal until it takes a defined value again
+	collapse_x: bool,
+
+	#[structopt(long = "list-signals", raw(global = "true"))]
+	/// Print the discovered signals as `name type width` lines (the format read by --vars)
+	/// instead of converting the log
+	list_signals: bool,
+
+	#[structopt(long = "input-time-unit", parse(try_from_str = "parse_unit"), raw(global = "true"))]
+	/// Unit of the timestamps in the log, which may then be decimal numbers (e.g. `#12.5`).
+	/// Timestamps are converted to the output timescale and snapped to a step, see --snap-mode.
+	#[serde(serialize_with = "manifest::display_option")]
+	input_time_unit: Option<TimescaleUnit>,
+
+	#[structopt(long = "snap-mode", default_value = "nearest", raw(global = "true"))]
+	/// How --input-time-unit and --inline-time-units timestamps between two steps are snapped to one,
+	/// one of { 'nearest', 'floor', 'ceil' }
+	snap_mode: SnapMode,
+
+	#[structopt(long = "input-time-format", conflicts_with = "input_time_unit", raw(global = "true"))]
+	/// Format of wall-clock timestamps starting the lines, such as `%Y-%m-%dT%H:%M:%S%.f` (see chrono's
+	/// strftime), or `epoch-millis`. They are converted to ticks after the timestamp of the first line.
+	input_time_format: Option<String>,
+
+	#[structopt(long = "synthetic-time", parse(try_from_str), raw(conflicts_with_all = r#"&["input_time_format", "input_time_unit"]"#), raw(global = "true"))]
+	/// Timestamp the lines of a log without timestamps, `name value width`, this many ticks apart,
+	/// the first line at time zero
+	synthetic_time: Option<u64>,
+
+	#[structopt(long = "progress", raw(global = "true"))]
+	/// Report the progress of reading the input on stderr
+	progress: bool,
+
+	#[structopt(subcommand)]
+	command: Option<Command>
 }
 
-fn main() {
-	use vcd::{Writer, IdCode, Var, VarType, Header, Scope, ScopeItem, ScopeType};
+/// What to do with the input, converting it to a VCD without a subcommand. The options are given
+/// before or after the subcommand.
+#[derive(StructOpt, Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum Command {
+	#[structopt(name = "convert")]
+	/// Convert the log to a VCD
+	Convert,
+	#[structopt(name = "check")]
+	/// Convert the log and read the VCD back, like --self-check, reporting the diagnostics without writing it
+	Check,
+	#[structopt(name = "list")]
+	/// Print the discovered signals as `name type width` lines, like --list-signals
+	List,
+	#[structopt(name = "reverse")]
+	/// Convert a VCD back to a log of `#timestamp name value width` lines
+	Reverse
+}
 
-	let options = Options::from_args();
+impl Options {
+	/// The timescale of the output, the one of `-u` overrides the one of the header block.
+	fn timescale(&self, metadata: &Metadata) -> (u32, TimescaleUnit) {
+		match self.unit {
+			Some(unit) => (self.step_size, unit),
+			None => metadata.timescale.unwrap_or((self.step_size, DEFAULT_UNIT))
+		}
+	}
 
-	let input: Box<Read> = match options.input_path {
-		Some(path) => Box::new(File::open(path).expect("Failed to open input file.")),
-		None => Box::new(stdin())
+	fn parse_options(&self, (step_size, unit): (u32, TimescaleUnit)) -> ParseOptions {
+		ParseOptions {
+			input_format: self.input_format,
+			field_order: self.field_order,
+			columns: self.columns,
+			timestamp_scale: self.input_time_unit.map(|input_unit| {
+				unit.divisor() as f64 / input_unit.divisor() as f64 / f64::from(step_size)
+			}),
+			snap_mode: self.snap_mode,
+			allow_numeric_names: self.allow_numeric_names,
+			inline_units: if self.inline_time_units { Some((step_size, unit)) } else { None },
+			assume_width: self.assume_width,
+			scope_separator: self.scope_separator.clone(),
+			nest: !self.flat,
+			no_nest_patterns: self.no_nest_patterns.clone()
+		}
+	}
+
+	/// Whether the signals are listed instead of converted, with `--list-signals` or `list`.
+	fn lists_signals(&self) -> bool {
+		self.list_signals || self.command == Some(Command::List)
+	}
+
+	/// Whether the VCD is read back, with `--self-check` or `check`.
+	fn checks_output(&self) -> bool {
+		self.self_check || self.command == Some(Command::Check)
+	}
+
+	fn compression(&self) -> Option<Compression> {
+		self.compress.or_else(|| self.output_path.as_ref().and_then(|path| Compression::for_path(path)))
+	}
+
+	/// Returns the name of the variable the changes of `name` are logged to.
+	fn resolve_alias<'a>(&'a self, name: &'a str) -> &'a str {
+		self.aliases.iter()
+			.find(|(_, alias)| alias == name)
+			.map_or(name, |(name, _)| name.as_str())
+	}
+
+	fn has_aliases(&self, name: &str) -> bool {
+		self.aliases.iter().any(|(n, _)| n == name)
+	}
+}
+
+/// Anchors the `--strip-prefix` regex to the start of the line.
+fn parse_prefix(s: &str) -> Result<Regex, regex::Error> {
+	Regex::new(&format!("^(?:{})", s))
+}
+
+fn parse_unit(s: &str) -> Result<TimescaleUnit, String> {
+	match s.to_lowercase().as_str() {
+		"s" | "sec" | "second" | "seconds" => Ok(TimescaleUnit::S),
+		"ms" | "millisecond" | "milliseconds" => Ok(TimescaleUnit::MS),
+		"us" | "µs" | "microsecond" | "microseconds" => Ok(TimescaleUnit::US),
+		"ns" | "nanosecond" | "nanoseconds" => Ok(TimescaleUnit::NS),
+		"ps" | "picosecond" | "picoseconds" => Ok(TimescaleUnit::PS),
+		"fs" | "femtosecond" | "femtoseconds" => Ok(TimescaleUnit::FS),
+		_ => Err(format!("invalid timescale unit '{}'", s))
+	}
+}
+
+/// Splits a `key=value` command line argument.
+fn parse_assignment(s: &str) -> Result<(String, String), String> {
+	let mut parts = s.splitn(2, '=');
+	let key = parts.next().unwrap();
+	let value = parts.next().ok_or_else(|| format!("expected key=value, got '{}'", s))?;
+	Ok((String::from(key), String::from(value)))
+}
+
+fn parse_real_scale(s: &str) -> Result<(String, f64), String> {
+	let (name, factor) = parse_assignment(s)?;
+	match f64::from_str(&factor) {
+		Ok(factor) if factor.is_finite() => Ok((name, factor)),
+		_ => Err(format!("invalid scale factor '{}'", factor))
+	}
+}
+
+fn parse_real_sentinel(s: &str) -> Result<(String, f64), String> {
+	let (name, sentinel) = parse_assignment(s)?;
+	let sentinel = f64::from_str(&sentinel).map_err(|_| format!("invalid sentinel '{}'", sentinel))?;
+	Ok((name, sentinel))
+}
+
+fn parse_scope_type(s: &str) -> Result<(String, ScopeType), String> {
+	let (path, scope_type) = parse_assignment(s)?;
+	let scope_type = ScopeType::from_str(&scope_type).map_err(|e| e.to_string())?;
+	Ok((path, scope_type))
+}
+
+fn parse_var_type(s: &str) -> Result<(Option<String>, VarType), String> {
+	let (name, var_type) = match parse_assignment(s) {
+		Ok((name, var_type)) => (Some(name), var_type),
+		Err(_) => (None, String::from(s))
 	};
-	let input_reader = BufReader::new(input);
+	let var_type = VarType::from_str(&var_type).map_err(|e| e.to_string())?;
+	Ok((name, var_type))
+}
+
+fn parse_transform(s: &str) -> Result<(String, String), String> {
+	let (name, transform) = parse_assignment(s)?;
+	match transform.as_str() {
+		"invert" | "reverse" => Ok((name, transform)),
+		_ => Err(format!("invalid transform '{}', expected 'invert' or 'reverse'", transform))
+	}
+}
 
-	let mut output: Box<Write> = match options.output_path {
-		Some(path) => Box::new(File::create(path).expect("Failed to create output file.")),
-		None => Box::new(stdout())
+/// Parses a `--enum`, reading the states from its file.
+fn parse_enum(s: &str) -> Result<(String, Vec<(u64, String)>), String> {
+	let (name, path) = parse_assignment(s)?;
+	let file = std::fs::read_to_string(&path).map_err(|e| format!("can't read '{}': {}", path, e))?;
+	let mut states = Vec::new();
+	for line in file.lines().filter(|line| !line.trim().is_empty()) {
+		let fields: Vec<&str> = line.split_whitespace().collect();
+		match fields[..] {
+			[value, state] if u64::from_str(value).is_ok() => states.push((u64::from_str(value).unwrap(), String::from(state))),
+			_ => return Err(format!("invalid enum state '{}', expected 'value state'", line))
+		}
+	}
+	Ok((name, states))
+}
+
+/// The fields holding the name and value of a change line, none for other lines or formats.
+fn value_fields(options: &Options, fields: &[&str]) -> Option<(usize, usize)> {
+	if !matches!(options.input_format, InputFormat::Log | InputFormat::VcdLike) {
+		return None;
+	}
+	let (name, value) = match options.columns {
+		Some(columns) => (columns.name, columns.value),
+		None if options.field_order == FieldOrder::ValueWidth => (1, 2),
+		None => (1, 3)
 	};
+	if fields.len() <= name.max(value) || (options.columns.is_none() && fields.len() < 4) {
+		return None;
+	}
+	Some((name, value))
+}
 
-	let mut writer = Writer::new(&mut output);
+/// The `strength <signal> <value>` comment of a change logged with a drive strength, such as `St1`,
+/// which VCD values don't have.
+fn strength_comment(change: &ValueChange) -> Option<Directive> {
+	let strength = change.strength?;
+	Some(Directive::Comment(format!("strength {} {}{}", change.signal_name, strength, change.value)))
+}
 
-	let mut value_changes: Vec<ValueChange> = input_reader.lines().filter_map(|line| {
-		ValueChange::from_str(line.unwrap().as_str()).ok()
-	}).collect();
-	value_changes.sort_by_key(|v| v.timestamp);
+/// Replaces the whitespace separated field `index` of the line, keeping the spacing around it so the
+/// columns of the diagnostics still point into the logged line.
+fn replace_field(line: &str, index: usize, with: &str) -> String {
+	let field = line.split_whitespace().nth(index).expect("the line has the field");
+	let start = field.as_ptr() as usize - line.as_ptr() as usize;
+	format!("{}{}{}", &line[..start], with, &line[start + field.len()..])
+}
 
-	let mut id_iter = 0u32..93u32;
-	// maps signal name -> (type, size, id)
-	// TODO: make sure types of veriables don't change (i.e. someone uses 'A' as a scalar, but then later uses it as a real)
-	let mut variables: HashMap<String, (VarType, usize, IdCode)> = HashMap::new();
-
-	for elem in &value_changes {
-		variables.entry(elem.signal_name.clone()).or_insert_with(|| { //TODO: get rid of the clone of every lookup
-			let (sig_type, width) = match elem.value {
-				value_change::Value::Scalar(_) => (VarType::Wire, 1),
-				value_change::Value::BinaryVector{width, ..} => (VarType::Integer, width),
-				value_change::Value::Real(_) => (VarType::Real, 32)
-			};
-			let id = id_iter.next().expect("Input has too many variables, ran out of ids.");
-			(sig_type, width, IdCode::from(id))
-		});
+/// Replaces a `!` value with the default of the signal: its `--assume-initial` value, or x.
+fn resolve_reset<'a>(options: &Options, line: &'a str) -> std::borrow::Cow<'a, str> {
+	let fields: Vec<&str> = line.split_whitespace().collect();
+	match value_fields(options, &fields) {
+		Some((name, value)) if fields[value] == "!" => {
+			let initial = options.assume_initial.iter().rev()
+				.find(|(n, _)| n == fields[name])
+				.map_or("x", |(_, initial)| initial.as_str());
+			replace_field(line, value, initial).into()
+		},
+		_ => line.into()
 	}
+}
 
-	//TODO: nested variable scopes based on name
-	let scope = Scope {
-		scope_type: ScopeType::Module,
-		identifier: String::from("outputs"),
-		// TODO: order alphabetically?
-		children: variables.iter().map(|(name, (var_type, size, code))| {
-			ScopeItem::Var(Var {
-				var_type: *var_type,
-				size: *size as u32,
-				code: *code,
-				reference: name.clone()
-			})
-		}).collect()
+/// Replaces the state name logged as the value of an enumerated signal by its value, in binary.
+fn resolve_state<'a>(options: &Options, line: &'a str) -> std::borrow::Cow<'a, str> {
+	if options.enums.is_empty() {
+		return line.into();
+	}
+	let fields: Vec<&str> = line.split_whitespace().collect();
+	let (name, value) = match value_fields(options, &fields) {
+		Some(fields) => fields,
+		None => return line.into()
 	};
+	let state = options.enums.iter().rev()
+		.find(|(n, _)| n == fields[name])
+		.and_then(|(_, states)| states.iter().find(|(_, state)| state == fields[value]));
+	match state {
+		Some((n, _)) => replace_field(line, value, &format!("{:b}", n)).into(),
+		None => line.into()
+	}
+}
+
+fn parse_radix(s: &str) -> Result<(String, String), String> {
+	let (name, radix) = parse_assignment(s)?;
+	match radix.as_str() {
+		"hex" | "dec" | "bin" => Ok((name, radix)),
+		_ => Err(format!("invalid radix '{}', expected 'hex', 'dec' or 'bin'", radix))
+	}
+}
 
-	let header = Header {
-		comment: None,
-		date: None,
-		version: None,
-		timescale: Some((options.step_size, options.unit)),
-		items: vec![ScopeItem::Scope(scope)]
+fn parse_memory(s: &str) -> Result<usize, String> {
+	let (digits, multiplier) = match s.char_indices().last() {
+		Some((i, 'K')) => (&s[..i], 1 << 10),
+		Some((i, 'M')) => (&s[..i], 1 << 20),
+		Some((i, 'G')) => (&s[..i], 1 << 30),
+		_ => (s, 1)
 	};
+	usize::from_str(digits).ok()
+		.and_then(|n| n.checked_mul(multiplier))
+		.ok_or_else(|| format!("invalid memory limit '{}', expected bytes such as 512M", s))
+}
+
+fn parse_color(s: &str) -> Result<(String, &'static str), String> {
+	let (name, color) = parse_assignment(s)?;
+	match gtkw::COLORS.iter().find(|&&c| c == color) {
+		Some(color) => Ok((name, *color)),
+		None => Err(format!("invalid color '{}', expected one of {}", color, gtkw::COLORS.join(", ")))
+	}
+}
 
-	writer.header(&header).unwrap();
-	writer.timestamp(0).unwrap();
+fn parse_realtime_scale(s: &str) -> Result<f64, String> {
+	match f64::from_str(s) {
+		Ok(scale) if scale > 0.0 && scale.is_finite() => Ok(scale),
+		_ => Err(format!("invalid realtime scale '{}', expected a positive number", s))
+	}
+}
+
+fn parse_pull(s: &str) -> Result<(String, ScalarValue), String> {
+	let (name, value) = parse_assignment(s)?;
+	match value.as_str() {
+		"0" => Ok((name, ScalarValue::V0)),
+		"1" => Ok((name, ScalarValue::V1)),
+		_ => Err(format!("invalid pull value '{}', expected '0' or '1'", value))
+	}
+}
 
-	// TODO: Initial values = x
+/// The kind of output written by a conversion.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum OutputFormat {
+	Vcd,
+	Edges,
+	Deltas,
+	/// The VCD converted to a FST
+	Fst
+}
 
-	// TODO: merge identical timestamps
-	for change in value_changes {
-		writer.timestamp(change.timestamp).unwrap();
-		let (_, _, id) = variables.get(&change.signal_name).unwrap();
-		match change.value {
-			value_change::Value::Scalar(v) => {
-				writer.change_scalar(*id, v).unwrap();
+impl FromStr for OutputFormat {
+	type Err = String;
+	fn from_str(s: &str) -> Result<OutputFormat, String> {
+		match s {
+			"vcd" => Ok(OutputFormat::Vcd),
+			"edges" => Ok(OutputFormat::Edges),
+			"deltas" => Ok(OutputFormat::Deltas),
+			"fst" => Ok(OutputFormat::Fst),
+			_ => Err(format!("invalid output format '{}', expected 'vcd', 'edges', 'deltas' or 'fst'", s))
+		}
+	}
+}
+
+/// What `--timestamp-ranges` does at the end of a run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum RangeEnd {
+	/// Keep the value of the run
+	Hold,
+	/// Change the signal to x, reals keep their value
+	Unknown
+}
+
+impl FromStr for RangeEnd {
+	type Err = String;
+	fn from_str(s: &str) -> Result<RangeEnd, String> {
+		match s {
+			"hold" => Ok(RangeEnd::Hold),
+			"unknown" => Ok(RangeEnd::Unknown),
+			_ => Err(format!("invalid range end '{}', expected 'hold' or 'unknown'", s))
+		}
+	}
+}
+
+/// The changes of a run read with `--timestamp-ranges`, the change at the start and the one at the
+/// end of the range if the signal is unknown past it.
+fn run_changes(options: &Options, change: ValueChange, end: Option<u64>) -> Vec<ValueChange> {
+	let unknown = match (&change.value, end) {
+		(_, None) => None,
+		_ if options.timestamp_ranges != Some(RangeEnd::Unknown) => None,
+		(value_change::Value::Scalar(_), _) => Some(value_change::Value::Scalar(ScalarValue::X)),
+		(value_change::Value::BinaryVector{width, ..}, _) => Some(value_change::Value::BinaryVector{width: *width, value: vec![ScalarValue::X]}),
+		(value_change::Value::Real(_), _) => None
+	};
+	let end = unknown.map(|value| ValueChange { timestamp: end.unwrap(), value, ..change.clone() });
+	std::iter::once(change).chain(end).collect()
+}
+
+/// What `--unknown-marker` does with lines whose value type is an unrecognized marker.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum UnknownMarker {
+	/// Fail the conversion
+	Error,
+	/// Drop the line silently
+	Skip,
+	/// Drop the line with a warning, like other invalid lines
+	Warn
+}
+
+impl FromStr for UnknownMarker {
+	type Err = String;
+	fn from_str(s: &str) -> Result<UnknownMarker, String> {
+		match s {
+			"error" => Ok(UnknownMarker::Error),
+			"skip" => Ok(UnknownMarker::Skip),
+			"warn" => Ok(UnknownMarker::Warn),
+			_ => Err(format!("invalid unknown marker policy '{}', expected 'error', 'skip' or 'warn'", s))
+		}
+	}
+}
+
+/// How `--real-merge` merges the changes of a real signal at the same timestamp.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum RealMerge {
+	Last,
+	Mean
+}
+
+impl FromStr for RealMerge {
+	type Err = String;
+	fn from_str(s: &str) -> Result<RealMerge, String> {
+		match s {
+			"last" => Ok(RealMerge::Last),
+			"mean" => Ok(RealMerge::Mean),
+			_ => Err(format!("invalid real merge '{}', expected 'last' or 'mean'", s))
+		}
+	}
+}
+
+/// What `--real-special` does with infinite and nan real values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum RealSpecial {
+	Drop,
+	Error,
+	Clamp,
+	Pass
+}
+
+impl FromStr for RealSpecial {
+	type Err = String;
+	fn from_str(s: &str) -> Result<RealSpecial, String> {
+		match s {
+			"drop" => Ok(RealSpecial::Drop),
+			"error" => Ok(RealSpecial::Error),
+			"clamp" => Ok(RealSpecial::Clamp),
+			"pass" => Ok(RealSpecial::Pass),
+			_ => Err(format!("invalid real special policy '{}', expected 'drop', 'error', 'clamp' or 'pass'", s))
+		}
+	}
+}
+
+/// The `--order-by` order of the variable declarations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum OrderBy {
+	FirstSeen,
+	Alphabetical
+}
+
+impl FromStr for OrderBy {
+	type Err = String;
+	fn from_str(s: &str) -> Result<OrderBy, String> {
+		match s {
+			"first-seen" => Ok(OrderBy::FirstSeen),
+			"alphabetical" => Ok(OrderBy::Alphabetical),
+			_ => Err(format!("invalid order '{}', expected 'first-seen' or 'alphabetical'", s))
+		}
+	}
+}
+
+// (type, size, id, top scope of a --label log)
+type Variable = (VarType, usize, IdCode, Option<String>);
+
+// maps signal name -> variable
+type Variables = HashMap<String, Variable>;
+
+/// The number of value changes of a chunk of `--external-sort` without a `--chunk-size`.
+const DEFAULT_CHUNK_SIZE: usize = 1_000_000;
+
+/// The timescale unit when neither `-u` nor the header block of the log set one.
+const DEFAULT_UNIT: TimescaleUnit = TimescaleUnit::NS;
+
+/// How often `--watch` checks whether the input was modified.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The first line of a `--dump-parsed` file, as the layout of the value changes changes between versions.
+const PARSED_HEADER: &str = concat!("log2vcd-parsed ", env!("CARGO_PKG_VERSION"), "\n");
+
+fn main() {
+	let options = Options::from_args();
+	logger::init(options.verbose);
+	if options.conformance {
+		match run_conformance(&mut stdout()) {
+			Ok(true) => return,
+			Ok(false) => std::process::exit(1),
+			Err(e) => {
+				eprintln!("error: {}", e);
+				std::process::exit(1);
+			}
+		}
+	}
+	if let (Some(input_dir), Some(output_dir)) = (&options.input_dir, &options.output_dir) {
+		match convert_dir(&options, input_dir, output_dir, &mut stdout()) {
+			Ok(true) => return,
+			Ok(false) => std::process::exit(1),
+			Err(e) => {
+				eprintln!("error: {}", e);
+				std::process::exit(1);
+			}
+		}
+	}
+	// a streamed VCD is still valid when it's interrupted
+	let interrupted = if options.vars_path.is_some() { Some(interrupt::on_sigint()) } else { None };
+
+	if !options.watch {
+		if let Err(e) = run_once(&options, interrupted) {
+			eprintln!("error: {}", e);
+			std::process::exit(1);
+		}
+		return;
+	}
+
+	// failed conversions are reported, the next modification may fix them
+	let path = options.input_path.as_ref().unwrap();
+	let mut modified = match watch::modified(path) {
+		Ok(modified) => modified,
+		Err(e) => {
+			eprintln!("error: {}", e);
+			std::process::exit(1);
+		}
+	};
+	loop {
+		if let Err(e) = run_once(&options, None) {
+			eprintln!("error: {}", e);
+		}
+		modified = match watch::wait_for_change(path, modified, WATCH_INTERVAL, None) {
+			Ok(modified) => modified,
+			Err(e) => {
+				eprintln!("error: {}", e);
+				std::process::exit(1);
+			}
+		};
+	}
+}
+
+/// Converts the cases of the conformance corpus, reporting which of them match their expected
+/// VCD. Returns whether all of them do.
+fn run_conformance(out: &mut dyn Write) -> io::Result<bool> {
+	let mut passed = 0;
+	for case in &conformance::CASES {
+		let args = std::iter::once("log2vcd").chain(case.args.iter().cloned());
+		let options = Options::from_iter_safe(args).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.message))?;
+		let mut output = Vec::new();
+		// the diagnostics of a case are part of its result, not printed
+		let mut diagnostics = Diagnostics::new(DiagnosticsFormat::Json);
+		let failure = match convert(&options, case.log.as_bytes(), &mut output, &mut diagnostics) {
+			Ok(()) => conformance::first_difference(case.vcd, &String::from_utf8_lossy(&output)),
+			Err(e) => Some(e.to_string())
+		};
+		match failure {
+			None => {
+				passed += 1;
+				writeln!(out, "pass {}", case.name)?;
 			},
-			value_change::Value::BinaryVector{value, ..} => {
-				let value: Vec<vcd::Value> = value.iter().map(|el| {
-					let v: vcd::Value = el.clone().into(); // TODO: fix this
-					v
-				}).collect();
-				writer.change_vector(*id, &value[..]).unwrap();
+			Some(reason) => writeln!(out, "FAIL {}: {}", case.name, reason)?
+		}
+	}
+	writeln!(out, "{} of {} conformance cases passed", passed, conformance::CASES.len())?;
+	Ok(passed == conformance::CASES.len())
+}
+
+/// Converts every `*.log` of a directory, in the order of their names, reporting which of them
+/// converted. Returns whether all of them did, the first failure is returned with `--strict`.
+fn convert_dir(options: &Options, input_dir: &Path, output_dir: &Path, out: &mut dyn Write) -> io::Result<bool> {
+	let mut logs = Vec::new();
+	for entry in std::fs::read_dir(input_dir)? {
+		let path = entry?.path();
+		if path.is_file() && path.extension().is_some_and(|extension| extension == "log") {
+			logs.push(path);
+		}
+	}
+	logs.sort();
+	std::fs::create_dir_all(output_dir)?;
+
+	let mut converted = 0;
+	for log in &logs {
+		let name = log.file_name().unwrap().to_string_lossy();
+		let vcd = output_dir.join(log.file_name().unwrap()).with_extension("vcd");
+		match convert_path(options, Some(log), Some(&vcd), None) {
+			Ok(()) => {
+				converted += 1;
+				writeln!(out, "converted {}", name)?;
 			},
-			value_change::Value::Real(v) => {
-				writer.change_real(*id, v).unwrap();
-			}
+			Err(e) if options.strict => return Err(io::Error::new(e.kind(), format!("{}: {}", name, e))),
+			Err(e) => writeln!(out, "FAIL {}: {}", name, e)?
 		}
 	}
+	writeln!(out, "{} of {} logs converted", converted, logs.len())?;
+	Ok(converted == logs.len())
+}
+
+/// Converts the input to the output once.
+fn run_once(options: &Options, interrupted: Option<Arc<AtomicBool>>) -> io::Result<()> {
+	convert_path(options, options.input_path.as_deref(), options.output_path.as_deref(), interrupted)
+}
 
-	output.flush().unwrap();
+/// Converts the input file (or stdin) to the output file (or stdout).
+fn convert_path(options: &Options, input_path: Option<&Path>, output_path: Option<&Path>, interrupted: Option<Arc<AtomicBool>>) -> io::Result<()> {
+	let input: Box<dyn Read + Send> = match input_path {
+		Some(path) => Box::new(File::open(path).map_err(|e| io::Error::new(e.kind(), format!("failed to open input file: {}", e)))?),
+		// the labeled logs or the saved value changes replace stdin
+		None if !options.labels.is_empty() || options.load_parsed_path.is_some() => Box::new(io::empty()),
+		None => Box::new(stdin())
+	};
+	let input: Box<dyn Read> = match interrupted {
+		Some(flag) => Box::new(Interruptible::new(input, flag)),
+		None => Box::new(input)
+	};
+	let input: Box<dyn Read> = if options.progress {
+		let size = input_path.and_then(|path| std::fs::metadata(path).ok()).map(|m| m.len());
+		Box::new(Progress::new(input, size, io::stderr()))
+	}
+	else {
+		input
+	};
+	// piped input has no extension to tell its compression, so it's recognized by its content
+	let input_reader = compress::decompress(BufReader::new(input))?;
+
+	let mut diagnostics = Diagnostics::new(options.diagnostics).with_max_warnings(options.max_warnings).with_profile(options.profile);
+	// the output file is only replaced once the conversion succeeded
+	let result = match output_path {
+		Some(path) => atomic::write_atomically(path, |output| {
+			if options.tee {
+				let mut output = Tee::new(output, stdout());
+				convert(options, input_reader, &mut output, &mut diagnostics).and_then(|_| output.flush())
+			}
+			else {
+				convert(options, input_reader, output, &mut diagnostics)
+			}
+		}),
+		None => {
+			let mut output = stdout();
+			convert(options, input_reader, &mut output, &mut diagnostics).and_then(|_| output.flush())
+		}
+	};
+	let result = result.and_then(|_| match (output_path, options.also_emit) {
+		(Some(path), Some(unit)) => write_rescaled(path, unit),
+		_ => Ok(())
+	});
+	let result = result.and_then(|_| match (output_path, options.window_split) {
+		(Some(path), Some(window)) => write_windows(path, window),
+		_ => Ok(())
+	});
+	diagnostics.finish(io::stderr())?;
+	result
+}
+
+/// The path of the `--also-emit` VCD of the output, with the unit before its extension.
+fn rescaled_path(path: &Path, unit: TimescaleUnit) -> PathBuf {
+	match path.extension() {
+		Some(extension) => path.with_extension(format!("{}.{}", unit, extension.to_string_lossy())),
+		None => path.with_extension(unit.to_string())
+	}
+}
+
+/// Writes the `--also-emit` VCD of the output.
+fn write_rescaled(path: &Path, unit: TimescaleUnit) -> io::Result<()> {
+	let vcd = BufReader::new(File::open(path)?);
+	atomic::write_atomically(rescaled_path(path, unit), |output| rescale::rescale(vcd, output, unit))
+}
+
+/// The path of a `--window-split` VCD of the output, with the index of the window before its extension.
+fn window_path(path: &Path, index: u64) -> PathBuf {
+	match path.extension() {
+		Some(extension) => path.with_extension(format!("{}.{}", index, extension.to_string_lossy())),
+		None => path.with_extension(index.to_string())
+	}
+}
+
+/// Writes the `--window-split` VCDs of the output.
+fn write_windows(path: &Path, window: u64) -> io::Result<()> {
+	if window == 0 {
+		return Err(io::Error::new(io::ErrorKind::InvalidInput, "the windows have to be at least one tick long"));
+	}
+	let vcd = BufReader::new(File::open(path)?);
+	window::split(vcd, window, |index, vcd| atomic::write_atomically(window_path(path, index), |output| output.write_all(vcd))).map(|_| ())
+}
+
+/// Reports that the input didn't contain a single value change, which is an error in strict mode.
+fn no_value_changes(options: &Options, diagnostics: &mut Diagnostics) -> io::Result<()> {
+	if options.strict {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, "no value changes found"));
+	}
+	diagnostics.report(Diagnostic::new("no-value-changes", "no value changes found"));
+	Ok(())
+}
+
+/// Splits the `--strip-prefix` off the start of a line, returns the prefix and the rest of the line.
+fn strip_prefix(options: &Options, line: String) -> (String, String) {
+	match options.strip_prefix.as_ref().and_then(|prefix| prefix.find(&line)) {
+		Some(prefix) => (line[..prefix.end()].to_string(), line[prefix.end()..].to_string()),
+		None => (String::new(), line)
+	}
+}
+
+/// Converts the `--input-time-format` timestamp starting a line, returns None if the line has to be skipped.
+///
+/// Lines that don't start with a timestamp of the format are left as they are, to be reported
+/// by the parser.
+fn wall_clock_line(clock: &mut Option<WallClock>, line: String, line_no: usize, diagnostics: &mut Diagnostics) -> Option<String> {
+	let clock = match clock {
+		Some(clock) => clock,
+		None => return Some(line)
+	};
+	match clock.rewrite(&line) {
+		Ok(line) => Some(line),
+		Err(WallClockError::Unparsed) => Some(line),
+		Err(WallClockError::BeforeFirst) => {
+			diagnostics.stats.lines += 1;
+			diagnostics.stats.drop_line("before-first-timestamp");
+			let message = "timestamp is before the timestamp of the first line";
+			diagnostics.report(Diagnostic { line: Some(line_no), ..Diagnostic::new("before-first-timestamp", message) });
+			None
+		}
+	}
+}
+
+/// Prefixes a line with its `--synthetic-time` timestamp, blank lines don't take a step.
+fn synthetic_time_line(options: &Options, line: String, steps: &mut u64) -> String {
+	match options.synthetic_time {
+		Some(step) if !line.trim().is_empty() => {
+			let timestamp = *steps * step;
+			*steps += 1;
+			format!("#{} {}", timestamp, line)
+		},
+		_ => line
+	}
+}
+
+/// Prefixes the lines of a `--input-format vcd-like` log with the timestamp of the last `#timestamp`
+/// line, returns None for the timestamp lines.
+///
+/// Lines before the first timestamp, and the lines that already have one, are left as they are.
+fn vcd_like_line(options: &Options, line: String, timestamp: &mut Option<String>) -> Option<String> {
+	if options.input_format != InputFormat::VcdLike {
+		return Some(line);
+	}
+	let mut fields = line.split_whitespace();
+	match (fields.next(), fields.next()) {
+		(Some(t), None) if t.starts_with('#') => {
+			*timestamp = Some(String::from(t));
+			None
+		},
+		(Some(first), Some(_)) if !first.starts_with('#') && first != "BASE" => match timestamp {
+			Some(t) => Some(format!("{} {}", t, line.trim_start())),
+			None => Some(line)
+		},
+		_ => Some(line)
+	}
+}
+
+/// Skips a line longer than `--max-line-len`, an error with `--strict`.
+fn skip_long_line(options: &Options, error: &io::Error, line_no: usize, diagnostics: &mut Diagnostics) -> io::Result<()> {
+	let message = error.to_string();
+	if options.strict {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line_no, message)));
+	}
+	diagnostics.stats.lines += 1;
+	diagnostics.stats.drop_line("line-too-long");
+	diagnostics.report(Diagnostic { line: Some(line_no), ..Diagnostic::new("line-too-long", message) });
+	Ok(())
+}
+
+/// Reports a non-blank line identical to the `last` one with `--report-dup-lines`, counting it in
+/// the stats.
+fn check_duplicate_line(options: &Options, line: &str, line_no: usize, last: &mut Option<String>, diagnostics: &mut Diagnostics) {
+	if !options.report_dup_lines {
+		return;
+	}
+	let count = diagnostics.stats.duplicate_lines.get_or_insert(0);
+	if line.trim().is_empty() {
+		return;
+	}
+	if last.as_deref() == Some(line) {
+		*count += 1;
+		diagnostics.report(Diagnostic { line: Some(line_no), ..Diagnostic::new("duplicate-line", "line is identical to the line before it, it may be logged twice") });
+	}
+	else {
+		*last = Some(String::from(line));
+	}
+}
+
+/// Parses a line of the log, reporting it if it isn't valid, which is an error with `--strict`.
+///
+/// Blank lines and lines below `--min-level` are skipped silently. A JSON line only has the changes of the fields that differ
+/// from their last value in `json_values`. `prefix` is the part of the line `--strip-prefix` split off, the columns of
+/// the diagnostics count it so they point into the line as it was read.
+fn parse_line(options: &Options, prefix: &str, line: &str, line_no: usize, parse_options: &ParseOptions, json_values: &mut HashMap<String, value_change::Value>, diagnostics: &mut Diagnostics) -> io::Result<Vec<ValueChange>> {
+	if line.trim().is_empty() {
+		return Ok(Vec::new());
+	}
+	if parse_options.input_format == InputFormat::Leveled {
+		let below = |(level, _)| options.min_level.is_some_and(|min| level < min);
+		if value_change::split_level(line).is_some_and(below) {
+			return Ok(Vec::new());
+		}
+	}
+	diagnostics.stats.lines += 1;
+	let parsed = match parse_options.input_format {
+		InputFormat::JsonLines => log2vcd::parse_line_with(line, line_no, parse_options).map(|changes| {
+			changes.into_iter()
+				.filter(|change| json_values.insert(change.signal_name.clone(), change.value.clone()).as_ref() != Some(&change.value))
+				.collect()
+		}),
+		_ if options.timestamp_ranges.is_some() => {
+			ValueChange::parse_range(line, parse_options)
+				.map(|(change, end)| run_changes(options, change, end))
+				.map_err(|error| LineError::new(line_no, line, error))
+		},
+		_ => log2vcd::parse_line_with(line, line_no, parse_options)
+	};
+	let parsed = parsed.map_err(|e| match prefix {
+		"" => e,
+		prefix => LineError { column: e.column + prefix.chars().count(), text: format!("{}{}", prefix, e.text), ..e }
+	});
+	match parsed {
+		Ok(changes) => {
+			diagnostics.stats.value_changes += changes.len();
+			Ok(changes)
+		},
+		Err(ref e) if e.error == ParseValueChangeError::UnknownMarker && options.unknown_marker == UnknownMarker::Error => {
+			let message = format!("line {}, column {}: {}", line_no, e.column, e.error);
+			Err(io::Error::new(io::ErrorKind::InvalidData, message))
+		},
+		Err(ref e) if options.strict && (e.error != ParseValueChangeError::UnknownMarker || options.unknown_marker != UnknownMarker::Skip) => {
+			let message = format!("line {}, column {}: {} [{}]: '{}'", line_no, e.column, e.error, e.error.code(), e.text);
+			Err(io::Error::new(io::ErrorKind::InvalidData, message))
+		},
+		Err(e) => {
+			trace!("dropped line {}: {}", line_no, e.error);
+			diagnostics.stats.drop_line(e.error.code());
+			// an explained line is reported, even the unknown markers that would be ignored
+			let explain = options.explain.is_some_and(|n| diagnostics.stats.dropped.values().sum::<usize>() <= n);
+			if explain || e.error != ParseValueChangeError::UnknownMarker || options.unknown_marker == UnknownMarker::Warn {
+				let explanation = if explain { Some(explain::explain(&e)) } else { None };
+				diagnostics.report(Diagnostic { explanation, ..Diagnostic::parse_error(&e) });
+			}
+			Ok(Vec::new())
+		}
+	}
+}
+
+/// Reports the first value change of `--preserve-order` whose timestamp is before the one of the
+/// change preceding it, which is an error in strict mode.
+fn check_order(options: &Options, value_changes: &[ValueChange], diagnostics: &mut Diagnostics) -> io::Result<()> {
+	let decrease = value_changes.windows(2).find(|pair| pair[1].timestamp < pair[0].timestamp);
+	if let Some(pair) = decrease {
+		let message = format!("timestamp {} of '{}' is before the previous timestamp {}", pair[1].timestamp, pair[1].signal_name, pair[0].timestamp);
+		if options.strict {
+			return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+		}
+		diagnostics.report(Diagnostic::new("unordered-timestamp", message));
+	}
+	Ok(())
+}
+
+/// Merges the changes of each real signal at the same timestamp into the first of them.
+///
+/// `value_changes` must already be sorted by timestamp.
+fn merge_reals(value_changes: &mut Vec<ValueChange>, merge: RealMerge) {
+	// signal -> (index of the merged change, sum, count), for the current timestamp
+	let mut merged: HashMap<String, (usize, f64, usize)> = HashMap::new();
+	let mut last = None;
+	let mut keep = vec![true; value_changes.len()];
+	for i in 0..value_changes.len() {
+		let change = &value_changes[i];
+		if last != Some(change.timestamp) {
+			merged.clear();
+			last = Some(change.timestamp);
+		}
+		let v = match change.value {
+			value_change::Value::Real(v) => v,
+			_ => continue
+		};
+		match merged.get_mut(&change.signal_name) {
+			Some((first, sum, count)) => {
+				*sum += v;
+				*count += 1;
+				keep[i] = false;
+				let v = match merge {
+					RealMerge::Last => v,
+					RealMerge::Mean => *sum / *count as f64
+				};
+				value_changes[*first].value = value_change::Value::Real(v);
+			},
+			None => { merged.insert(change.signal_name.clone(), (i, v, 1)); }
+		}
+	}
+	let mut keep = keep.into_iter();
+	value_changes.retain(|_| keep.next().unwrap());
+}
+
+/// Returns the bus and the index of a signal logged as a bit `name.<index>` of a `--merge-bits` bus.
+fn bus_bit<'a>(options: &Options, name: &'a str) -> Option<(&'a str, usize)> {
+	let (bus, index) = name.rsplit_once('.')?;
+	let index = usize::from_str(index).ok()?;
+	if options.merge_bits.iter().any(|pattern| pattern.is_match(bus)) { Some((bus, index)) } else { None }
+}
+
+/// Merges the scalar changes of the bits of `--merge-bits` buses into vector changes of the
+/// buses, one per timestamp the bits of a bus change at. Bits that weren't logged yet are `x`.
+///
+/// `value_changes` must already be sorted by timestamp.
+fn merge_bits(options: &Options, value_changes: Vec<ValueChange>) -> Vec<ValueChange> {
+	let mut widths: HashMap<String, usize> = HashMap::new();
+	for change in &value_changes {
+		if let (Some((bus, index)), value_change::Value::Scalar(_)) = (bus_bit(options, &change.signal_name), &change.value) {
+			let width = widths.entry(String::from(bus)).or_insert(0);
+			*width = (*width).max(index + 1);
+		}
+	}
+
+	let mut bits: HashMap<String, Vec<ScalarValue>> = widths.iter()
+		.map(|(bus, width)| (bus.clone(), vec![ScalarValue::X; *width]))
+		.collect();
+	// buses whose bits changed at the current timestamp, in the order they first did
+	let mut changed: Vec<String> = Vec::new();
+	let mut merged = Vec::with_capacity(value_changes.len());
+	let mut value_changes = value_changes.into_iter().peekable();
+	while let Some(change) = value_changes.next() {
+		let timestamp = change.timestamp;
+		match (bus_bit(options, &change.signal_name), &change.value) {
+			(Some((bus, index)), value_change::Value::Scalar(bit)) => {
+				let value = bits.get_mut(bus).unwrap();
+				let width = value.len();
+				value[width - 1 - index] = bit.clone();
+				if !changed.iter().any(|name| name == bus) {
+					changed.push(String::from(bus));
+				}
+			},
+			_ => merged.push(change)
+		}
+		if value_changes.peek().map_or(true, |next| next.timestamp != timestamp) {
+			for bus in changed.drain(..) {
+				let value = bits[&bus].clone();
+				merged.push(ValueChange {
+					timestamp,
+					value: value_change::Value::BinaryVector{width: value.len(), value},
+					signal_name: bus,
+					line: None,
+					scope: None,
+					strength: None
+				});
+			}
+		}
+	}
+	merged
+}
+
+/// Keeps only the last change of each signal in every bucket of `interval` ticks.
+///
+/// `value_changes` must already be sorted by timestamp.
+fn decimate(value_changes: &mut Vec<ValueChange>, interval: u64) {
+	let mut seen: HashSet<(&str, u64)> = HashSet::new();
+	let mut keep: Vec<bool> = value_changes.iter().rev()
+		.map(|change| seen.insert((&change.signal_name, change.timestamp / interval)))
+		.collect();
+	keep.reverse();
+	let mut keep = keep.into_iter();
+	value_changes.retain(|_| keep.next().unwrap());
+}
+
+/// Replaces the value changes with the value every signal holds at each multiple of `interval`
+/// ticks, up to the first one at or after the last change. Signals are left out until their first
+/// change.
+///
+/// `value_changes` must already be sorted by timestamp.
+fn resample(value_changes: Vec<ValueChange>, interval: u64) -> Vec<ValueChange> {
+	let last = match value_changes.last() {
+		Some(change) => change.timestamp,
+		None => return value_changes
+	};
+	// the last change of each signal, in the order of their first change
+	let mut held: Vec<ValueChange> = Vec::new();
+	let mut index: HashMap<String, usize> = HashMap::new();
+	let mut resampled = Vec::new();
+	let mut changes = value_changes.into_iter().peekable();
+	let mut grid = 0;
+	loop {
+		while let Some(change) = changes.next_if(|change| change.timestamp <= grid) {
+			match index.get(&change.signal_name) {
+				Some(&i) => held[i] = change,
+				None => {
+					index.insert(change.signal_name.clone(), held.len());
+					held.push(change);
+				}
+			}
+		}
+		resampled.extend(held.iter().map(|change| ValueChange { timestamp: grid, ..change.clone() }));
+		if grid >= last {
+			return resampled;
+		}
+		grid = grid.saturating_add(interval);
+	}
+}
+
+/// Drops every value change that doesn't belong to one of the first `n` distinct timestamps.
+///
+/// `value_changes` must already be sorted by timestamp.
+fn truncate_to_timestamps(value_changes: &mut Vec<ValueChange>, n: usize) {
+	let mut distinct = 0;
+	let mut last = None;
+	let end = value_changes.iter().position(|v| {
+		if last != Some(v.timestamp) {
+			distinct += 1;
+			last = Some(v.timestamp);
+		}
+		distinct > n
+	});
+	if let Some(end) = end {
+		value_changes.truncate(end);
+	}
+}
+
+/// Hands out the ids of new variables, honoring the ids pinned with `--id-map`.
+struct IdAllocator {
+	pinned: HashMap<String, IdCode>,
+	used: HashSet<IdCode>,
+	next: IdCode
+}
+
+impl IdAllocator {
+	fn new(pinned: HashMap<String, IdCode>) -> IdAllocator {
+		let used = pinned.values().cloned().collect();
+		IdAllocator { pinned, used, next: IdCode::FIRST }
+	}
+
+	fn id_for(&mut self, name: &str) -> IdCode {
+		if let Some(id) = self.pinned.get(name) {
+			return *id;
+		}
+		while self.used.contains(&self.next) {
+			self.next = self.next.next();
+		}
+		let id = self.next;
+		self.next = self.next.next();
+		id
+	}
+
+	/// Assigns the next id to `name`, unless it is already pinned.
+	fn reserve(&mut self, name: &str) {
+		if !self.pinned.contains_key(name) {
+			let id = self.id_for(name);
+			self.used.insert(id);
+			self.pinned.insert(String::from(name), id);
+		}
+	}
+}
+
+/// Reserves the ids of the signals in the declaration order of the `--order` file, followed by the
+/// unlisted signals in alphabetical order.
+fn reserve_order<R: BufRead>(reader: R, value_changes: &[ValueChange], ids: &mut IdAllocator) -> io::Result<()> {
+	let mut names: HashSet<&str> = value_changes.iter().map(|change| change.signal_name.as_str()).collect();
+	for line in reader.lines() {
+		let line = line?;
+		let name = line.trim();
+		if names.remove(name) {
+			ids.reserve(name);
+		}
+	}
+	let mut unlisted: Vec<&str> = names.into_iter().collect();
+	unlisted.sort();
+	for name in unlisted {
+		ids.reserve(name);
+	}
+	Ok(())
+}
+
+/// Checks the discovered variables against the `--expect-signals` file.
+fn check_expected_signals(options: &Options, variables: &Variables) -> io::Result<()> {
+	let path = match options.expect_signals_path {
+		Some(ref path) => path,
+		None => return Ok(())
+	};
+	let mut expected = HashSet::new();
+	for line in BufReader::new(File::open(path)?).lines() {
+		let line = line?;
+		if !line.trim().is_empty() {
+			expected.insert(String::from(line.trim()));
+		}
+	}
+	let mut unexpected: Vec<&str> = variables.keys().filter(|name| !expected.contains(*name)).map(String::as_str).collect();
+	unexpected.sort();
+	if !unexpected.is_empty() {
+		let message = format!("the log has signals that aren't expected: {}", unexpected.join(", "));
+		return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+	}
+	let mut missing: Vec<&str> = expected.iter().filter(|name| !variables.contains_key(*name)).map(String::as_str).collect();
+	missing.sort();
+	if options.expect_exact && !missing.is_empty() {
+		let message = format!("the log is missing expected signals: {}", missing.join(", "));
+		return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+	}
+	Ok(())
+}
+
+/// Reads an id map file, each non-empty line pins the id of a signal as `name id`.
+fn read_id_map<R: BufRead>(reader: R) -> io::Result<HashMap<String, IdCode>> {
+	let invalid = |line: &str| io::Error::new(io::ErrorKind::InvalidData, format!("invalid id mapping: '{}'", line));
+
+	let mut ids = HashMap::new();
+	let mut used = HashSet::new();
+	for line in reader.lines() {
+		let line = line?;
+		let fields: Vec<&str> = line.split_whitespace().collect();
+		if fields.is_empty() {
+			continue;
+		}
+		if fields.len() != 2 {
+			return Err(invalid(&line));
+		}
+		let id = IdCode::from_str(fields[1]).map_err(|_| invalid(&line))?;
+		if !used.insert(id) {
+			return Err(io::Error::new(io::ErrorKind::InvalidData, format!("id '{}' is pinned more than once", id)));
+		}
+		ids.insert(String::from(fields[0]), id);
+	}
+	Ok(ids)
+}
+
+/// Reads a variable declaration file.
+///
+/// Each non-empty line declares one variable as `name type width`, where type is a vcd
+/// variable type such as `wire`, `integer` or `real`. Ids are assigned in declaration order.
+fn read_vars<R: BufRead>(reader: R, ids: &mut IdAllocator) -> io::Result<Variables> {
+	let invalid = |line: &str| io::Error::new(io::ErrorKind::InvalidData, format!("invalid variable declaration: '{}'", line));
+
+	let mut variables = Variables::new();
+	for (i, line) in reader.lines().enumerate() {
+		let line = line?;
+		let fields: Vec<&str> = line.split_whitespace().collect();
+		if fields.is_empty() {
+			continue;
+		}
+		if fields.len() != 3 {
+			return Err(invalid(&line));
+		}
+		let var_type = VarType::from_str(fields[1]).map_err(|_| invalid(&line))?;
+		let width = usize::from_str(fields[2]).map_err(|_| invalid(&line))?;
+		if variables.contains_key(fields[0]) {
+			let message = format!("line {}: variable '{}' is declared twice", i + 1, fields[0]);
+			return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+		}
+		let id = ids.id_for(fields[0]);
+		variables.insert(String::from(fields[0]), (var_type, width, id, None));
+	}
+	Ok(variables)
+}
+
+/// Returns the type configured for the scope at `path`, scopes are modules by default.
+fn scope_type(options: &Options, path: &str) -> ScopeType {
+	options.scope_types.iter()
+		.rev()
+		.find(|(p, _)| p == path)
+		.map_or(ScopeType::Module, |(_, scope_type)| *scope_type)
+}
+
+/// Returns the type a variable is declared with, `--var-type` if compatible with the inferred one.
+fn var_type(options: &Options, name: &str, inferred: VarType) -> io::Result<VarType> {
+	let forced = options.var_types.iter().rev().find(|(n, _)| n.as_deref().map_or(true, |n| n == name));
+	match forced {
+		Some((_, var_type)) if (*var_type == VarType::Real) != (inferred == VarType::Real) => {
+			let message = format!("signal '{}' can't be declared {} rather than {}", name, var_type, inferred);
+			Err(io::Error::new(io::ErrorKind::InvalidInput, message))
+		},
+		Some((_, var_type)) => Ok(*var_type),
+		None => Ok(inferred)
+	}
+}
+
+/// Returns the variables ordered by id, which is the order they were registered in.
+fn sorted_by_id(variables: &Variables) -> Vec<(&String, &Variable)> {
+	let mut sorted: Vec<(&String, &Variable)> = variables.iter().collect();
+	sorted.sort_by_key(|(_, (_, _, id, _))| *id);
+	sorted
+}
+
+/// Adds a variable to the scope at `path` below `scope`, creating the scopes that don't exist yet.
+///
+/// `scope_path` is the dot separated path of `scope` itself, used to look up the types of new scopes.
+fn insert_var(options: &Options, scope: &mut Scope, scope_path: &str, path: &[&str], var: Var) {
+	let (identifier, rest) = match path.split_first() {
+		Some(split) => split,
+		None => {
+			scope.children.push(ScopeItem::Var(var));
+			return;
+		}
+	};
+
+	let child_path = format!("{}.{}", scope_path, identifier);
+	let existing = scope.children.iter().position(|item| match item {
+		ScopeItem::Scope(s) => s.identifier == *identifier,
+		_ => false
+	});
+	let index = existing.unwrap_or_else(|| {
+		scope.children.push(ScopeItem::Scope(Scope {
+			scope_type: scope_type(options, &child_path),
+			identifier: String::from(*identifier),
+			children: Vec::new()
+		}));
+		scope.children.len() - 1
+	});
+	if let ScopeItem::Scope(ref mut child) = scope.children[index] {
+		insert_var(options, child, &child_path, rest, var);
+	}
+}
+
+/// Writes the `--gtkw` save file for the header, the hints of a variable are the ones of its full
+/// signal name.
+fn write_save_file(options: &Options, variables: &Variables, header: &Header) -> io::Result<()> {
+	let path = match options.gtkw_path {
+		Some(ref path) => path,
+		None => return Ok(())
+	};
+	let names: HashMap<IdCode, &str> = variables.iter().map(|(name, (_, _, code, _))| (*code, name.as_str())).collect();
+	let hints = |var: &Var| {
+		let name = names[&var.code];
+		(
+			options.radixes.iter().rev().find(|(n, _)| n == name).map(|(_, r)| r.clone()),
+			options.colors.iter().rev().find(|(n, _)| n == name).map(|(_, c)| *c)
+		)
+	};
+	let out = io::BufWriter::new(File::create(path)?);
+	gtkw::write(out, options.output_path.as_deref(), header, &hints)
+}
+
+/// Returns the top scope of a variable and its name within it: the label of the log it was read
+/// from, or `outputs`.
+fn top_scope<'a>(name: &'a str, label: Option<&'a str>) -> (&'a str, &'a str) {
+	match label.and_then(|label| Some((label, labeled_name(name, label)?))) {
+		Some((label, name)) => (label, name),
+		None => ("outputs", name)
+	}
+}
+
+/// The name of a signal of a `--label` log within the top scope of the label, the signal names
+/// of the log are qualified with the label. None if the name was renamed out of the scope, by an
+/// alias for example.
+fn labeled_name<'a>(name: &'a str, label: &str) -> Option<&'a str> {
+	name.strip_prefix(label)?.strip_prefix('.')
+}
+
+/// Returns the path of the scope (below the top scope) a variable is declared in, and its
+/// reference within that scope. Names with an empty scope or reference, such as `.a` or `a.`,
+/// are invalid.
+fn var_path<'a>(options: &Options, name: &'a str) -> io::Result<(Vec<&'a str>, &'a str)> {
+	let mut path = Vec::new();
+	let mut reference = name;
+	if !options.flat && !options.no_nest_patterns.iter().any(|pattern| pattern.is_match(name)) {
+		path = name.split(options.scope_separator.as_deref().unwrap_or(".")).collect();
+		if path.contains(&"") {
+			let message = format!("signal '{}' has an empty scope name, remove the separator or pass --flat", name);
+			return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+		}
+		reference = path.pop().unwrap();
+	}
+	path.extend(scope_path(reference));
+	Ok((path, reference))
+}
+
+/// Returns the top scopes of the variables, with any nested scopes of their names. Variables
+/// are declared in the order of their ids.
+///
+/// The scopes of dotted names are nested in a top scope rather than declared at the root, so that
+/// the signals of every `--label` log stay apart in the scope of their label, and an unlabeled
+/// signal, dotted or not, is in `outputs`.
+fn scopes(options: &Options, variables: &Variables) -> io::Result<Vec<ScopeItem>> {
+	let mut scopes: Vec<Scope> = Vec::new();
+	for (name, (var_type, size, code, label)) in sorted_by_id(variables) {
+		let var_type = self::var_type(options, name, *var_type)?;
+		let (top, name) = top_scope(name, label.as_deref());
+		let (path, reference) = var_path(options, name)?;
+		let var = Var {
+			var_type,
+			size: *size as u32,
+			code: *code,
+			reference: String::from(reference)
+		};
+		let index = scopes.iter().position(|s| s.identifier == top).unwrap_or_else(|| {
+			scopes.push(Scope { scope_type: scope_type(options, top), identifier: String::from(top), children: Vec::new() });
+			scopes.len() - 1
+		});
+		insert_var(options, &mut scopes[index], top, &path, var);
+	}
+	if scopes.is_empty() {
+		scopes.push(Scope { scope_type: scope_type(options, "outputs"), identifier: String::from("outputs"), children: Vec::new() });
+	}
+	Ok(scopes.into_iter().map(ScopeItem::Scope).collect())
+}
+
+fn header(options: &Options, variables: &Variables, metadata: &Metadata) -> io::Result<Header> {
+	let comments: Vec<String> = metadata.comment.iter().cloned()
+		.chain(options.radixes.iter().map(|(name, radix)| format!("radix {} {}", name, radix)))
+		.chain(options.real_scales.iter().map(|(name, factor)| format!("real-scale {} {}", name, factor)))
+		.chain(options.enums.iter().map(|(name, states)| {
+			let states: Vec<String> = states.iter().map(|(value, state)| format!("{}={}", value, state)).collect();
+			format!("enum {} {}", name, states.join(" "))
+		}))
+		.collect();
+
+	Ok(Header {
+		comment: if comments.is_empty() { None } else { Some(comments.join("\n    ")) },
+		date: metadata.date.clone(),
+		version: metadata.version.clone(),
+		timescale: Some(options.timescale(metadata)),
+		items: scopes(options, variables)?
+	})
+}
+
+/// Warns about falling back to the default timescale unit.
+fn check_timescale(options: &Options, metadata: &Metadata, diagnostics: &mut Diagnostics) {
+	if options.unit.is_none() && metadata.timescale.is_none() {
+		let message = format!("no timescale unit given, using {} (pass -u to set one)", DEFAULT_UNIT);
+		diagnostics.report(Diagnostic::new("default-timescale", message));
+	}
+}
+
+/// Reads the header block of `key: value` lines at the top of a log, up to a blank line.
+fn read_metadata<I>(options: &Options, lines: &mut Peekable<I>, metadata: &mut Metadata, diagnostics: &mut Diagnostics) -> io::Result<()>
+	where I: Iterator<Item = (usize, io::Result<String>)>
+{
+	let is_field = |(_, line): &(usize, io::Result<String>)| line.as_ref().ok().and_then(|line| metadata::parse_field(line)).is_some();
+	let mut block = false;
+	while let Some((i, line)) = lines.next_if(is_field) {
+		block = true;
+		let line = line?;
+		let (key, value) = metadata::parse_field(&line).unwrap();
+		if let Err(message) = metadata.set(key, value) {
+			if options.strict {
+				return Err(io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", i + 1, message)));
+			}
+			let mut diagnostic = Diagnostic::new("invalid-header-field", message);
+			diagnostic.line = Some(i + 1);
+			diagnostics.report(diagnostic);
+		}
+	}
+	if block {
+		lines.next_if(|(_, line)| line.as_ref().is_ok_and(|line| line.trim().is_empty()));
+	}
+	Ok(())
+}
+
+/// Applies the encoding options to a parsed value before it is registered and written.
+fn encode(options: &Options, value: value_change::Value) -> value_change::Value {
+	match value {
+		value_change::Value::Real(v) if options.real_as_fixed.is_some() => {
+			value_change::Value::fixed_point(v, options.real_as_fixed.unwrap(), options.fixed_width)
+		},
+		value_change::Value::Real(v) if options.real_precision.is_some() => {
+			// formatting rounds to the closest decimal, which scaling by a power of ten doesn't always
+			let rounded = format!("{:.*}", options.real_precision.unwrap(), v);
+			value_change::Value::Real(f64::from_str(&rounded).unwrap())
+		},
+		value_change::Value::BinaryVector{width, value} if options.min_width.is_some_and(|min| width < min) => {
+			// the value is padded to its own width first, so an `x` vector stays `x` up to there
+			let min = options.min_width.unwrap();
+			let mut extended = vec![value_change::ScalarValue::V0; min - width];
+			extended.extend(value_change::left_extend(&value, width));
+			value_change::Value::BinaryVector{width: min, value: extended}
+		},
+		value => value
+	}
+}
+
+/// Replaces the high impedance bits of a signal with a `--pull` by its pull value.
+fn pull(options: &Options, name: &str, value: value_change::Value) -> value_change::Value {
+	let to = match options.pulls.iter().rev().find(|(n, _)| n == name) {
+		Some((_, to)) => to,
+		None => return value
+	};
+	let pull_bit = |bit: ScalarValue| if bit == ScalarValue::Z { to.clone() } else { bit };
+	match value {
+		value_change::Value::Scalar(bit) => value_change::Value::Scalar(pull_bit(bit)),
+		value_change::Value::BinaryVector{width, value} => {
+			value_change::Value::BinaryVector{width, value: value.into_iter().map(pull_bit).collect()}
+		},
+		value => value
+	}
+}
+
+/// Splits a vector change of `name` into scalar changes of each of its bits, `name[0]` being
+/// the least significant bit.
+fn split_vector(change: ValueChange) -> Vec<ValueChange> {
+	match change.value {
+		value_change::Value::BinaryVector{width, ref value} => {
+			let bits = value_change::left_extend(value, width);
+			bits.into_iter().rev().enumerate().map(|(i, bit)| ValueChange {
+				timestamp: change.timestamp,
+				signal_name: format!("{}[{}]", change.signal_name, i),
+				value: value_change::Value::Scalar(bit),
+				line: change.line,
+				scope: change.scope.clone(),
+				strength: None
+			}).collect()
+		},
+		_ => vec![change]
+	}
+}
+
+/// Encodes the real values of a signal with a `--real-scale` as integer vectors of its scale.
+fn scale_real(options: &Options, name: &str, value: value_change::Value) -> value_change::Value {
+	let factor = options.real_scales.iter().rev().find(|(n, _)| n == name).map(|(_, factor)| *factor);
+	match (value, factor) {
+		(value_change::Value::Real(v), Some(factor)) => value_change::Value::from_integer((v * factor).round() as i64, options.fixed_width),
+		(value, _) => value
+	}
+}
+
+/// Whether a value is the `--real-sentinel` of a signal.
+fn is_sentinel(options: &Options, name: &str, value: &value_change::Value) -> bool {
+	match value {
+		value_change::Value::Real(v) => options.real_sentinels.iter()
+			.any(|(n, sentinel)| n == name && (v == sentinel || (v.is_nan() && sentinel.is_nan()))),
+		_ => false
+	}
+}
+
+/// Turns a parsed value change into the value changes that get registered and written, none
+/// for a `--real-sentinel`.
+fn prepare(options: &Options, change: ValueChange) -> Vec<ValueChange> {
+	let signal_name = String::from(options.resolve_alias(&change.signal_name));
+	if is_sentinel(options, &signal_name, &change.value) {
+		return Vec::new();
+	}
+	let value = scale_real(options, &signal_name, change.value);
+	let mut change = ValueChange {
+		signal_name,
+		value: encode(options, value),
+		..change
+	};
+	for (name, transform) in &options.transforms {
+		match transform.as_str() {
+			_ if *name != change.signal_name => {},
+			"invert" => convert::invert(&mut change),
+			_ => convert::reverse_bits(&mut change)
+		}
+	}
+	if options.split_vectors {
+		split_vector(change)
+	}
+	else {
+		vec![change]
+	}
+}
+
+/// The `--normalize-names` form of a signal name.
+fn normalized_name(name: &str) -> String {
+	let name: String = name.chars()
+		.filter_map(|c| match c {
+			c if c.is_whitespace() => Some('_'),
+			c if c.is_ascii_alphanumeric() || "_.[]$".contains(c) => Some(c),
+			_ => None
+		})
+		.collect();
+	if name.is_empty() { String::from("_") } else { name }
+}
+
+/// Renames the signals to their normalized names, reporting every renamed signal and the signals
+/// that end up with the same name.
+fn normalize_names(value_changes: &mut [ValueChange], diagnostics: &mut Diagnostics) {
+	let mut renamed: HashMap<String, String> = HashMap::new();
+	let mut originals: HashMap<String, String> = HashMap::new();
+	for change in value_changes.iter_mut() {
+		if !renamed.contains_key(&change.signal_name) {
+			let normalized = normalized_name(&change.signal_name);
+			if normalized != change.signal_name {
+				let message = format!("signal '{}' is renamed to '{}'", change.signal_name, normalized);
+				diagnostics.report(Diagnostic::new("renamed-signal", message));
+			}
+			match originals.get(&normalized) {
+				Some(original) if *original != change.signal_name => {
+					let message = format!("signals '{}' and '{}' are both normalized to '{}'", original, change.signal_name, normalized);
+					diagnostics.report(Diagnostic::new("name-collision", message));
+				},
+				Some(_) => {},
+				None => { originals.insert(normalized.clone(), change.signal_name.clone()); }
+			}
+			renamed.insert(change.signal_name.clone(), normalized);
+		}
+		change.signal_name = renamed[&change.signal_name].clone();
+	}
+}
+
+/// Shortens a vector that is entirely `x` or entirely `z` to a single bit, which VCD extends to the
+/// full width.
+fn compact(value: value_change::Value) -> value_change::Value {
+	match value {
+		value_change::Value::BinaryVector{width, value} => {
+			let uniform = value.first()
+				.filter(|first| (**first == ScalarValue::X || **first == ScalarValue::Z) && value.iter().all(|v| v == *first))
+				.cloned();
+			match uniform {
+				Some(bit) if value.len() == width => value_change::Value::BinaryVector{width, value: vec![bit]},
+				_ => value_change::Value::BinaryVector{width, value}
+			}
+		},
+		value => value
+	}
+}
+
+/// A line of the log that isn't a value change, but is written to the VCD.
+enum Directive {
+	/// A timed comment, `#timestamp $comment text`
+	Comment(String),
+	/// A raw VCD value change of `--vcd-passthrough`, along with its line number
+	Change(usize, IdCode, value_change::Value)
+}
+
+type Directives = Vec<(u64, Directive)>;
+
+/// Parses the directives of a log line, keeping track of `time`, the time of the last line with a
+/// timestamp.
+fn parse_directive(options: &Options, line: &str, line_no: usize, parse_options: &ParseOptions, base: i128, time: &mut u64) -> io::Result<Option<Directive>> {
+	if let Some((timestamp, text)) = value_change::parse_comment(line, parse_options) {
+		*time = rebase(base, timestamp, line_no)?;
+		return Ok(Some(Directive::Comment(metadata::escape_end(&text))));
+	}
+	if !options.vcd_passthrough {
+		return Ok(None);
+	}
+	if let Some(timestamp) = value_change::parse_vcd_timestamp(line) {
+		*time = rebase(base, timestamp, line_no)?;
+		return Ok(None);
+	}
+	Ok(value_change::parse_vcd_change(line).map(|(id, value)| Directive::Change(line_no, id, value)))
+}
+
+/// Adds the `BASE` of the lines after it to a timestamp, which fails if the result is before time zero
+/// or past the last timestamp.
+fn rebase(base: i128, timestamp: u64, line_no: usize) -> io::Result<u64> {
+	let rebased = base + i128::from(timestamp);
+	if rebased < 0 {
+		let message = format!("line {}: timestamp {} with base {} is before time zero, which a VCD can't represent", line_no, timestamp, base);
+		return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+	}
+	u64::try_from(rebased).map_err(|_| {
+		let message = format!("line {}: timestamp {} with base {} is past the last timestamp a VCD can represent", line_no, timestamp, base);
+		io::Error::new(io::ErrorKind::InvalidData, message)
+	})
+}
+
+/// Writes the value changes of a conversion, keeping track of the last value written to each variable.
+struct ChangeWriter<'a> {
+	options: &'a Options,
+	last_values: HashMap<IdCode, value_change::Value>,
+	/// The latest timestamp written so far
+	end: u64,
+	/// The last timestamp written, which `--canonical` doesn't write again
+	time: Option<u64>,
+	/// The value changes written so far, only kept for `--self-check`
+	written: Vec<self_check::Written>,
+	/// The number of value changes written so far
+	emitted: usize,
+	/// The offsets of the timestamps written so far, only kept for `--time-index`
+	index: Option<TimeIndex>,
+	pacer: Option<Pacer>
+}
+
+impl<'a> ChangeWriter<'a> {
+	fn new<W: Write>(options: &'a Options, timescale: (u32, TimescaleUnit), output: &CountingWriter<W>) -> ChangeWriter<'a> {
+		let index = options.time_index_path.as_ref().map(|_| output.index(options.line_ending == LineEnding::Crlf));
+		let pacer = options.realtime.map(|scale| Pacer::new(scale, timescale));
+		ChangeWriter { options, last_values: HashMap::new(), end: 0, time: None, written: Vec::new(), emitted: 0, index, pacer }
+	}
+
+	/// Writes a timestamp, recording its offset for `--time-index` and waiting until it's due
+	/// with `--realtime`.
+	fn timestamp(&mut self, writer: &mut dyn WaveWriter, timestamp: u64) -> io::Result<()> {
+		if self.options.canonical && self.time.replace(timestamp) == Some(timestamp) {
+			return Ok(());
+		}
+		if let Some(ref mut index) = self.index {
+			index.record(timestamp);
+		}
+		if let Some(ref mut pacer) = self.pacer {
+			pacer.wait(timestamp);
+		}
+		writer.timestamp(timestamp)
+	}
+
+	/// Writes the `--time-index` of the timestamps written.
+	fn write_index(&self) -> io::Result<()> {
+		match (&self.options.time_index_path, &self.index) {
+			(Some(path), Some(index)) => compress::write_sidecar(path, self.options.compress_sidecar, |out| index.write(out)),
+			_ => Ok(())
+		}
+	}
+
+	/// Writes a timed comment, which a `--snapshot` skips like the value changes.
+	fn comment(&mut self, writer: &mut dyn WaveWriter, timestamp: u64, text: &str) -> io::Result<()> {
+		self.end = self.end.max(timestamp);
+		if self.options.snapshot {
+			return Ok(());
+		}
+		self.timestamp(writer, timestamp)?;
+		writer.comment(text)
+	}
+
+	/// Writes a directive of the log, raw value changes are only written for declared variables.
+	fn directive(&mut self, writer: &mut dyn WaveWriter, timestamp: u64, directive: Directive, ids: &HashSet<IdCode>, diagnostics: &mut Diagnostics) -> io::Result<()> {
+		match directive {
+			Directive::Comment(text) => self.comment(writer, timestamp, &text),
+			Directive::Change(line, id, value) if ids.contains(&id) => self.change(writer, timestamp, id, value, Some(line)),
+			Directive::Change(line, id, _) => {
+				let message = format!("skipping raw value change for undeclared id '{}'", id);
+				diagnostics.report(Diagnostic { line: Some(line), ..Diagnostic::new("undeclared-id", message) });
+				Ok(())
+			}
+		}
+	}
+
+	/// Writes a value change, unless the options filter it out. A `--snapshot` only keeps it as
+	/// the last value of its signal. With `--annotate-lines` it's preceded by the line it was read from.
+	fn change(&mut self, writer: &mut dyn WaveWriter, timestamp: u64, id: IdCode, value: value_change::Value, line: Option<usize>) -> io::Result<()> {
+		if self.filtered(id, &value) {
+			return Ok(());
+		}
+		self.end = self.end.max(timestamp);
+		if self.options.snapshot {
+			self.last_values.insert(id, value);
+			return Ok(());
+		}
+		self.timestamp(writer, timestamp)?;
+		if let (true, Some(line)) = (self.options.annotate_lines, line) {
+			writer.comment(&format!("line {}", line))?;
+		}
+		self.emitted += 1;
+		self.last_values.insert(id, value.clone());
+		if self.options.checks_output() {
+			self.written.push((timestamp, id, value.clone()));
+		}
+		let value = if self.options.compact { compact(value) } else { value };
+		write_value(writer, id, value)
+	}
+
+	fn filtered(&self, id: IdCode, value: &value_change::Value) -> bool {
+		match (value, self.last_values.get(&id)) {
+			(value_change::Value::Real(v), Some(value_change::Value::Real(last))) => {
+				self.options.real_epsilon.is_some_and(|e| (v - last).abs() <= e)
+			},
+			(value, Some(last)) if self.options.collapse_x => value.is_unknown() && last.is_unknown(),
+			_ => false
+		}
+	}
+}
+
+/// Writes the time 0 timestamp, followed by the initial values.
+fn write_time_zero(options: &Options, writer: &mut dyn WaveWriter, variables: &Variables, changes: &mut ChangeWriter, diagnostics: &mut Diagnostics) -> io::Result<()> {
+	if options.no_time_zero || options.snapshot {
+		return Ok(());
+	}
+	changes.timestamp(writer, 0)?;
+	write_initial_values(options, writer, variables, changes, diagnostics)
+}
+
+/// Writes the $dumpvars block requested by `--dumpvars` or `--assume-initial`.
+///
+/// Signals with an assumed initial value start with that value, with `--dumpvars` every other
+/// signal (except reals, which have no unknown state) starts as x. The initial values are the last
+/// values of `changes`, so `--collapse-x` skips an unknown first change of a signal starting as x.
+fn write_initial_values(options: &Options, writer: &mut dyn WaveWriter, variables: &Variables, changes: &mut ChangeWriter, diagnostics: &mut Diagnostics) -> io::Result<()> {
+	if !options.dumpvars && options.assume_initial.is_empty() {
+		return Ok(());
+	}
+
+	for (name, _) in &options.assume_initial {
+		if !variables.contains_key(name) {
+			diagnostics.report(Diagnostic::new("unknown-signal", format!("ignoring assumed initial value of unknown signal '{}'", name)));
+		}
+	}
+
+	writer.begin(SimulationCommand::Dumpvars)?;
+	for (name, (var_type, width, id, _)) in sorted_by_id(variables) {
+		let assumed = options.assume_initial.iter().rev().find(|(n, _)| n == name);
+		let value = match assumed {
+			Some((_, value)) => {
+				let value_type = if *var_type == VarType::Real { String::from("f") } else { width.to_string() };
+				value_change::Value::parse(value, &value_type).map_err(|e| {
+					io::Error::new(io::ErrorKind::InvalidInput, format!("invalid initial value '{}' for signal '{}': {:?}", value, name, e))
+				})?
+			},
+			None if !options.dumpvars || *var_type == VarType::Real => continue,
+			None if *width == 1 => value_change::Value::Scalar(ScalarValue::X),
+			None => value_change::Value::BinaryVector{width: *width, value: vec![ScalarValue::X]}
+		};
+		changes.last_values.insert(*id, value.clone());
+		write_value(writer, *id, value)?;
+	}
+	writer.end()
+}
+
+/// Writes the final timestamp requested by `--trailing-timestamp` or `--end-time`, with the last
+/// values of `--pad-end` or `--snapshot`.
+fn write_end_time(options: &Options, writer: &mut dyn WaveWriter, changes: &mut ChangeWriter) -> io::Result<()> {
+	let last = changes.end;
+	let end = match options.end_time {
+		Some(end) if end < last => {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("end time {} is before the last value change at {}", end, last)));
+		},
+		Some(end) => Some(end),
+		None if options.trailing_timestamp => Some(last + 1),
+		None => None
+	};
+	if options.snapshot {
+		changes.timestamp(writer, end.unwrap_or(last))?;
+		writer.begin(SimulationCommand::Dumpvars)?;
+		write_last_values(options, writer, changes)?;
+		writer.end()?;
+	}
+	else if options.pad_end {
+		// the values already end at the last timestamp
+		if let Some(end) = end.filter(|&end| end > last) {
+			changes.timestamp(writer, end)?;
+			write_last_values(options, writer, changes)?;
+		}
+	}
+	else if let Some(end) = end {
+		changes.timestamp(writer, end)?;
+	}
+	Ok(())
+}
+
+/// Writes the `--emit-tick-comment` comment, with the timescale of the header.
+fn write_tick_comment(options: &Options, writer: &mut dyn WaveWriter, header: &Header) -> io::Result<()> {
+	if let (true, Some((step, unit))) = (options.emit_tick_comment, header.timescale) {
+		writer.comment(&format!("1 tick = {} {}", step, unit))?;
+	}
+	Ok(())
+}
+
+/// Writes the `--emit-end-comment` comment, with the timestamp of the last value change.
+fn write_end_comment(options: &Options, writer: &mut dyn WaveWriter, last: u64) -> io::Result<()> {
+	if options.emit_end_comment {
+		writer.comment(&format!("end_time={}", last))?;
+	}
+	Ok(())
+}
+
+/// Writes the last value of every signal, in the order of their ids.
+fn write_last_values(options: &Options, writer: &mut dyn WaveWriter, changes: &ChangeWriter) -> io::Result<()> {
+	let mut values: Vec<(&IdCode, &value_change::Value)> = changes.last_values.iter().collect();
+	values.sort_by_key(|(id, _)| **id);
+	for (id, value) in values {
+		let value = if options.compact { compact(value.clone()) } else { value.clone() };
+		write_value(writer, *id, value)?;
+	}
+	Ok(())
+}
+
+/// Registers a variable for every signal of the value changes.
+fn discover(options: &Options, value_changes: &[ValueChange], ids: &mut IdAllocator) -> io::Result<Variables> {
+	let mut variables = Variables::new();
+	for elem in value_changes {
+		discover_change(options, elem, &mut variables, ids)?;
+	}
+	Ok(variables)
+}
+
+/// Registers the variable of a value change, if it's the first change of the signal.
+fn discover_change(options: &Options, elem: &ValueChange, variables: &mut Variables, ids: &mut IdAllocator) -> io::Result<()> {
+	let (sig_type, width) = var_kind(&elem.value);
+	let (var_type, var_width, _, scope) = variables.entry(elem.signal_name.clone()).or_insert_with(|| { //TODO: get rid of the clone of every lookup
+		(sig_type, width, ids.id_for(&elem.signal_name), elem.scope.clone())
+	});
+	if *scope != elem.scope {
+		let message = format!("signal '{}' is logged both by a --label log and outside of it", elem.signal_name);
+		return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+	}
+	if options.promote_width && *var_type != VarType::Real && sig_type != VarType::Real {
+		*var_width = (*var_width).max(width);
+		*var_type = if *var_width > 1 { VarType::Integer } else { VarType::Wire };
+	}
+	else if (*var_type, *var_width) != (sig_type, width) && options.has_aliases(&elem.signal_name) {
+		let message = format!("signal '{}' and its aliases are logged with different types", elem.signal_name);
+		return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+	}
+	Ok(())
+}
+
+/// Renames the changes of signals logged with more than one width to `signal@width`.
+fn split_by_width(value_changes: &mut [ValueChange]) {
+	let suffix = |change: &ValueChange| match var_kind(&change.value) {
+		(VarType::Real, _) => String::from("f"),
+		(_, width) => width.to_string()
+	};
+	let mut widths: HashMap<String, HashSet<String>> = HashMap::new();
+	for change in value_changes.iter() {
+		widths.entry(change.signal_name.clone()).or_default().insert(suffix(change));
+	}
+	for change in value_changes.iter_mut() {
+		if widths[&change.signal_name].len() > 1 {
+			change.signal_name = format!("{}@{}", change.signal_name, suffix(change));
+		}
+	}
+}
+
+/// Counts the transitions of each signal, the changes to a value other than its previous one.
+fn transition_counts(value_changes: &[ValueChange]) -> HashMap<&str, usize> {
+	let mut last_values: HashMap<&str, &value_change::Value> = HashMap::new();
+	let mut counts: HashMap<&str, usize> = HashMap::new();
+	for change in value_changes {
+		let count = counts.entry(&change.signal_name).or_insert(0);
+		if let Some(last) = last_values.insert(&change.signal_name, &change.value) {
+			if *last != change.value {
+				*count += 1;
+			}
+		}
+	}
+	counts
+}
+
+/// Removes the signals that keep the value of their first change, along with their changes.
+fn trim_signals(variables: &mut Variables, value_changes: &mut Vec<ValueChange>) {
+	let changing: HashSet<String> = transition_counts(value_changes).into_iter()
+		.filter(|(_, count)| *count > 0)
+		.map(|(name, _)| String::from(name))
+		.collect();
+	variables.retain(|name, _| changing.contains(name));
+	value_changes.retain(|change| changing.contains(&change.signal_name));
+}
+
+/// Writes the `n` signals with the most transitions, with their transition counts.
+fn write_hotspots(value_changes: &[ValueChange], n: usize, out: &mut dyn Write) -> io::Result<()> {
+	let mut counts: Vec<(&str, usize)> = transition_counts(value_changes).into_iter().collect();
+	counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+	writeln!(out, "signals with the most transitions:")?;
+	for (name, count) in counts.into_iter().take(n) {
+		writeln!(out, "  {} {}", name, count)?;
+	}
+	Ok(())
+}
+
+/// Writes the intervals longer than `threshold` ticks between consecutive distinct timestamps, as
+/// `start end duration` lines.
+///
+/// A timestamp before the one preceding it, kept by `--preserve-order`, isn't a gap.
+fn write_gaps(value_changes: &[ValueChange], threshold: u64, out: &mut dyn Write) -> io::Result<()> {
+	writeln!(out, "gaps longer than {} ticks:", threshold)?;
+	for pair in value_changes.windows(2) {
+		let (start, end) = (pair[0].timestamp, pair[1].timestamp);
+		let duration = end.saturating_sub(start);
+		if duration > threshold {
+			writeln!(out, "  {} {} {}", start, end, duration)?;
+		}
+	}
+	Ok(())
+}
+
+/// Widens the values of promoted variables to the declared width. A `0` or `1` scalar becomes a
+/// single bit vector that VCD zero-extends to the full width, an `x` or `z` fills the whole width
+/// like VCD extends a vector starting with one.
+fn promote(variables: &Variables, change: &mut ValueChange) {
+	let width = variables[&change.signal_name].1;
+	match change.value {
+		value_change::Value::Scalar(ref v) if width > 1 => {
+			let bits = match v {
+				ScalarValue::X | ScalarValue::Z => width,
+				_ => 1
+			};
+			change.value = value_change::Value::BinaryVector{width, value: vec![v.clone(); bits]};
+		},
+		value_change::Value::BinaryVector{width: ref mut w, ..} => *w = width,
+		_ => {}
+	}
+}
+
+/// Writes a `timestamp signal old->new` line for every change of a signal's value, signals start
+/// out as `x`.
+fn write_edges<I: Iterator<Item = io::Result<ValueChange>>>(value_changes: I, output: &mut dyn Write) -> io::Result<()> {
+	let mut last_values: HashMap<String, String> = HashMap::new();
+	for change in value_changes {
+		let change = change?;
+		let new = change.value.to_string();
+		let old = last_values.insert(change.signal_name.clone(), new.clone()).unwrap_or_else(|| String::from("x"));
+		if old != new {
+			writeln!(output, "{} {} {}->{}", change.timestamp, change.signal_name, old, new)?;
+		}
+	}
+	Ok(())
+}
+
+/// Writes a `delta signal value` line for every value change, `delta` being the time since the
+/// previous change (or since 0 for the first one).
+fn write_deltas<I: Iterator<Item = io::Result<ValueChange>>>(value_changes: I, output: &mut dyn Write) -> io::Result<()> {
+	let mut last = 0;
+	for change in value_changes {
+		let change = change?;
+		// --preserve-order keeps the timestamps of the log, which may go back
+		let delta = change.timestamp.checked_sub(last).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData,
+			format!("timestamp {} of '{}' is before the previous one {}, deltas can't be negative", change.timestamp, change.signal_name, last)))?;
+		writeln!(output, "{} {} {}", delta, change.signal_name, change.value)?;
+		last = change.timestamp;
+	}
+	Ok(())
+}
+
+/// Writes the `--emit-toc` comment, listing the signals in declaration order.
+fn write_toc(options: &Options, writer: &mut dyn WaveWriter, variables: &Variables) -> io::Result<()> {
+	if !options.emit_toc {
+		return Ok(());
+	}
+	let entries: Vec<String> = sorted_by_id(variables).into_iter()
+		.map(|(name, (_, width, id, _))| format!("{} {} {}", name, id, width))
+		.collect();
+	writer.comment(&format!("toc\n    {}", entries.join("\n    ")))
+}
+
+/// Writes the `--dump-ids` mapping of every signal to its id.
+fn write_ids(variables: &Variables, out: &mut dyn Write) -> io::Result<()> {
+	for (name, (_, _, id, _)) in sorted_by_id(variables) {
+		writeln!(out, "{} {}", name, id)?;
+	}
+	Ok(())
+}
+
+/// Writes the `--annotate-counts` comment listing the transitions of every signal.
+fn write_counts(writer: &mut dyn WaveWriter, variables: &Variables, counts: &HashMap<String, usize>) -> io::Result<()> {
+	let entries: Vec<String> = sorted_by_id(variables).into_iter()
+		.map(|(name, _)| format!("{} {}", name, counts.get(name).cloned().unwrap_or(0)))
+		.collect();
+	writer.comment(&format!("transition counts\n    {}", entries.join("\n    ")))
+}
+
+/// Writes a `name type width` line for every variable, ordered by name.
+fn list_signals(variables: &Variables, output: &mut dyn Write) -> io::Result<()> {
+	let mut names: Vec<&String> = variables.keys().collect();
+	names.sort();
+	for name in names {
+		let (var_type, width, _, _) = variables[name];
+		writeln!(output, "{} {} {}", name, var_type, width)?;
+	}
+	Ok(())
+}
+
+fn convert<R: BufRead>(options: &Options, input_reader: R, output: &mut dyn Write, diagnostics: &mut Diagnostics) -> io::Result<()> {
+	let mut limited;
+	let output: &mut dyn Write = match options.max_output_bytes {
+		Some(limit) => {
+			limited = Limited::new(output, limit);
+			&mut limited
+		},
+		None => output
+	};
+	let result = match options.manifest_path {
+		None => convert_compressed(options, input_reader, output, diagnostics),
+		Some(ref path) => {
+			let mut input_reader = HashingReader::new(input_reader);
+			let mut output = HashingWriter::new(output);
+			convert_compressed(options, &mut input_reader, &mut output, diagnostics).and_then(|_| {
+				// the hash is of the whole input, even if the conversion stopped early
+				io::copy(&mut input_reader, &mut io::sink())?;
+				let manifest = manifest::to_json(&input_reader.hash(), &output.hash(), serde_json::to_value(options)?, diagnostics.stats.to_json());
+				compress::write_sidecar(path, options.compress_sidecar, |out| writeln!(out, "{}", manifest))
+			})
+		}
+	};
+	// the summary is written even if the conversion failed, it tells why
+	if let Some(ref path) = options.summary_json_path {
+		compress::write_sidecar(path, options.compress_sidecar, |out| writeln!(out, "{}", diagnostics.stats.to_json()))?;
+	}
+	if options.stats {
+		eprintln!("{}", diagnostics.stats);
+	}
+	result
+}
+
+/// Converts the log, compressed with `--compress`.
+fn convert_compressed<R: BufRead>(options: &Options, input_reader: R, output: &mut dyn Write, diagnostics: &mut Diagnostics) -> io::Result<()> {
+	match options.compression() {
+		None => convert_lines(options, input_reader, output, diagnostics),
+		Some(Compression::Zstd) => {
+			let mut encoder = zstd::Encoder::new(output, 0)?;
+			// the encoder has to be finished to write the end of the frame
+			convert_lines(options, input_reader, &mut encoder, diagnostics).and_then(|_| encoder.finish().map(|_| ()))
+		}
+	}
+}
+
+/// Converts the log, with the line endings of `--line-ending`.
+fn convert_lines<R: BufRead>(options: &Options, input_reader: R, output: &mut dyn Write, diagnostics: &mut Diagnostics) -> io::Result<()> {
+	match options.line_ending {
+		LineEnding::Lf => convert_checked(options, input_reader, output, diagnostics),
+		LineEnding::Crlf => convert_checked(options, input_reader, &mut CrlfWriter::new(output), diagnostics)
+	}
+}
+
+/// Converts the log, reading the VCD back first with `--self-check`. `check` only reads it back,
+/// `reverse` converts a VCD to a log instead.
+fn convert_checked<R: BufRead>(options: &Options, input_reader: R, output: &mut dyn Write, diagnostics: &mut Diagnostics) -> io::Result<()> {
+	if options.command == Some(Command::Reverse) {
+		return reverse::reverse(input_reader, output);
+	}
+	let vcd_output = options.output_format == OutputFormat::Vcd && !options.lists_signals();
+	if !(vcd_output && options.checks_output()) {
+		// `check` only reports the diagnostics, whatever the output format
+		let output: &mut dyn Write = if options.command == Some(Command::Check) { &mut io::sink() } else { output };
+		return write_vcd(options, input_reader, output, diagnostics).map(|_| ());
+	}
+	// the VCD is kept in memory, so it can be read back before it's written out
+	let mut vcd = Vec::new();
+	let written = write_vcd(options, input_reader, &mut vcd, diagnostics)?;
+	if options.checks_output() {
+		self_check::verify(&vcd[..], &written)?;
+	}
+	if options.command == Some(Command::Check) {
+		return Ok(());
+	}
+	output.write_all(&vcd)
+}
+
+/// The width of each signal, along with the line of the change that established it (none for
+/// declared variables).
+type Widths = HashMap<String, (usize, Option<usize>)>;
+
+/// Whether each signal is a real, along with the line of the change that established it (none for
+/// declared variables).
+type Kinds = HashMap<String, (bool, Option<usize>)>;
+
+/// Reports a real value change of a signal that isn't a real or the other way around, which is an
+/// error in strict mode. Returns whether the change is kept, a variable can't change its type.
+fn check_type(options: &Options, change: &ValueChange, line_no: usize, kinds: &mut Kinds, diagnostics: &mut Diagnostics) -> io::Result<bool> {
+	if options.split_by_width {
+		return Ok(true);
+	}
+	let real = matches!(change.value, value_change::Value::Real(_));
+	let (established_real, established) = *kinds.entry(change.signal_name.clone()).or_insert((real, Some(line_no)));
+	if real == established_real {
+		return Ok(true);
+	}
+	let kind = |real| if real { "a real" } else { "a scalar or vector" };
+	let message = match established {
+		Some(line) => format!("signal '{}' has {} value, but {} one on line {}", change.signal_name, kind(real), kind(established_real), line),
+		None => format!("signal '{}' has {} value, but is declared with {} one", change.signal_name, kind(real), kind(established_real))
+	};
+	if options.strict {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line_no, message)));
+	}
+	diagnostics.report(Diagnostic { line: Some(line_no), ..Diagnostic::new("type-change", format!("skipping the value change, {}", message)) });
+	Ok(false)
+}
+
+/// Applies `--real-special` to the infinite and nan real values, returning whether the change is kept.
+fn check_real_special(options: &Options, change: &mut ValueChange, line_no: usize, diagnostics: &mut Diagnostics) -> io::Result<bool> {
+	let real = match change.value {
+		_ if is_sentinel(options, options.resolve_alias(&change.signal_name), &change.value) => return Ok(true),
+		value_change::Value::Real(real) if !real.is_finite() => real,
+		_ => return Ok(true)
+	};
+	match options.real_special {
+		RealSpecial::Drop => {
+			let message = format!("dropping the real value {} of '{}', see --real-special", real, change.signal_name);
+			diagnostics.report(Diagnostic { line: Some(line_no), ..Diagnostic::new("non-finite-real", message) });
+			Ok(false)
+		},
+		RealSpecial::Error => {
+			let message = format!("line {}: signal '{}' has the real value {}, see --real-special", line_no, change.signal_name, real);
+			Err(io::Error::new(io::ErrorKind::InvalidData, message))
+		},
+		RealSpecial::Clamp => {
+			change.value = value_change::Value::Real(if real.is_nan() { 0.0 } else { real.signum() * f64::MAX });
+			Ok(true)
+		},
+		RealSpecial::Pass => Ok(true)
+	}
+}
+
+/// Fails on a change of a signal narrower than its earlier changes with `--growing-widths`.
+fn check_growing_width(options: &Options, change: &ValueChange, line_no: usize, widths: &mut HashMap<String, usize>) -> io::Result<()> {
+	if !options.growing_widths {
+		return Ok(());
+	}
+	let width = match change.value {
+		value_change::Value::Scalar(_) => 1,
+		value_change::Value::BinaryVector{width, ..} => width,
+		value_change::Value::Real(_) => return Ok(())
+	};
+	match widths.insert(change.signal_name.clone(), width) {
+		Some(widest) if width < widest => {
+			widths.insert(change.signal_name.clone(), widest);
+			let message = format!("line {}: signal '{}' shrinks from a width of {} to {}", line_no, change.signal_name, widest, width);
+			Err(io::Error::new(io::ErrorKind::InvalidData, message))
+		},
+		_ => Ok(())
+	}
+}
+
+/// Reports a value change with more bits than the width of its signal, which is an error in strict
+/// mode. The first change of a signal establishes its width, unless it's declared up front.
+fn check_width(options: &Options, change: &ValueChange, line_no: usize, widths: &mut Widths, diagnostics: &mut Diagnostics) -> io::Result<()> {
+	if let value_change::Value::BinaryVector{width, ref value} = change.value {
+		// a single x or z is the usual way of writing a vector that's unknown as a whole
+		let short = value.len() < width && !(value.len() == 1 && change.value.is_unknown());
+		if options.validate_widths_strict && short {
+			let message = format!("line {}: signal '{}' has a {} bit value, shorter than its width of {}", line_no, change.signal_name, value.len(), width);
+			return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+		}
+	}
+	if options.promote_width || options.split_by_width {
+		return Ok(());
+	}
+	let bits = match change.value {
+		value_change::Value::Scalar(_) => 1,
+		value_change::Value::BinaryVector{ref value, ..} => value.len(),
+		value_change::Value::Real(_) => return Ok(())
+	};
+	let (width, established) = *widths.entry(change.signal_name.clone()).or_insert((var_kind(&change.value).1, Some(line_no)));
+	if bits <= width {
+		return Ok(());
+	}
+	let message = match established {
+		Some(line) => format!("signal '{}' has a {} bit value, wider than its width of {} from line {}", change.signal_name, bits, width, line),
+		None => format!("signal '{}' has a {} bit value, wider than its declared width of {}", change.signal_name, bits, width)
+	};
+	if options.strict {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line_no, message)));
+	}
+	diagnostics.report(Diagnostic { line: Some(line_no), ..Diagnostic::new("exceeds-width", message) });
+	Ok(())
+}
+
+/// A value change or directive read from a line of a log.
+enum LineItem {
+	Change(ValueChange),
+	/// A directive, at its timestamp
+	Directive(u64, Directive)
+}
+
+/// Turns the lines of a log into value changes and directives, through every preprocessing step
+/// of the options. The conversion in memory and streaming both read their lines with it.
+struct LineReader<'a> {
+	options: &'a Options,
+	parse_options: ParseOptions,
+	/// The `--label` of the log, the top scope of its signals
+	label: Option<&'a str>,
+	/// The distinct timestamps after which the reading stops, for a log in output order
+	head: Option<usize>,
+	clock: Option<WallClock>,
+	steps: u64,
+	vcd_timestamp: Option<String>,
+	/// The time of the last line with a timestamp, and the `BASE` of the lines
+	time: u64,
+	base: i128,
+	json_values: HashMap<String, value_change::Value>,
+	widths: Widths,
+	kinds: Kinds,
+	grown: HashMap<String, usize>,
+	last_line: Option<String>,
+	/// The distinct timestamps of the value changes read so far, and the last one
+	distinct: usize,
+	last: Option<u64>
+}
+
+impl<'a> LineReader<'a> {
+	fn new(options: &'a Options, timescale: (u32, TimescaleUnit), label: Option<&'a str>, head: Option<usize>) -> LineReader<'a> {
+		LineReader {
+			options,
+			parse_options: options.parse_options(timescale),
+			label,
+			head,
+			clock: options.input_time_format.as_ref().map(|format| WallClock::new(format, timescale)),
+			steps: 0,
+			vcd_timestamp: None,
+			time: 0,
+			base: 0,
+			json_values: HashMap::new(),
+			widths: Widths::new(),
+			kinds: Kinds::new(),
+			grown: HashMap::new(),
+			last_line: None,
+			distinct: 0,
+			last: None
+		}
+	}
+
+	/// Checks the widths and types of the value changes against the ones of declared variables.
+	fn declared(self, variables: &Variables) -> LineReader<'a> {
+		LineReader {
+			widths: variables.iter().map(|(name, (_, width, _, _))| (name.clone(), (*width, None))).collect(),
+			kinds: variables.iter().map(|(name, (var_type, _, _, _))| (name.clone(), (*var_type == VarType::Real, None))).collect(),
+			..self
+		}
+	}
+
+	/// Reads the lines up to the end of the head, handing what they turn into to `sink`.
+	fn read<I>(&mut self, lines: I, sink: &mut dyn FnMut(LineItem, &mut Diagnostics) -> io::Result<()>, diagnostics: &mut Diagnostics) -> io::Result<()>
+		where I: Iterator<Item = (usize, io::Result<String>)>
+	{
+		for (i, line) in lines {
+			let line = match line {
+				Err(ref e) if limit::too_long(e).is_some() => {
+					skip_long_line(self.options, e, i + 1, diagnostics)?;
+					continue;
+				},
+				line => line?
+			};
+			if !self.read_line(line, i + 1, sink, diagnostics)? {
+				break;
+			}
+		}
+		Ok(())
+	}
+
+	/// Reads a line, returns false if it's past the head.
+	fn read_line(&mut self, line: String, line_no: usize, sink: &mut dyn FnMut(LineItem, &mut Diagnostics) -> io::Result<()>, diagnostics: &mut Diagnostics) -> io::Result<bool> {
+		let options = self.options;
+		let (prefix, line) = strip_prefix(options, line);
+		check_duplicate_line(options, &line, line_no, &mut self.last_line, diagnostics);
+		let line = match wall_clock_line(&mut self.clock, line, line_no, diagnostics) {
+			Some(line) => line,
+			None => return Ok(true)
+		};
+		let line = synthetic_time_line(options, line, &mut self.steps);
+		let line = match vcd_like_line(options, line, &mut self.vcd_timestamp) {
+			Some(line) => line,
+			None => return Ok(true)
+		};
+		let line = resolve_state(options, &line);
+		let line = resolve_reset(options, &line);
+		if let Some(timestamp) = value_change::parse_base(&line, &self.parse_options) {
+			self.base = timestamp;
+			return Ok(true);
+		}
+		if let Some(directive) = parse_directive(options, &line, line_no, &self.parse_options, self.base, &mut self.time)? {
+			sink(LineItem::Directive(self.time, directive), diagnostics)?;
+			return Ok(true);
+		}
+		if options.vcd_passthrough && value_change::parse_vcd_timestamp(&line).is_some() {
+			return Ok(true);
+		}
+		for mut change in parse_line(options, &prefix, &line, line_no, &self.parse_options, &mut self.json_values, diagnostics)? {
+			change.timestamp = rebase(self.base, change.timestamp, line_no)?;
+			change.line = Some(line_no);
+			self.time = change.timestamp;
+			if let Some(label) = self.label {
+				change.signal_name = format!("{}.{}", label, change.signal_name);
+				change.scope = Some(String::from(label));
+			}
+			if !check_real_special(options, &mut change, line_no, diagnostics)? {
+				continue;
+			}
+			if self.last != Some(change.timestamp) {
+				self.distinct += 1;
+				self.last = Some(change.timestamp);
+			}
+			if self.head.is_some_and(|n| self.distinct > n) {
+				return Ok(false);
+			}
+			if let Some(comment) = strength_comment(&change) {
+				sink(LineItem::Directive(change.timestamp, comment), diagnostics)?;
+			}
+			for change in prepare(options, change) {
+				if !check_type(options, &change, line_no, &mut self.kinds, diagnostics)? {
+					continue;
+				}
+				check_width(options, &change, line_no, &mut self.widths, diagnostics)?;
+				check_growing_width(options, &change, line_no, &mut self.grown)?;
+				sink(LineItem::Change(change), diagnostics)?;
+			}
+		}
+		Ok(true)
+	}
+}
+
+/// Reads the value changes and directives of a log. The signals of a log with a `--label` are
+/// declared in the top scope named after the label.
+///
+/// The header block of the input is read into `metadata`, labeled logs don't have one.
+fn read_log<R: BufRead>(options: &Options, input_reader: R, label: Option<&str>, metadata: &mut Metadata, sink: &mut dyn FnMut(ValueChange) -> io::Result<()>, directives: &mut Directives, diagnostics: &mut Diagnostics) -> io::Result<()> {
+	let mut lines = limit::Lines::new(input_reader, options.max_line_len).enumerate().peekable();
+	if label.is_none() {
+		read_metadata(options, &mut lines, metadata, diagnostics)?;
+	}
+	// the reading stops at the end of the head only when the log is in the order of the output,
+	// an unordered one is read whole and cut to its first timestamps once sorted
+	let head = options.head.filter(|_| options.preserve_order);
+	let mut reader = LineReader::new(options, options.timescale(metadata), label, head);
+	reader.read(lines, &mut |read, _| match read {
+		LineItem::Change(change) => sink(change),
+		LineItem::Directive(timestamp, directive) => {
+			directives.push((timestamp, directive));
+			Ok(())
+		}
+	}, diagnostics)
+}
+
+/// Fails if more of the lines read were dropped than `--max-drop-rate` allows.
+fn check_drop_rate(options: &Options, diagnostics: &Diagnostics) -> io::Result<()> {
+	let stats = &diagnostics.stats;
+	let dropped: usize = stats.dropped.values().sum();
+	match options.max_drop_rate {
+		Some(max) if stats.lines > 0 && dropped as f64 * 100.0 / stats.lines as f64 > max => {
+			let message = format!("{} of {} lines were dropped, more than the maximum of {}%", dropped, stats.lines, max);
+			Err(io::Error::new(io::ErrorKind::InvalidData, message))
+		},
+		_ => Ok(())
+	}
+}
+
+/// The `--prefix` of the signals of a log file.
+fn file_prefix<'a>(options: &'a Options, path: Option<&Path>) -> Option<&'a str> {
+	let path = path?;
+	options.prefixes.iter().rev().find(|(file, _)| Path::new(file) == path).map(|(_, prefix)| prefix.as_str())
+}
+
+/// Prefixes the signal of a value change read from a log, within the top scope of its label.
+fn prefixed(mut change: ValueChange, prefix: Option<&str>) -> ValueChange {
+	let prefix = match prefix {
+		Some(prefix) => prefix,
+		None => return change
+	};
+	let name = &change.signal_name;
+	change.signal_name = match change.scope.as_deref().and_then(|label| Some((label, labeled_name(name, label)?))) {
+		Some((label, name)) => format!("{}.{}{}", label, prefix, name),
+		None => format!("{}{}", prefix, name)
+	};
+	change
+}
+
+/// Reads the input and the `--label` logs, returning the header block of the input and the directives.
+fn read_logs<R: BufRead>(options: &Options, input_reader: R, sink: &mut dyn FnMut(ValueChange) -> io::Result<()>, diagnostics: &mut Diagnostics) -> io::Result<(Metadata, Directives)> {
+	let mut metadata = Metadata::default();
+	let mut directives = Directives::new();
+	let prefix = file_prefix(options, options.input_path.as_deref());
+	read_log(options, input_reader, None, &mut metadata, &mut |change| sink(prefixed(change, prefix)), &mut directives, diagnostics)?;
+	for (path, label) in &options.labels {
+		let reader = compress::decompress(BufReader::new(File::open(path)?))?;
+		let prefix = file_prefix(options, Some(Path::new(path)));
+		read_log(options, reader, Some(label), &mut metadata, &mut |change| sink(prefixed(change, prefix)), &mut directives, diagnostics)?;
+	}
+	directives.sort_by_key(|(timestamp, _)| *timestamp);
+	check_drop_rate(options, diagnostics)?;
+	Ok((metadata, directives))
+}
+
+/// The bytes a value change takes in memory, including its name and bits.
+fn change_size(change: &ValueChange) -> usize {
+	let bits = match change.value {
+		value_change::Value::BinaryVector{ref value, ..} => value.capacity() * std::mem::size_of::<ScalarValue>(),
+		_ => 0
+	};
+	std::mem::size_of::<ValueChange>() + change.signal_name.capacity() + bits
+}
+
+/// Adds a value change to the ones held in memory, failing once they exceed `--max-memory`.
+fn hold(options: &Options, value_changes: &mut Vec<ValueChange>, memory: &mut usize, change: ValueChange) -> io::Result<()> {
+	*memory += change_size(&change);
+	match options.max_memory {
+		Some(max) if *memory > max => Err(io::Error::new(io::ErrorKind::OutOfMemory, format!(
+			"the value changes take more than the {} bytes of --max-memory after {} changes, see --external-sort",
+			max, value_changes.len()
+		))),
+		_ => { value_changes.push(change); Ok(()) }
+	}
+}
+
+/// Reads the value changes of a `--dump-parsed` file, failing when it wasn't written by this version.
+fn load_parsed(path: &Path) -> io::Result<Vec<ValueChange>> {
+	let mut reader = BufReader::new(File::open(path)?);
+	let mut header = vec![0; PARSED_HEADER.len()];
+	match reader.read_exact(&mut header) {
+		Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => header.clear(),
+		result => result?
+	}
+	if header != PARSED_HEADER.as_bytes() {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, format!("'{}' isn't a --dump-parsed file of this version of log2vcd", path.display())));
+	}
+	bincode::deserialize_from(reader).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Reads the `--diff` log into the companion diff signals of the value changes. Its diagnostics are
+/// reported with the name of the log, and its lines don't count in the statistics of the input.
+fn add_diff_signals(options: &Options, path: &Path, value_changes: &mut Vec<ValueChange>, diagnostics: &mut Diagnostics) -> io::Result<()> {
+	let mut other = Vec::new();
+	let reader = compress::decompress(BufReader::new(File::open(path)?))?;
+	let mut own = Diagnostics::new(DiagnosticsFormat::Json);
+	let read = read_log(options, reader, None, &mut Metadata::default(), &mut |change| { other.push(change); Ok(()) }, &mut Directives::new(), &mut own);
+	for diagnostic in own.into_reported() {
+		diagnostics.report(Diagnostic { message: format!("{}: {}", path.display(), diagnostic.message), ..diagnostic });
+	}
+	read?;
+	other.sort_by_key(|v| v.timestamp);
+	let diffs = diff::diff_signals(value_changes, &other);
+	value_changes.extend(diffs);
+	value_changes.sort_by_key(|v| v.timestamp);
+	Ok(())
+}
+
+/// Writes the VCD, returning the value changes that were written when they are kept for `--self-check`.
+fn write_vcd<R: BufRead>(options: &Options, input_reader: R, output: &mut dyn Write, diagnostics: &mut Diagnostics) -> io::Result<Vec<self_check::Written>> {
+	let mut ids = IdAllocator::new(match options.id_map_path {
+		Some(ref path) => read_id_map(BufReader::new(File::open(path)?))?,
+		None => HashMap::new()
+	});
+
+	if let Some(ref path) = options.vars_path {
+		let variables = read_vars(BufReader::new(File::open(path)?), &mut ids)?;
+		diagnostics.stats.signals = variables.len();
+		if options.lists_signals() {
+			return list_signals(&variables, output).map(|_| Vec::new());
+		}
+		if let OutputFormat::Edges | OutputFormat::Deltas = options.output_format {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "--vars streams a VCD, it can't write edges or deltas"));
+		}
+		// a strict conversion that fails mustn't leave a partial VCD in the output
+		let written = if options.strict {
+			let mut buffered = Vec::new();
+			let written = stream(options, &variables, input_reader, &mut buffered, diagnostics)?;
+			output.write_all(&buffered)?;
+			written
+		}
+		else {
+			stream(options, &variables, input_reader, output, diagnostics)?
+		};
+		diagnostics.phase("converting");
+		return Ok(written);
+	}
+
+	if options.external_sort {
+		let written = external_sort(options, options.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE), input_reader, ids, output, diagnostics)?;
+		diagnostics.phase("converting");
+		return Ok(written);
+	}
+
+	let mut value_changes: Vec<ValueChange> = Vec::new();
+	let mut memory = 0;
+	let (metadata, mut comments) = match options.load_parsed_path {
+		Some(ref path) => {
+			for change in load_parsed(path)? {
+				hold(options, &mut value_changes, &mut memory, change)?;
+			}
+			(Metadata::default(), Directives::new())
+		},
+		None => read_logs(options, input_reader, &mut |change| hold(options, &mut value_changes, &mut memory, change), diagnostics)?
+	};
+	info!("read {} value changes", value_changes.len());
+	diagnostics.phase("parsing");
+	if let Some(ref path) = options.dump_parsed_path {
+		let mut file = io::BufWriter::new(File::create(path)?);
+		file.write_all(PARSED_HEADER.as_bytes())?;
+		bincode::serialize_into(&mut file, &value_changes).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+		file.flush()?;
+	}
+	if options.normalize_names {
+		normalize_names(&mut value_changes, diagnostics);
+	}
+	if options.split_by_width {
+		split_by_width(&mut value_changes);
+	}
+	if options.preserve_order {
+		check_order(options, &value_changes, diagnostics)?;
+	}
+	else if options.canonical {
+		value_changes.sort_by(|a, b| {
+			a.timestamp.cmp(&b.timestamp)
+				.then_with(|| a.signal_name.cmp(&b.signal_name))
+				.then_with(|| a.value.canonical_cmp(&b.value))
+		});
+		debug!("sorted the value changes by timestamp, signal and value");
+	}
+	else {
+		value_changes.sort_by_key(|v| v.timestamp);
+		debug!("sorted the value changes by timestamp");
+	}
+	if let Some(ref path) = options.diff_path {
+		add_diff_signals(options, path, &mut value_changes, diagnostics)?;
+	}
+	if let Some(merge) = options.real_merge {
+		merge_reals(&mut value_changes, merge);
+	}
+	if !options.merge_bits.is_empty() {
+		value_changes = merge_bits(options, value_changes);
+	}
+	if let Some(interval) = options.decimate {
+		if interval == 0 {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "the decimation interval has to be at least one tick"));
+		}
+		decimate(&mut value_changes, interval);
+	}
+	if let Some(interval) = options.resample {
+		if interval == 0 {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "the resampling interval has to be at least one tick"));
+		}
+		value_changes = resample(value_changes, interval);
+	}
+
+	if let Some(n) = options.head {
+		truncate_to_timestamps(&mut value_changes, n);
+		let last = value_changes.last().map_or(0, |change| change.timestamp);
+		comments.retain(|(timestamp, _)| *timestamp <= last);
+	}
+
+	if value_changes.is_empty() {
+		no_value_changes(options, diagnostics)?;
+	}
+
+	if let Some(ref path) = options.order_path {
+		reserve_order(BufReader::new(File::open(path)?), &value_changes, &mut ids)?;
+	}
+	else if options.order_by == Some(OrderBy::Alphabetical) || options.canonical {
+		reserve_order(io::empty(), &value_changes, &mut ids)?;
+	}
+	let mut variables = discover(options, &value_changes, &mut ids)?;
+	info!("discovered {} variables", variables.len());
+	check_expected_signals(options, &variables)?;
+	if options.trim_signals {
+		trim_signals(&mut variables, &mut value_changes);
+	}
+	if let Some(n) = options.hotspots {
+		write_hotspots(&value_changes, n, &mut io::stderr())?;
+	}
+	if let Some(threshold) = options.gap_report {
+		write_gaps(&value_changes, threshold, &mut io::stderr())?;
+	}
+	if let Some(ref path) = options.thumbnail_path {
+		thumbnail::write(io::BufWriter::new(File::create(path)?), &value_changes)?;
+	}
+	diagnostics.stats.signals = variables.len();
+	if options.promote_width {
+		for change in value_changes.iter_mut() {
+			promote(&variables, change);
+		}
+	}
+	diagnostics.phase("sorting");
+	let counts = if options.annotate_counts {
+		Some(transition_counts(&value_changes).into_iter().map(|(name, count)| (String::from(name), count)).collect())
+	}
+	else {
+		None
+	};
+	let log = ParsedLog { variables: &variables, metadata: &metadata, comments, counts };
+	let written = write_output(options, log, value_changes.into_iter().map(Ok), output, diagnostics)?;
+	diagnostics.phase("writing");
+	Ok(written)
+}
+
+/// What was read from a log besides its value changes, written along with them.
+struct ParsedLog<'a> {
+	variables: &'a Variables,
+	metadata: &'a Metadata,
+	comments: Directives,
+	/// The transition counts of `--annotate-counts`
+	counts: Option<HashMap<String, usize>>
+}
+
+/// Writes the sorted value changes in the output format.
+fn write_output<I: Iterator<Item = io::Result<ValueChange>>>(options: &Options, log: ParsedLog, value_changes: I, output: &mut dyn Write, diagnostics: &mut Diagnostics) -> io::Result<Vec<self_check::Written>> {
+	if options.lists_signals() {
+		return list_signals(log.variables, output).map(|_| Vec::new());
+	}
+	if options.output_format == OutputFormat::Edges {
+		return write_edges(value_changes, output).map(|_| Vec::new());
+	}
+	if options.output_format == OutputFormat::Deltas {
+		return write_deltas(value_changes, output).map(|_| Vec::new());
+	}
+	emit(options, log, value_changes, output, diagnostics)
+}
+
+/// Writes the VCD of sorted value changes and the comments of the log, with the transition counts
+/// of `--annotate-counts`.
+fn emit<I: Iterator<Item = io::Result<ValueChange>>>(options: &Options, log: ParsedLog, value_changes: I, output: &mut dyn Write, diagnostics: &mut Diagnostics) -> io::Result<Vec<self_check::Written>> {
+	let ParsedLog { variables, metadata, comments, counts } = log;
+	check_timescale(options, metadata, diagnostics);
+	let header = header(options, variables, metadata)?;
+	check_unique_references(&header)?;
+	write_save_file(options, variables, &header)?;
+	if options.dump_ids {
+		write_ids(variables, &mut io::stderr())?;
+	}
+	let mut output = CountingWriter::new(output);
+	let mut changes = ChangeWriter::new(options, options.timescale(metadata), &output);
+	let mut check = Vec::new();
+	let mut wave = wave_writer(options, &mut output, &mut check);
+	let writer = wave.as_mut();
+	writer.header(&header)?;
+	write_tick_comment(options, writer, &header)?;
+	write_toc(options, writer, variables)?;
+	if let Some(counts) = counts {
+		write_counts(writer, variables, &counts)?;
+	}
+	write_time_zero(options, writer, variables, &mut changes, diagnostics)?;
+
+	// only --canonical merges identical timestamps, otherwise each value change repeats its own
+	let ids: HashSet<IdCode> = variables.values().map(|(_, _, id, _)| *id).collect();
+	let mut comments = comments.into_iter().peekable();
+	for change in value_changes {
+		let change = change?;
+		// raw value changes follow the log lines of their timestamp, comments go before them
+		let before = |(timestamp, directive): &(u64, Directive)| match directive {
+			Directive::Comment(_) => *timestamp <= change.timestamp,
+			Directive::Change(..) => *timestamp < change.timestamp
+		};
+		while let Some((timestamp, directive)) = comments.next_if(before) {
+			changes.directive(writer, timestamp, directive, &ids, diagnostics)?;
+		}
+		let (_, _, id, _) = variables[&change.signal_name];
+		let value = pull(options, &change.signal_name, change.value);
+		changes.change(writer, change.timestamp, id, value, change.line)?;
+	}
+	for (timestamp, directive) in comments {
+		changes.directive(writer, timestamp, directive, &ids, diagnostics)?;
+	}
+
+	let last = changes.end;
+	write_end_time(options, writer, &mut changes)?;
+	write_end_comment(options, writer, last)?;
+	wave.finish()?;
+	drop(wave);
+	if !check.is_empty() {
+		self_check::verify(&check[..], &changes.written)?;
+	}
+	changes.write_index()?;
+	info!("emitted {} value changes", changes.emitted);
+	Ok(changes.written)
+}
+
+/// Writes both waveforms.
+struct Both<A: WaveWriter, B: WaveWriter>(A, B);
+
+impl<A: WaveWriter, B: WaveWriter> WaveWriter for Both<A, B> {
+	fn header(&mut self, header: &Header) -> io::Result<()> {
+		self.0.header(header)?;
+		self.1.header(header)
+	}
+
+	fn timestamp(&mut self, timestamp: u64) -> io::Result<()> {
+		self.0.timestamp(timestamp)?;
+		self.1.timestamp(timestamp)
+	}
+
+	fn comment(&mut self, text: &str) -> io::Result<()> {
+		self.0.comment(text)?;
+		self.1.comment(text)
+	}
+
+	fn begin(&mut self, command: SimulationCommand) -> io::Result<()> {
+		self.0.begin(command)?;
+		self.1.begin(command)
+	}
+
+	fn end(&mut self) -> io::Result<()> {
+		self.0.end()?;
+		self.1.end()
+	}
+
+	fn change_scalar(&mut self, id: IdCode, value: vcd::Value) -> io::Result<()> {
+		self.0.change_scalar(id, value)?;
+		self.1.change_scalar(id, value)
+	}
+
+	fn change_vector(&mut self, id: IdCode, value: &[vcd::Value]) -> io::Result<()> {
+		self.0.change_vector(id, value)?;
+		self.1.change_vector(id, value)
+	}
+
+	fn change_real(&mut self, id: IdCode, value: f64) -> io::Result<()> {
+		self.0.change_real(id, value)?;
+		self.1.change_real(id, value)
+	}
+
+	fn finish(&mut self) -> io::Result<()> {
+		self.0.finish()?;
+		self.1.finish()
+	}
+}
+
+/// The writer of the VCD, or of the FST of `--output-format fst`. A FST can't be read back, so with
+/// `--self-check` the VCD is also written to `check`.
+fn wave_writer<'w>(options: &Options, output: &'w mut dyn Write, check: &'w mut Vec<u8>) -> Box<dyn WaveWriter + 'w> {
+	match options.output_format {
+		OutputFormat::Fst if options.checks_output() => Box::new(Both(fst::FstWriter::new(output), Writer::new(check))),
+		OutputFormat::Fst => Box::new(fst::FstWriter::new(output)),
+		_ => Box::new(Writer::new(output))
+	}
+}
+
+/// Returns a predicate that is true for the value changes within the first `head` timestamps.
+fn within_head(head: Option<usize>) -> impl FnMut(&io::Result<ValueChange>) -> bool {
+	let mut distinct = 0;
+	let mut last = None;
+	move |change| match change {
+		Ok(change) => {
+			if last != Some(change.timestamp) {
+				distinct += 1;
+				last = Some(change.timestamp);
+			}
+			head.map_or(true, |n| distinct <= n)
+		},
+		Err(_) => true
+	}
+}
+
+/// Writes the VCD of a log that's sorted on disk with `--external-sort`, only the chunks being
+/// sorted are kept in memory.
+fn external_sort<R: BufRead>(options: &Options, chunk_size: usize, input_reader: R, mut ids: IdAllocator, output: &mut dyn Write, diagnostics: &mut Diagnostics) -> io::Result<Vec<self_check::Written>> {
+	let mut sort = ExternalSort::new(chunk_size);
+	let (metadata, mut comments) = read_logs(options, input_reader, &mut |change| sort.push(change), diagnostics)?;
+
+	// a first pass over the sorted changes declares the variables in the same order as in memory
+	let mut variables = Variables::new();
+	let mut last = None;
+	for change in sort.merged()?.take_while(within_head(options.head)) {
+		let change = change?;
+		discover_change(options, &change, &mut variables, &mut ids)?;
+		last = Some(change.timestamp);
+	}
+	check_expected_signals(options, &variables)?;
+	diagnostics.stats.signals = variables.len();
+	match last {
+		Some(last) if options.head.is_some() => comments.retain(|(timestamp, _)| *timestamp <= last),
+		Some(_) => {},
+		None => no_value_changes(options, diagnostics)?
+	}
+	let value_changes = sort.merged()?.take_while(within_head(options.head));
+	write_output(options, ParsedLog { variables: &variables, metadata: &metadata, comments, counts: None }, value_changes, output, diagnostics)
+}
+
+/// Converts an already sorted log in a single pass, using variables declared up front.
+///
+/// Changes to signals that weren't declared are skipped with a warning.
+fn stream<R: BufRead>(options: &Options, variables: &Variables, input_reader: R, output: &mut dyn Write, diagnostics: &mut Diagnostics) -> io::Result<Vec<self_check::Written>> {
+	// the header block is read before the header it contributes to is written
+	let mut lines = limit::Lines::new(input_reader, options.max_line_len).enumerate().peekable();
+	let mut metadata = Metadata::default();
+	read_metadata(options, &mut lines, &mut metadata, diagnostics)?;
+	check_timescale(options, &metadata, diagnostics);
+	let header = header(options, variables, &metadata)?;
+	check_unique_references(&header)?;
+	write_save_file(options, variables, &header)?;
+	if options.dump_ids {
+		write_ids(variables, &mut io::stderr())?;
+	}
+	let mut output = CountingWriter::new(output);
+	let mut changes = ChangeWriter::new(options, options.timescale(&metadata), &output);
+	let mut check = Vec::new();
+	let mut wave = wave_writer(options, &mut output, &mut check);
+	let writer = wave.as_mut();
+	writer.header(&header)?;
+	write_tick_comment(options, writer, &header)?;
+	write_toc(options, writer, variables)?;
+	write_time_zero(options, writer, variables, &mut changes, diagnostics)?;
+
+	let ids: HashSet<IdCode> = variables.values().map(|(_, _, id, _)| *id).collect();
+	let mut reader = LineReader::new(options, options.timescale(&metadata), None, options.head).declared(variables);
+	let mut interrupted = false;
+	let lines = lines.map_while(|(i, line)| match line {
+		Err(ref e) if interrupt::is_interrupted(e) => {
+			interrupted = true;
+			None
+		},
+		line => Some((i, line))
+	});
+	reader.read(lines, &mut |read, diagnostics| match read {
+		LineItem::Change(change) => match variables.get(&change.signal_name) {
+			Some((_, _, id, _)) => changes.change(writer, change.timestamp, *id, pull(options, &change.signal_name, change.value), change.line),
+			None => {
+				let message = format!("skipping value change for undeclared signal '{}'", change.signal_name);
+				diagnostics.report(Diagnostic { line: change.line, ..Diagnostic::new("undeclared-signal", message) });
+				Ok(())
+			}
+		},
+		LineItem::Directive(timestamp, directive) => changes.directive(writer, timestamp, directive, &ids, diagnostics)
+	}, diagnostics)?;
+
+	if reader.last.is_none() {
+		no_value_changes(options, diagnostics)?;
+	}
+	check_drop_rate(options, diagnostics)?;
+
+	let last = changes.end;
+	if interrupted && options.end_time.is_none() {
+		diagnostics.report(Diagnostic::new("interrupted", format!("interrupted, the VCD ends at {}", changes.end + 1)));
+		let end = changes.end + 1;
+		changes.timestamp(writer, end)?;
+	}
+	else {
+		write_end_time(options, writer, &mut changes)?;
+	}
+	write_end_comment(options, writer, last)?;
+	wave.finish()?;
+	drop(wave);
+	if !check.is_empty() {
+		self_check::verify(&check[..], &changes.written)?;
+	}
+	changes.write_index()?;
+	info!("emitted {} value changes", changes.emitted);
+	Ok(changes.written)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use std::sync::{ Mutex, Once };
+	use crate::temp::TempPath;
+
+	fn try_run(args: &[&str], input: &str) -> io::Result<String> {
+		let mut argv = vec!["log2vcd", "-u", "ns"];
+		argv.extend_from_slice(args);
+		let options = Options::from_iter(argv);
+		let mut output = Vec::new();
+		convert(&options, input.as_bytes(), &mut output, &mut Diagnostics::new(options.diagnostics))?;
+		Ok(String::from_utf8(output).unwrap())
+	}
+
+	fn run(args: &[&str], input: &str) -> String {
+		try_run(args, input).unwrap()
+	}
+
+	fn timestamps(vcd: &str) -> Vec<&str> {
+		vcd.lines().filter(|l| l.starts_with('#')).collect()
+	}
+
+	#[test]
+	fn head() {
+		let input = "#10 a 1 1\n#20 a 0 1\n#20 b 1 1\n#30 a 1 1\n#40 b 0 1\n";
+		let result = run(&["--head", "2"], input);
+		assert_eq!(vec!["#0", "#10", "#20", "#20"], timestamps(&result));
+		// the lines past the head aren't read when the log is in the order of the output
+		let invalid = format!("{}not a value change\n", input);
+		assert!(try_run(&["--head", "2", "--strict", "--preserve-order"], &invalid).is_ok());
+		assert!(try_run(&["--head", "2", "--strict"], &invalid).is_err());
+
+		// the head is of the sorted log
+		let unsorted = "#30 a 1 1\n#10 a 0 1\n#20 a 1 1\n";
+		assert_eq!(vec!["#0", "#10"], timestamps(&run(&["--head", "1"], unsorted)));
+		assert_eq!(vec!["#0", "#10"], timestamps(&run(&["--head", "1", "--external-sort"], unsorted)));
+		assert_eq!(vec!["#0", "#30"], timestamps(&run(&["--head", "1", "--preserve-order"], unsorted)));
+
+		// a dropped value change doesn't count towards the head, read in memory or streamed
+		let dropped = "#10 r nan f\n#20 r 1.5 f\n#30 r 2.5 f\n";
+		let vars = TempPath::with_contents("head_vars", "r real 64\n");
+		assert!(run(&["--head", "1", "--preserve-order"], dropped).ends_with("#20\nr1.5 !\n"));
+		assert!(run(&["--head", "1", "--vars", vars.to_str().unwrap()], dropped).ends_with("#20\nr1.5 !\n"));
+	}
+
+	#[test]
+	fn interrupted_streaming() {
+		use std::sync::Arc;
+		use std::sync::atomic::{ AtomicBool, Ordering };
+
+		/// Interrupts the conversion while it waits for more input after the first line.
+		struct Ctrlc(Arc<AtomicBool>, &'static [u8]);
+		impl Read for Ctrlc {
+			fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+				if !self.1.is_empty() {
+					return self.1.read(buf);
+				}
+				self.0.store(true, Ordering::SeqCst);
+				loop {
+					std::thread::park();
+				}
+			}
+		}
+
+		let vars = TempPath::with_contents("interrupted-vars", "a wire 1\n");
+		let options = Options::from_iter(vec!["log2vcd", "-u", "ns", "--vars", vars.to_str().unwrap(), "--diagnostics", "json"]);
+		let flag = Arc::new(AtomicBool::new(false));
+		let input = Interruptible::new(Ctrlc(flag.clone(), b"#10 a 1 1\n"), flag);
+		let mut output = Vec::new();
+		convert(&options, BufReader::with_capacity(16, input), &mut output, &mut Diagnostics::new(options.diagnostics)).unwrap();
+		let result = String::from_utf8(output).unwrap();
+		assert_eq!(vec!["#0", "#10", "#11"], timestamps(&result));
+
+		let mut parser = vcd::Parser::new(result.as_bytes());
+		parser.parse_header().unwrap();
+		assert!(parser.all(|command| command.is_ok()));
+	}
+
+	#[test]
+	fn vars_file_streaming() {
+		let vars = TempPath::with_contents("vars", "a wire 1\nbus integer 4\n");
+		let vars = vars.to_str().unwrap();
+		// the log is streamed as-is, so out of order lines are not sorted
+		let input = "#10 a 1 1\n#20 bus 1010 4\n#15 a 0 1\n#30 c 1 1\n";
+		let result = run(&["--vars", vars], input);
+		assert!(result.contains("$var wire 1 ! a $end"));
+		assert!(result.contains("$var integer 4 \" bus $end"));
+		assert!(!result.contains(" c $end"));
+		assert_eq!(vec!["#0", "#10", "#20", "#15"], timestamps(&result));
+	}
+
+	#[test]
+	fn vars_file_head() {
+		let vars = TempPath::with_contents("vars_head", "a wire 1\n");
+		let input = "#10 a 1 1\n#20 a 0 1\n#30 a 1 1\n";
+		let result = run(&["--vars", vars.to_str().unwrap(), "--head", "2"], input);
+		assert_eq!(vec!["#0", "#10", "#20"], timestamps(&result));
+
+		let result = run(&["--vars", vars.to_str().unwrap(), "--head", "2", "--trailing-timestamp"], input);
+		assert_eq!(vec!["#0", "#10", "#20", "#21"], timestamps(&result));
+	}
+
+	#[test]
+	fn invalid_vars_file() {
+		let vars = TempPath::with_contents("vars_invalid", "a wire\n");
+		assert!(try_run(&["--vars", vars.to_str().unwrap()], "").is_err());
+		let vars = TempPath::with_contents("vars_twice", "a wire 1\nb wire 1\na integer 8\n");
+		let error = try_run(&["--vars", vars.to_str().unwrap()], "").unwrap_err();
+		assert_eq!("line 3: variable 'a' is declared twice", error.to_string());
+	}
+
+	#[test]
+	fn real_as_fixed() {
+		let result = run(&["--real-as-fixed", "4"], "#10 r 1.5 f\n");
+		assert!(result.contains("$var integer 32 ! r $end"));
+		assert!(result.contains("b11000 !"));
+	}
+
+	#[test]
+	fn real_scale() {
+		let input = "#10 volts 1.2345 f\n#20 volts -0.5 f\n#30 other 1.5 f\n";
+		let result = run(&["--real-scale", "volts=1000", "--fixed-width", "16"], input);
+		assert!(result.starts_with("$comment\n    real-scale volts 1000\n$end\n"));
+		assert!(result.contains("$var integer 16 ! volts $end"));
+		assert!(result.contains("$var real 32 \" other $end"));
+		// 1235 and -500 as 16 bit integers
+		assert!(result.contains("#10\nb10011010011 !\n#20\nb1111111000001100 !\n#30\nr1.5 \"\n"));
+		assert!(parse_real_scale("volts=fast").is_err());
+	}
+
+	#[test]
+	fn real_sentinel() {
+		let input = "#10 probe 1.5 f\n#20 probe -999 f\n#30 probe 2 f\n#40 gap nan f\n#50 gap 0.5 f\n#60 other -999 f\n";
+		let result = run(&["--real-sentinel", "probe=-999", "--real-sentinel", "gap=nan"], input);
+		assert!(result.ends_with("#10\nr1.5 !\n#30\nr2 !\n#50\nr0.5 \"\n#60\nr-999 #\n"));
+		assert!(parse_real_sentinel("probe=none").is_err());
+	}
+
+	#[test]
+	fn expect_signals() {
+		let expected = TempPath::with_contents("expected", "a\nbus\n\nspare\n");
+		let path = expected.to_str().unwrap();
+		let input = "#10 a 1 1\n#20 bus 1010 4\n";
+		assert_eq!(run(&[], input), run(&["--expect-signals", path], input));
+		let error = try_run(&["--expect-signals", path], "#10 a 1 1\n#20 new 1 1\n#30 extra 1 1\n").unwrap_err();
+		assert_eq!("the log has signals that aren't expected: extra, new", error.to_string());
+		let error = try_run(&["--expect-signals", path, "--expect-exact"], input).unwrap_err();
+		assert_eq!("the log is missing expected signals: spare", error.to_string());
+		assert!(try_run(&["--expect-signals", path, "--expect-exact"], "#10 a 1 1\n#20 bus 1010 4\n#30 spare 0 1\n").is_ok());
+	}
+
+	#[test]
+	fn trailing_timestamp() {
+		let input = "#10 a 1 1\n#20 a 0 1\n";
+		assert_eq!(vec!["#0", "#10", "#20"], timestamps(&run(&[], input)));
+		let result = run(&["--trailing-timestamp"], input);
+		assert_eq!(vec!["#0", "#10", "#20", "#21"], timestamps(&result));
+		assert!(result.ends_with("0!\n#21\n"));
+	}
+
+	#[test]
+	fn pad_end() {
+		let input = "#10 a 1 1\n#20 bus 1010 4\n#30 a 0 1\n";
+		assert!(run(&["--pad-end"], input).ends_with("#30\n0!\n"));
+		assert!(run(&["--pad-end", "--end-time", "30"], input).ends_with("#30\n0!\n"));
+		assert!(run(&["--pad-end", "--end-time", "50"], input).ends_with("#30\n0!\n#50\n0!\nb1010 \"\n"));
+		assert!(run(&["--pad-end", "--trailing-timestamp"], input).ends_with("#30\n0!\n#31\n0!\nb1010 \"\n"));
+	}
+
+	#[test]
+	fn snapshot() {
+		let input = "#10 a 1 1\n#20 bus 1010 4\n#25 $comment note\n#30 a 0 1\n#40 bus 1 4\n#40 a 1 1\n";
+		let result = run(&["--snapshot"], input);
+		assert!(result.ends_with("$enddefinitions $end\n#40\n$dumpvars\n1!\nb1 \"\n$end\n"));
+		assert!(run(&["--snapshot", "--end-time", "100"], input).ends_with("$enddefinitions $end\n#100\n$dumpvars\n1!\nb1 \"\n$end\n"));
+	}
+
+	#[test]
+	fn end_time() {
+		let input = "#10 a 1 1\n#20 a 0 1\n";
+		assert_eq!(vec!["#0", "#10", "#20", "#100"], timestamps(&run(&["--end-time", "100"], input)));
+
+		assert!(try_run(&["--end-time", "15"], input).is_err());
+	}
+
+	#[test]
+	fn end_comment() {
+		let input = "#10 a 1 1\n#30 a 0 1\n#20 a 1 1\n";
+		assert!(run(&["--emit-end-comment"], input).ends_with("#30\n0!\n$comment\n    end_time=30\n$end\n"));
+		assert!(run(&["--emit-end-comment", "--end-time", "50"], input).ends_with("#50\n$comment\n    end_time=30\n$end\n"));
+	}
+
+	#[test]
+	fn timestamp_ranges() {
+		let input = "#100-#200 sig 1 1\n#150-#300 bus 1010 4\n#250 sig 0 1\n#300-#400 r 1.5 f\n";
+		let hold = run(&["--timestamp-ranges", "hold"], input);
+		assert_eq!(vec!["#0", "#100", "#150", "#250", "#300"], timestamps(&hold));
+		let unknown = run(&["--timestamp-ranges", "unknown"], input);
+		assert!(unknown.contains("#200\nx!\n"));
+		assert!(unknown.contains("#300\nbx \"\n"));
+		// reals have no unknown value
+		assert!(!unknown.contains("#400"));
+		assert!(Options::from_iter_safe(vec!["log2vcd", "--timestamp-ranges", "always"]).is_err());
+		assert!(!run(&[], input).contains("#100"));
+	}
+
+	#[test]
+	fn snap_mode() {
+		let input = "#1400 a 1 1\n#1600 b 1 1\n";
+		let modes = [("nearest", vec!["#0", "#1", "#2"]), ("floor", vec!["#0", "#1", "#1"]), ("ceil", vec!["#0", "#2", "#2"])];
+		for (mode, expected) in modes.iter() {
+			assert_eq!(*expected, timestamps(&run(&["--input-time-unit", "ps", "--snap-mode", mode], input)));
+		}
+		assert!(Options::from_iter_safe(vec!["log2vcd", "--snap-mode", "up"]).is_err());
+	}
+
+	#[test]
+	fn canonical() {
+		let input = "#10 b 1 1\n#10 a 0 1\n#0 bus 1010 4\n#20 a 1 1\n#20 r 1.5 f\n#20 b 0 1\n";
+		let result = run(&["--canonical"], input);
+		assert_eq!(result, run(&["--canonical"], input));
+		assert!(result.contains("$var wire 1 ! a $end\n$var wire 1 \" b $end\n$var integer 4 # bus $end\n$var real 32 $ r $end\n"));
+		assert_eq!(vec!["#0", "#10", "#20"], timestamps(&result));
+		assert!(result.ends_with("#10\n0!\n1\"\n#20\n1!\n0\"\nr1.5 $\n"));
+	}
+
+	#[test]
+	fn canonical_line_order() {
+		let input = "#10 b 1 1\n#10 a 0 1\n#0 bus 1010 4\n#20 a 1 1\n#20 r 1.5 f\n#20 b 0 1\n";
+		let result = run(&["--canonical"], input);
+		let mut lines: Vec<&str> = input.lines().collect();
+		lines.reverse();
+		assert_eq!(result, run(&["--canonical"], &(lines.join("\n") + "\n")));
+		assert_ne!(run(&[], input), run(&[], &(lines.join("\n") + "\n")));
+
+		// the changes of a signal at the same timestamp are ordered by their value
+		let twice = "#10 a 1 1\n#10 a 0 1\n#10 bus 0011 4\n#10 bus 1100 4\n";
+		let mut lines: Vec<&str> = twice.lines().collect();
+		lines.reverse();
+		assert_eq!(run(&["--canonical"], twice), run(&["--canonical"], &(lines.join("\n") + "\n")));
+	}
+
+	#[test]
+	fn tick_comment() {
+		let result = run(&["--emit-tick-comment", "--step_size", "10"], "#1 a 1 1\n");
+		assert!(result.contains("$enddefinitions $end\n$comment\n    1 tick = 10 ns\n$end\n"));
+		let options = Options::from_iter(vec!["log2vcd", "-u", "us", "--emit-tick-comment"]);
+		let mut output = Vec::new();
+		convert(&options, "#1 a 1 1\n".as_bytes(), &mut output, &mut Diagnostics::new(options.diagnostics)).unwrap();
+		assert!(String::from_utf8(output).unwrap().contains("$comment\n    1 tick = 1 us\n$end\n"));
+		assert!(!run(&[], "#1 a 1 1\n").contains("tick"));
+	}
+
+	#[test]
+	fn strip_prefix() {
+		let input = "[core] #10 sig 1 1\n[dma] #20 sig 0 1\n#30 sig 1 1\n#40 [core] 0 1\n";
+		let result = run(&["--strip-prefix", r"\[\w+\] "], input);
+		assert_eq!(vec!["#0", "#10", "#20", "#30"], timestamps(&result));
+		assert!(result.ends_with("#10\n1!\n#20\n0!\n#30\n1!\n"));
+
+		// only a prefix at the start of the line is stripped
+		let result = run(&["--strip-prefix", r"\[core\] "], "#10 sig 1 1\n#20 [core] 0 1\n");
+		assert_eq!(vec!["#0", "#10"], timestamps(&result));
+
+		// the columns point into the logged line
+		let error = try_run(&["--strict", "--strip-prefix", r"\[\w+\] "], "[core] #10 sig 2 1\n").unwrap_err();
+		assert_eq!("line 1, column 16: value doesn't match its value type [invalid-value]: '[core] #10 sig 2 1'", error.to_string());
+	}
+
+	#[test]
+	fn no_nest_pattern() {
+		let input = "#10 peer10.0.0.1 1 1\n#10 core.alu.zero 1 1\n";
+		let result = run(&["--no-nest-pattern", r"\d+(\.\d+){3}$"], input);
+		assert!(result.contains("$var wire 1 ! peer10.0.0.1 $end"));
+		assert!(result.contains("$scope module core $end\n$scope module alu $end\n$var wire 1 \" zero $end"));
+		assert!(!result.contains("$scope module peer10 $end"));
+	}
+
+	#[test]
+	fn scope_separator() {
+		let input = "#10 cpu/alu/zero 1 1\n#10 cpu/pc 1010 4\n#20 core::busy 1 1\n";
+		let result = run(&["--scope-separator", "/"], input);
+		assert!(result.contains("$scope module cpu $end\n$scope module alu $end\n$var wire 1 ! zero $end\n$upscope $end\n$var integer 4 \" pc $end\n$upscope $end\n"));
+		// the colons aren't a separator
+		assert!(!result.contains("busy"));
+		let result = run(&["--scope-separator", "::"], input);
+		assert!(result.contains("$scope module core $end\n$var wire 1 ! busy $end"));
+		assert!(!result.contains("zero"));
+		assert!(!run(&[], input).contains("$var"));
+	}
+
+	#[test]
+	fn balanced_scopes() {
+		let input = "#10 soc.cpu.alu.zero 1 1\n#10 soc.cpu.alu.carry 0 1\n#10 soc.cpu.pc 1010 4\n#10 soc.dma.busy 1 1\n#10 top 1 1\n";
+		let result = run(&[], input);
+		let header = &result[..result.find("$enddefinitions").unwrap()];
+		let mut depth = 0;
+		let mut deepest = 0;
+		for line in header.lines() {
+			if line.starts_with("$scope") {
+				depth += 1;
+				deepest = deepest.max(depth);
+			} else if line.starts_with("$upscope") {
+				assert!(depth > 0, "unmatched $upscope in {}", header);
+				depth -= 1;
+			}
+		}
+		assert_eq!(0, depth, "unmatched $scope in {}", header);
+		// outputs, soc, cpu, alu
+		assert_eq!(4, deepest);
+		assert!(header.contains("$scope module alu $end\n$var wire 1 ! zero $end\n$var wire 1 \" carry $end\n$upscope $end\n\
+			$var integer 4 # pc $end\n$upscope $end\n$scope module dma $end\n$var wire 1 $ busy $end\n$upscope $end\n$upscope $end\n\
+			$var wire 1 % top $end\n$upscope $end\n"));
+	}
+
+	#[test]
+	fn nested_scopes() {
+		let options = Options::from_iter(vec!["log2vcd", "-u", "ns"]);
+		let mut variables = Variables::new();
+		let mut id = IdCode::FIRST;
+		for name in &["a.b", "a.c", "x.out", "y.out", "top"] {
+			variables.insert(String::from(*name), (VarType::Wire, 1, id, None));
+			id = id.next();
+		}
+		let var = |reference: &str, name: &str| ScopeItem::Var(Var { var_type: VarType::Wire, size: 1, code: variables[name].2, reference: String::from(reference) });
+		let scope = |identifier: &str, children: Vec<ScopeItem>| ScopeItem::Scope(Scope { scope_type: ScopeType::Module, identifier: String::from(identifier), children });
+		assert_eq!(vec![scope("outputs", vec![
+			scope("a", vec![var("b", "a.b"), var("c", "a.c")]),
+			scope("x", vec![var("out", "x.out")]),
+			scope("y", vec![var("out", "y.out")]),
+			var("top", "top")
+		])], scopes(&options, &variables).unwrap());
+
+		// the names of --vars aren't parsed
+		for name in &[".a", "a.", "a..b"] {
+			let variables: Variables = vec![(String::from(*name), (VarType::Wire, 1, IdCode::FIRST, None))].into_iter().collect();
+			let error = scopes(&options, &variables).unwrap_err();
+			assert!(error.to_string().contains("has an empty scope name"), "{}", error);
+		}
+		// the lines of a log with an empty scope are dropped
+		let mut diagnostics = Diagnostics::new(DiagnosticsFormat::Json);
+		let mut output = Vec::new();
+		convert(&options, "#10 a. 1 1\n#10 b 1 1\n#20 .a 0 1\n".as_bytes(), &mut output, &mut diagnostics).unwrap();
+		assert!(String::from_utf8(output).unwrap().contains("$scope module outputs $end\n$var wire 1 ! b $end\n$upscope $end\n"));
+		let lines: Vec<serde_json::Value> = diagnostics.to_json().as_array().unwrap().iter().map(|d| d["line"].clone()).collect();
+		assert_eq!(vec![json!(1), json!(3)], lines);
+		assert_eq!("invalid-signal-name", diagnostics.to_json()[0]["code"]);
+		// an undotted signal is declared in the top scope directly, nested or not
+		assert!(run(&[], "#10 a 1 1\n").contains("$scope module outputs $end\n$var wire 1 ! a $end\n$upscope $end\n"));
+		assert!(run(&["--flat"], "#10 a 1 1\n").contains("$scope module outputs $end\n$var wire 1 ! a $end\n$upscope $end\n"));
+		assert!(run(&["--flat"], "#10 a.b 1 1\n").contains("$scope module outputs $end\n$var wire 1 ! a.b $end\n$upscope $end\n"));
+		assert!(run(&["--flat"], "#10 a. 1 1\n").contains("$var wire 1 ! a. $end"));
+	}
+
+	#[test]
+	fn scope_type() {
+		assert!(run(&[], "#10 a 1 1\n").contains("$scope module outputs $end"));
+		let result = run(&["--scope-type", "outputs=task"], "#10 a 1 1\n");
+		assert!(result.contains("$scope task outputs $end"));
+		assert!(parse_scope_type("outputs=class").is_err());
+		assert!(parse_scope_type("outputs").is_err());
+	}
+
+	#[test]
+	fn var_type() {
+		let input = "#10 a 1 1\n#10 bus 1010 4\n#10 r 1.5 f\n";
+		let result = run(&["--var-type", "bus=reg"], input);
+		assert!(result.contains("$var wire 1 ! a $end\n$var reg 4 \" bus $end\n$var real 32 # r $end"));
+		assert!(run(&["--var-type", "reg", "--var-type", "r=real"], input).contains("$var reg 1 ! a $end\n$var reg 4 \" bus $end"));
+		assert!(try_run(&["--var-type", "tri"], input).is_err());
+		assert!(try_run(&["--var-type", "a=real"], input).is_err());
+	}
+
+	#[test]
+	fn empty_input() {
+		let result = run(&[], "");
+		assert!(result.contains("$enddefinitions $end"));
+		assert_eq!(vec!["#0"], timestamps(&result));
+
+		assert!(try_run(&["--strict"], "").is_err());
+	}
+
+	#[test]
+	fn all_lines_malformed() {
+		let input = "not a value change\n#10 a 2 1\n";
+		assert_eq!(vec!["#0"], timestamps(&run(&[], input)));
+
+		assert!(try_run(&["--strict"], input).is_err());
+	}
+
+	#[test]
+	fn strict_lines() {
+		let input = "#10 a 1 1\n\n   \n#20 a 2 1\n#30 a 0 1\n";
+		assert_eq!(vec!["#0", "#10", "#30"], timestamps(&run(&[], input)));
+		let error = try_run(&["--strict"], input).unwrap_err();
+		assert_eq!("line 4, column 7: value doesn't match its value type [invalid-value]: '#20 a 2 1'", error.to_string());
+		// blank lines aren't errors
+		assert!(try_run(&["--strict"], "#10 a 1 1\n\n   \n#30 a 0 1\n").is_ok());
+
+		let options = Options::from_iter(vec!["log2vcd", "-u", "ns"]);
+		let mut diagnostics = Diagnostics::new(options.diagnostics);
+		convert(&options, input.as_bytes(), &mut Vec::new(), &mut diagnostics).unwrap();
+		let mut summary = Vec::new();
+		diagnostics.finish(&mut summary).unwrap();
+		assert_eq!("warning: skipped 1 of 3 lines\n", String::from_utf8(summary).unwrap());
+		// nothing of a streamed VCD is written before the error
+		let vars = TempPath::with_contents("strict_vars", "a wire 1\n");
+		let options = Options::from_iter(vec!["log2vcd", "-u", "ns", "--strict", "--vars", vars.to_str().unwrap()]);
+		let mut output = Vec::new();
+		assert!(convert(&options, input.as_bytes(), &mut output, &mut Diagnostics::new(options.diagnostics)).is_err());
+		assert!(output.is_empty());
+	}
+
+	#[test]
+	fn id_map() {
+		let map = TempPath::with_contents("id_map", "b !\n");
+		let input = "#10 a 1 1\n#20 b 1 1\n#30 c 1 1\n";
+		let result = run(&["--id-map", map.to_str().unwrap()], input);
+		assert!(result.contains("$var wire 1 ! b $end"));
+		assert!(result.contains("$var wire 1 \" a $end"));
+		assert!(result.contains("$var wire 1 # c $end"));
+	}
+
+	#[test]
+	fn order() {
+		let order = TempPath::with_contents("order", "c\n\nb\nmissing\n");
+		let input = "#10 a 1 1\n#20 d 1 1\n#30 b 1 1\n#40 c 1 1\n";
+		let result = run(&["--order", order.to_str().unwrap()], input);
+		let declared: Vec<&str> = result.lines().filter(|line| line.starts_with("$var")).collect();
+		assert_eq!(vec![
+			"$var wire 1 ! c $end",
+			"$var wire 1 \" b $end",
+			"$var wire 1 # a $end",
+			"$var wire 1 $ d $end"
+		], declared);
+	}
+
+	#[test]
+	fn order_by() {
+		let input = "#30 a 1 1\n#10 c 1 1\n#20 b 1 1\n#40 c 0 1\n";
+		let declared = |args: &[&str]| -> Vec<String> {
+			run(args, input).lines().filter(|line| line.starts_with("$var")).map(String::from).collect()
+		};
+		let first_seen = vec!["$var wire 1 ! c $end", "$var wire 1 \" b $end", "$var wire 1 # a $end"];
+		assert_eq!(first_seen, declared(&["--order-by", "first-seen"]));
+		assert_eq!(first_seen, declared(&[]));
+		assert_eq!(vec!["$var wire 1 ! a $end", "$var wire 1 \" b $end", "$var wire 1 # c $end"], declared(&["--order-by", "alphabetical"]));
+	}
+
+	#[test]
+	fn id_map_duplicate_id() {
+		let map = TempPath::with_contents("id_map_duplicate", "a !\nb !\n");
+		assert!(try_run(&["--id-map", map.to_str().unwrap()], "").is_err());
+	}
+
+	#[test]
+	fn dumpvars() {
+		let input = "#10 a 1 1\n#20 bus 1010 4\n#30 r 1.5 f\n";
+		let result = run(&["--dumpvars"], input);
+		assert!(result.contains("#0\n$dumpvars\nx!\nbx \"\n$end\n#10\n"));
+		assert!(!run(&[], input).contains("$dumpvars"));
+	}
+
+	#[test]
+	fn assume_initial() {
+		let input = "#10 a 0 1\n#20 bus 1010 4\n#5000 late 0 1\n";
+		let result = run(&["--dumpvars", "--assume-initial", "late=1"], input);
+		assert!(result.contains("#0\n$dumpvars\nx!\nbx \"\n1#\n$end\n"));
+
+		// without --dumpvars only the assumed values are written
+		let result = run(&["--assume-initial", "bus=1111"], input);
+		assert!(result.contains("#0\n$dumpvars\nb1111 \"\n$end\n"));
+
+		assert!(try_run(&["--assume-initial", "bus=12"], input).is_err());
+	}
+
+	#[test]
+	fn growing_widths() {
+		let result = run(&["--growing-widths", "--promote-width"], "#10 bus 1010 4\n#20 bus 11110000 8\n#30 bus 11110000 8\n");
+		assert!(result.contains("$var integer 8 ! bus $end"));
+		let error = try_run(&["--growing-widths", "--promote-width"], "#10 bus 11110000 8\n#20 bus 1010 4\n").unwrap_err();
+		assert_eq!("line 2: signal 'bus' shrinks from a width of 8 to 4", error.to_string());
+		assert!(try_run(&["--promote-width"], "#10 bus 11110000 8\n#20 bus 1010 4\n").is_ok());
+	}
+
+	#[test]
+	fn strength() {
+		let result = run(&[], "#10 a St1 1\n#20 a Pu0 1\n#30 a 1 1\n");
+		assert!(result.ends_with("#10\n$comment\n    strength a St1\n$end\n#10\n1!\n#20\n$comment\n    strength a Pu0\n$end\n#20\n0!\n#30\n1!\n"), "{}", result);
+		assert_eq!(result, run(&["--field-order", "width-value"], "#10 a 1 St1\n#20 a 1 Pu0\n#30 a 1 1\n"));
+		assert_eq!(result, run(&["--input-format", "kv"], "t=10 sig=a val=St1 w=1\nt=20 sig=a val=Pu0 w=1\nt=30 sig=a val=1 w=1\n"));
+	}
+
+	#[test]
+	fn reset_to_default() {
+		let input = "#10 a 1 1\n#10 bus 1010 4\n#20 a ! 1\n#20 bus ! 4\n";
+		let result = run(&["--assume-initial", "bus=0011"], input);
+		assert!(result.ends_with("#20\nx!\n#20\nb0011 \"\n"));
+		assert!(run(&["--field-order", "width-value"], "#10 a 1 1\n#20 a 1 !\n").ends_with("#20\nx!\n"));
+	}
+
+	#[test]
+	fn split_vectors() {
+		let input = "#10 bus 11110010 8\n#20 bus 1 8\n";
+		let result = run(&["--split-vectors"], input);
+		for i in 0..8 {
+			assert!(result.contains(&format!(" bus[{}] $end", i)));
+		}
+		assert!(!result.contains("$var integer 8"));
+		// the bits are grouped like array elements
+		assert!(result.contains("$scope module bus $end"));
+		// bus[0] is the least significant bit and got the first id
+		assert!(result.contains("$var wire 1 ! bus[0] $end"));
+		assert!(result.contains("#10\n0!\n"));
+		assert!(result.contains("#10\n1(\n"));
+		// the value at #20 is left extended with zeros
+		assert!(result.contains("#20\n1!\n"));
+		assert!(result.contains("#20\n0(\n"));
+	}
+
+	#[test]
+	fn unit() {
+		for unit in &["ns", "NS", "Ns", "nanosecond", "Nanoseconds"] {
+			assert_eq!(Ok(TimescaleUnit::NS), parse_unit(unit));
+		}
+		assert_eq!(Ok(TimescaleUnit::US), parse_unit("µs"));
+		assert_eq!(Ok(TimescaleUnit::S), parse_unit("SECONDS"));
+		assert_eq!(Ok(TimescaleUnit::FS), parse_unit("fs"));
+		assert!(parse_unit("ks").is_err());
+
+		let options = Options::from_iter(vec!["log2vcd", "-u", "PS"]);
+		let mut output = Vec::new();
+		convert(&options, "#1 a 1 1\n".as_bytes(), &mut output, &mut Diagnostics::new(DiagnosticsFormat::Text)).unwrap();
+		assert!(String::from_utf8(output).unwrap().contains("$timescale 1 ps $end"));
+	}
+
+	#[test]
+	fn no_time_zero() {
+		let result = run(&["--no-time-zero"], "#10 a 1 1\n#20 a 0 1\n");
+		assert_eq!(vec!["#10", "#20"], timestamps(&result));
+		assert!(result.contains("$enddefinitions $end\n#10\n"));
+
+		assert!(Options::from_iter_safe(vec!["log2vcd", "-u", "ns", "--no-time-zero", "--dumpvars"]).is_err());
+		assert!(Options::from_iter_safe(vec!["log2vcd", "-u", "ns", "--no-time-zero", "--assume-initial", "a=1"]).is_err());
+	}
+
+	#[test]
+	fn field_order() {
+		let input = "#10 bus 11110010 8\n#20 a 1 1\n";
+		let swapped = "#10 bus 8 11110010\n#20 a 1 1\n";
+		assert_eq!(run(&[], input), run(&["--field-order", "width-value"], swapped));
+	}
+
+	#[test]
+	fn columns() {
+		let input = "10 node1 bus 11110010 8 ok\n20 node1 a 1 1 ok\n";
+		let result = run(&["--columns", "1,3,4,5"], input);
+		assert_eq!(run(&[], "#10 bus 11110010 8\n#20 a 1 1\n"), result);
+		let states = TempPath::with_contents("column-states", "0 IDLE\n1 BUSY\n");
+		let result = run(&["--columns", "1,3,4,5", "--enum", &format!("state={}", states.to_str().unwrap())], "10 node1 state BUSY 1 ok\n");
+		assert!(result.ends_with("#10\n1!\n"));
+	}
+
+	#[test]
+	fn profile() {
+		let options = Options::from_iter(vec!["log2vcd", "-u", "ns", "--profile"]);
+		let mut diagnostics = Diagnostics::new(options.diagnostics).with_profile(options.profile);
+		let input = "#20 a 0 1\n#10 a 1 1\n";
+		let mut output = Vec::new();
+		convert(&options, input.as_bytes(), &mut output, &mut diagnostics).unwrap();
+		assert_eq!(run(&[], input), String::from_utf8(output).unwrap());
+
+		let mut profile = Vec::new();
+		diagnostics.finish(&mut profile).unwrap();
+		let phases: Vec<&str> = std::str::from_utf8(&profile).unwrap().lines()
+			.map(|line| line.rsplit_once(' ').unwrap().0)
+			.collect();
+		assert_eq!(vec!["profile: parsing", "profile: sorting", "profile: writing"], phases);
+	}
+
+	#[test]
+	fn json_diagnostics() {
+		let options = Options::from_iter(vec!["log2vcd", "-u", "ns", "--diagnostics", "json"]);
+		let mut diagnostics = Diagnostics::new(options.diagnostics);
+		let input = "#10 a 1 1\n#20 a 01 1\n\n#30 b 1.5 8\n";
+		convert(&options, input.as_bytes(), &mut Vec::new(), &mut diagnostics).unwrap();
+
+		let mut output = Vec::new();
+		diagnostics.finish(&mut output).unwrap();
+		let expected = json!([
+			{
+				"line": 2,
+				"column": 7,
+				"code": "multi-bit-scalar",
+				"message": "scalar value must be a single 0, 1, x or z",
+				"text": "#20 a 01 1"
+			},
+			{
+				"line": 4,
+				"column": 7,
+				"code": "invalid-value",
+				"message": "value doesn't match its value type",
+				"text": "#30 b 1.5 8"
+			}
+		]);
+		assert_eq!(expected, serde_json::from_slice::<serde_json::Value>(&output).unwrap());
+
+		// the explanations are in the JSON instead of before it
+		let options = Options::from_iter(vec!["log2vcd", "-u", "ns", "--diagnostics", "json", "--explain", "1"]);
+		let mut diagnostics = Diagnostics::new(options.diagnostics);
+		convert(&options, input.as_bytes(), &mut Vec::new(), &mut diagnostics).unwrap();
+		let json = diagnostics.to_json();
+		assert!(json[0]["explanation"].as_str().unwrap().starts_with("line 2: #20 a 01 1\n  error: "));
+		assert!(json[1].get("explanation").is_none());
+	}
+
+	#[test]
+	fn alias() {
+		let input = "#10 a 1 1\n#20 b 0 1\n#30 a 1 1\n";
+		let result = run(&["--alias", "a=b"], input);
+		assert!(result.contains("$var wire 1 ! a $end"));
+		assert!(!result.contains(" b $end"));
+		assert!(result.contains("#10\n1!\n#20\n0!\n#30\n1!\n"));
+
+		assert!(try_run(&["--alias", "a=b"], "#10 a 1 1\n#20 b 0101 4\n").is_err());
+	}
+
+	#[test]
+	fn enum_states() {
+		let states = TempPath::with_contents("states", "0 IDLE\n1 BUSY\n2 DONE\n");
+		let input = "#10 state IDLE 2\n#20 state BUSY 2\n#30 state 10 2\n#40 state DONE 2\n";
+		let result = run(&["--enum", &format!("state={}", states.to_str().unwrap())], input);
+		assert!(result.starts_with("$comment\n    enum state 0=IDLE 1=BUSY 2=DONE\n$end\n"));
+		assert!(result.ends_with("#10\nb0 !\n#20\nb1 !\n#30\nb10 !\n#40\nb10 !\n"));
+
+		// the other fields keep their columns
+		assert_eq!("#10  state 1\t2", replace_field("#10  state BUSY\t2", 2, "1"));
+	}
+
+	#[test]
+	fn radix() {
+		let input = "#10 bus 1010 4\n#10 count 11 2\n";
+		let result = run(&["--radix", "bus=hex", "--radix", "count=dec"], input);
+		assert!(result.starts_with("$comment\n    radix bus hex\n    radix count dec\n$end\n$timescale"));
+		assert!(Options::from_iter_safe(vec!["log2vcd", "-u", "ns", "--radix", "bus=oct"]).is_err());
+	}
+
+	#[test]
+	fn self_check() {
+		let input = "#10 a 1 1\n#20 bus 0011 4\n#30 r 1.5 f\n#40 a z 1\n#50 $comment done\n";
+		assert_eq!(run(&[], input), run(&["--self-check"], input));
+		run(&["--self-check", "--dumpvars", "--split-vectors"], input);
+	}
+
+	#[test]
+	fn compact() {
+		let input = "#10 bus zzzz 4\n#20 bus 0zzz 4\n#30 bus xxxx 4\n";
+		let expanded = run(&[], input);
+		assert!(expanded.contains("#10\nbzzzz !\n#20\nb0zzz !\n#30\nbxxxx !\n"));
+		let compact = run(&["--compact", "--self-check"], input);
+		assert!(compact.contains("#10\nbz !\n#20\nb0zzz !\n#30\nbx !\n"));
+	}
+
+	#[test]
+	fn max_line_len() {
+		let input = format!("#10 a 1 1\n#20 blob {} 4096\n#30 a 0 1\n", "1".repeat(4096));
+		let result = run(&["--max-line-len", "1024"], &input);
+		assert_eq!(vec!["#0", "#10", "#30"], timestamps(&result));
+		assert!(!result.contains("blob"));
+		let error = try_run(&["--max-line-len", "1024", "--strict"], &input).unwrap_err();
+		assert!(error.to_string().starts_with("line 2: "));
+	}
+
+	#[test]
+	fn line_ending() {
+		let input = "#10 a 1 1\n";
+		let lf = run(&[], input);
+		assert!(!lf.contains('\r'));
+		assert!(lf.ends_with("#10\n1!\n"));
+		let crlf = run(&["--line-ending", "crlf", "--self-check"], input);
+		assert_eq!(lf.replace('\n', "\r\n"), crlf);
+	}
+
+	#[test]
+	fn allow_numeric_names() {
+		let input = "#10 3 1 1\n#20 3 0 1\n";
+		assert!(try_run(&["--strict"], input).is_err());
+		let result = run(&["--allow-numeric-names"], input);
+		assert!(result.contains("$var wire 1 ! 3 $end"));
+		assert_eq!(vec!["#0", "#10", "#20"], timestamps(&result));
+	}
+
+	#[test]
+	fn single_bit_vars() {
+		let input = "#10 a 1 1\n#10 b b1 1\n#10 c 'b1 1\n#10 d 0 1s\n#10 e - 1\n#10 f true 1\n";
+		let result = run(&[], input);
+		for name in &["a", "b", "c", "d", "e", "f"] {
+			assert!(result.lines().any(|line| line.starts_with("$var wire 1 ") && line.ends_with(&format!(" {} $end", name))), "{}", name);
+		}
+		assert!(!result.contains("$var integer"));
+	}
+
+	#[test]
+	fn assume_width() {
+		let input = "#10 a 1\n#20 a 0\n#20 b 101 4\n";
+		assert!(!run(&[], input).contains(" a $end"));
+		let result = run(&["--assume-width", "1"], input);
+		assert!(result.contains("$var wire 1 ! a $end"));
+		assert!(result.ends_with("#10\n1!\n#20\n0!\n#20\nb101 \"\n"));
+	}
+
+	#[test]
+	fn gtkw() {
+		let path = TempPath::new("signals.gtkw");
+		let input = "#10 clk 1 1\n#10 bus 1010 4\n#10 mem[0] 11 2\n#10 r 1.5 f\n";
+		run(&["--gtkw", path.to_str().unwrap(), "--radix", "mem[0]=dec"], input);
+		let gtkw = std::fs::read_to_string(&path).unwrap();
+		assert_eq!("[treeopen] outputs.\n@28\noutputs.clk\n@22\noutputs.bus[3:0]\n[treeopen] outputs.mem.\n\
+			@24\noutputs.mem.mem[0][1:0]\n@420\noutputs.r\n", gtkw);
+
+		run(&["--gtkw", path.to_str().unwrap(), "--color", "clk=green", "--color", "r=red"], input);
+		let gtkw = std::fs::read_to_string(&path).unwrap();
+		assert!(gtkw.starts_with("[treeopen] outputs.\n[color] 4\n@28\noutputs.clk\n@22\noutputs.bus[3:0]\n"));
+		assert!(gtkw.ends_with("[color] 1\n@420\noutputs.r\n"));
+		assert!(parse_color("clk=pink").is_err());
+
+		// the hints are of the full signal name, not of the reference within its scope
+		run(&["--gtkw", path.to_str().unwrap(), "--radix", "x.out=dec", "--color", "y.out=red"], "#10 x.out 1010 4\n#10 y.out 0101 4\n");
+		let gtkw = std::fs::read_to_string(&path).unwrap();
+		assert_eq!("[treeopen] outputs.\n[treeopen] outputs.x.\n@24\noutputs.x.out[3:0]\n[treeopen] outputs.y.\n[color] 1\n@22\noutputs.y.out[3:0]\n", gtkw);
+	}
+
+	#[test]
+	fn min_width() {
+		let result = run(&["--min-width", "8"], "#0 bus 101 3\n#1 bus x 3\n#2 a 1 1\n#3 wide 1 10\n");
+		assert!(result.contains("$var integer 8 ! bus $end"));
+		assert!(result.contains("$var wire 1 \" a $end"));
+		assert!(result.contains("$var integer 10 # wide $end"));
+		assert!(result.contains("b00000101 !"));
+		assert!(result.contains("b00000xxx !"));
+	}
+
+	#[test]
+	fn promote_width() {
+		let input = "#10 a 1 1\n#20 a 10101010 8\n#30 a 0 1\n#40 a 1111 4\n";
+		let result = run(&["--promote-width", "--self-check"], input);
+		assert!(result.contains("$var integer 8 ! a $end"));
+		assert!(result.contains("#10\nb1 !\n#20\nb10101010 !\n#30\nb0 !\n#40\nb1111 !\n"));
+		let result = run(&["--promote-width", "--self-check"], "#10 a x 1\n#20 a 1010 4\n#30 a z 1\n");
+		assert!(result.contains("#10\nbxxxx !\n#20\nb1010 !\n#30\nbzzzz !\n"));
+		assert!(run(&["--promote-width"], "#10 r 1.5 f\n#20 r 2.5 f\n").contains("$var real 32 ! r $end"));
+	}
+
+	#[test]
+	fn type_change() {
+		let input = "#1 a 1 1\n#2 a 10100000 8\n#3 a 1.5 f\n";
+		let options = Options::from_iter(vec!["log2vcd", "-u", "ns"]);
+		let mut diagnostics = Diagnostics::new(DiagnosticsFormat::Json);
+		let mut output = Vec::new();
+		convert(&options, input.as_bytes(), &mut output, &mut diagnostics).unwrap();
+		let result = String::from_utf8(output).unwrap();
+		assert!(!result.contains("r1.5"));
+		let reported: Vec<(serde_json::Value, serde_json::Value)> = diagnostics.to_json().as_array().unwrap().iter()
+			.map(|d| (d["line"].clone(), d["code"].clone()))
+			.collect();
+		assert_eq!(vec![(json!(2), json!("exceeds-width")), (json!(3), json!("type-change"))], reported);
+		assert!(diagnostics.to_json()[1]["message"].as_str().unwrap().ends_with("signal 'a' has a real value, but a scalar or vector one on line 1"));
+
+		let error = try_run(&["--strict", "--promote-width"], input).unwrap_err();
+		assert_eq!("line 3: signal 'a' has a real value, but a scalar or vector one on line 1", error.to_string());
+		assert!(try_run(&["--strict", "--split-by-width"], input).unwrap().contains("$var real 32 # a@f $end"));
+
+		// declared variables can't change their type either
+		let vars = TempPath::with_contents("type_change_vars", "a wire 1\n");
+		let error = try_run(&["--strict", "--vars", vars.to_str().unwrap()], "#1 a 1 1\n#3 a 1.5 f\n").unwrap_err();
+		assert_eq!("line 2: signal 'a' has a real value, but is declared with a scalar or vector one", error.to_string());
+	}
+
+	#[test]
+	fn time_index() {
+		let path = TempPath::new("index.txt");
+		let input = "#10 a 1 1\n#20 $comment note\n#20 a 0 1\n#30 b 101 4\n";
+		for args in &[&["--time-index", path.to_str().unwrap()][..], &["--time-index", path.to_str().unwrap(), "--line-ending", "crlf"]] {
+			let result = run(args, input);
+			let index = std::fs::read_to_string(&path).unwrap();
+			let timestamps: Vec<&str> = index.lines().map(|line| line.split(' ').next().unwrap()).collect();
+			assert_eq!(vec!["0", "10", "20", "30"], timestamps);
+			for line in index.lines() {
+				let (timestamp, offset) = line.split_at(line.find(' ').unwrap());
+				let offset: usize = offset.trim().parse().unwrap();
+				assert!(result[offset..].starts_with(&format!("#{}\r\n", timestamp)) || result[offset..].starts_with(&format!("#{}\n", timestamp)));
+			}
+		}
+	}
+
+	#[test]
+	fn compress_sidecar() {
+		let path = TempPath::new("sidecar.json");
+		let compressed_path = TempPath::new("sidecar.json.gz");
+		let input = "#10 a 1 1\n#20 a 0 1\n";
+		run(&["--summary-json", path.to_str().unwrap()], input);
+		let plain = std::fs::read_to_string(&path).unwrap();
+		run(&["--summary-json", path.to_str().unwrap(), "--compress-sidecar"], input);
+		assert_eq!(*compressed_path, *compress::sidecar_path(&path, true));
+		let compressed = std::fs::read(&compressed_path).unwrap();
+		assert!(compressed.starts_with(&[0x1f, 0x8b]));
+		let mut decompressed = String::new();
+		compress::decompress(&compressed[..]).unwrap().read_to_string(&mut decompressed).unwrap();
+		assert_eq!(plain, decompressed);
+		assert!(plain.contains("\"value_changes\":2"));
+	}
+
+	#[test]
+	fn summary_json() {
+		let path = TempPath::new("summary.json");
+		let input = "#10 a 1 1\n\ngarbage\n#20 a 2 1\n#20 b 101 1\n#30 b 0 1\n";
+		run(&["--summary-json", path.to_str().unwrap()], input);
+		let summary: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+		assert_eq!(json!({
+			"lines": 5,
+			"value_changes": 2,
+			"dropped": 3,
+			"errors": { "invalid-format": 1, "invalid-value": 1, "multi-bit-scalar": 1 },
+			"signals": 2
+		}), summary);
+	}
+
+	#[test]
+	fn unknown_marker() {
+		let input = "#10 a 1 1\n#20 a 0 e\n";
+		assert!(try_run(&["--unknown-marker", "error"], input).is_err());
+		for policy in &["skip", "warn"] {
+			let options = Options::from_iter(vec!["log2vcd", "-u", "ns", "--unknown-marker", policy, "--diagnostics", "json"]);
+			let mut diagnostics = Diagnostics::new(options.diagnostics);
+			let mut output = Vec::new();
+			convert(&options, input.as_bytes(), &mut output, &mut diagnostics).unwrap();
+			assert_eq!(vec!["#0", "#10"], timestamps(&String::from_utf8(output).unwrap()));
+			assert_eq!(Some(&1), diagnostics.stats.dropped.get("unknown-marker"));
+			let reported = diagnostics.to_json().as_array().unwrap().len();
+			assert_eq!(if *policy == "warn" { 1 } else { 0 }, reported);
+		}
+	}
+
+	#[test]
+	fn report_dup_lines() {
+		let input = "#10 a 1 1\n#10 a 1 1\n#10 a 1 1\n\n#20 a 0 1\n#30 a 1 1\n#30 b 1 1\n#30 a 1 1\n#30 a 1 1\n";
+		let options = Options::from_iter(vec!["log2vcd", "-u", "ns", "--report-dup-lines", "--diagnostics", "json"]);
+		let mut diagnostics = Diagnostics::new(options.diagnostics);
+		let mut output = Vec::new();
+		convert(&options, input.as_bytes(), &mut output, &mut diagnostics).unwrap();
+		assert_eq!(Some(3), diagnostics.stats.duplicate_lines);
+		assert_eq!(json!(3), diagnostics.stats.to_json()["duplicate_lines"]);
+		let lines: Vec<serde_json::Value> = diagnostics.to_json().as_array().unwrap().iter().map(|d| d["line"].clone()).collect();
+		assert_eq!(vec![json!(2), json!(3), json!(9)], lines);
+		// the lines are still converted as usual
+		assert_eq!(run(&[], input), String::from_utf8(output).unwrap());
+	}
+
+	#[test]
+	fn exceeds_width() {
+		let input = "#10 bus 1010 4\n#20 bus 0 4\n#30 bus 10101010 8\n#40 r 1.5 f\n";
+		let options = Options::from_iter(vec!["log2vcd", "-u", "ns", "--diagnostics", "json"]);
+		let mut diagnostics = Diagnostics::new(options.diagnostics);
+		convert(&options, input.as_bytes(), &mut Vec::new(), &mut diagnostics).unwrap();
+		assert_eq!(json!([{
+			"line": 3,
+			"column": null,
+			"code": "exceeds-width",
+			"message": "signal 'bus' has a 8 bit value, wider than its width of 4 from line 1",
+			"text": null
+		}]), diagnostics.to_json());
+		assert!(try_run(&["--strict"], input).is_err());
+		assert!(try_run(&["--strict", "--promote-width"], input).is_ok());
+	}
+
+	#[test]
+	fn dump_parsed() {
+		let path = TempPath::new("parsed.bin");
+		let input = "#20 bus 1010 4\n#10 a 1 1\n#30 r 1.5 f\n#30 a x 1\n";
+		let dumped = run(&["--dump-parsed", path.to_str().unwrap()], input);
+		let loaded = run(&["--load-parsed", path.to_str().unwrap()], "");
+		assert_eq!(run(&[], input), dumped);
+		assert_eq!(dumped, loaded);
+
+		std::fs::write(&path, "#10 a 1 1\n").unwrap();
+		let error = try_run(&["--load-parsed", path.to_str().unwrap()], "").unwrap_err();
+		assert_eq!(io::ErrorKind::InvalidData, error.kind());
+		assert!(error.to_string().contains("isn't a --dump-parsed file"));
+	}
+
+	#[test]
+	fn watch() {
+		let input = TempPath::with_contents("watch.log", "#10 a 1 1\n");
+		let output = TempPath::new("watch.vcd");
+		let options = Options::from_iter(vec!["log2vcd", "-u", "ns", "--watch", "-i", input.to_str().unwrap(), "-o", output.to_str().unwrap()]);
+		let modified = watch::modified(&input).unwrap();
+		run_once(&options, None).unwrap();
+		assert_eq!(vec!["#0", "#10"], timestamps(&std::fs::read_to_string(&output).unwrap()));
+
+		let editor = {
+			let input = input.to_path_buf();
+			std::thread::spawn(move || watch::rewrite(&input, "#10 a 1 1\n#20 a 0 1\n", modified))
+		};
+		watch::wait_for_change(&input, modified, Duration::from_millis(10), Some(Duration::from_secs(10))).unwrap();
+		editor.join().unwrap();
+		run_once(&options, None).unwrap();
+		assert_eq!(vec!["#0", "#10", "#20"], timestamps(&std::fs::read_to_string(&output).unwrap()));
+	}
+
+	#[test]
+	fn max_drop_rate() {
+		let input = "#10 a 1 1\ngarbage\n#20 a 0 1\n#30 a 1 1\n";
+		let error = try_run(&["--max-drop-rate", "20"], input).unwrap_err();
+		assert_eq!("1 of 4 lines were dropped, more than the maximum of 20%", error.to_string());
+		assert!(try_run(&["--max-drop-rate", "25"], input).is_ok());
+	}
+
+	#[test]
+	fn max_output_bytes() {
+		let input = "#10 a 1 1\n#20 a 0 1\n#30 a 1 1\n";
+		let error = try_run(&["--max-output-bytes", "64"], input).unwrap_err();
+		assert_eq!("the output exceeds the limit of 64 bytes", error.to_string());
+		assert!(try_run(&["--max-output-bytes", "4096"], input).is_ok());
+	}
+
+	#[test]
+	fn manifest() {
+		use sha2::{ Digest, Sha256 };
+
+		let path = TempPath::new("manifest.json");
+		let input = "#10 a 1 1\n#20 a 0 1\n";
+		let result = run(&["--manifest", path.to_str().unwrap(), "--head", "1"], input);
+		let manifest: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+		let sha256 = |bytes: &[u8]| -> String { Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect() };
+		assert_eq!(json!(sha256(input.as_bytes())), manifest["input_sha256"]);
+		assert_eq!(json!(sha256(result.as_bytes())), manifest["output_sha256"]);
+		assert_eq!(json!(env!("CARGO_PKG_VERSION")), manifest["version"]);
+		assert_eq!(json!(1), manifest["options"]["head"]);
+		assert_eq!(json!("ns"), manifest["options"]["unit"]);
+		assert_eq!(json!("vcd"), manifest["options"]["output_format"]);
+		assert_eq!(json!(2), manifest["stats"]["value_changes"]);
+	}
+
+	#[test]
+	fn inline_time_units() {
+		let input = "#100ns a 1 1\n#1us a 0 1\n#1500ns a 1 1\n";
+		let result = run(&["--inline-time-units"], input);
+		assert_eq!(vec!["#0", "#100", "#1000", "#1500"], timestamps(&result));
+	}
+
+	#[test]
+	fn label() {
+		let run_a = TempPath::with_contents("run_a.log", "#10 clk 1 1\n#30 clk 0 1\n");
+		let run_b = TempPath::with_contents("run_b.log", "#20 clk 1 1\n#20 bus 1010 4\n");
+		let label_a = format!("{}=run_a", run_a.display());
+		let label_b = format!("{}=run_b", run_b.display());
+		let result = run(&["--label", &label_a, "--label", &label_b], "");
+		assert!(result.contains("$scope module run_a $end\n$var wire 1 ! clk $end\n$upscope $end\n\
+			$scope module run_b $end\n$var wire 1 \" clk $end\n$var integer 4 # bus $end\n$upscope $end\n"));
+		assert!(!result.contains("outputs"));
+		assert_eq!(vec!["#0", "#10", "#20", "#20", "#30"], timestamps(&result));
+
+		// a signal of the input named like a labeled one isn't taken for it
+		let error = try_run(&["--label", &format!("{}=soc", run_a.display())], "#5 soc.clk 1 1\n").unwrap_err();
+		assert_eq!("signal 'soc.clk' is logged both by a --label log and outside of it", error.to_string());
+	}
+
+	#[test]
+	fn compress() {
+		let input = "#10 a 1 1\n#20 bus 1010 4\n#30 a 0 1\n";
+		let options = Options::from_iter(vec!["log2vcd", "-u", "ns", "--compress", "zstd"]);
+		let mut compressed = Vec::new();
+		convert(&options, input.as_bytes(), &mut compressed, &mut Diagnostics::new(options.diagnostics)).unwrap();
+		let decompressed = zstd::decode_all(&compressed[..]).unwrap();
+		assert_eq!(run(&[], input), String::from_utf8(decompressed).unwrap());
+	}
+
+	#[test]
+	fn normalize_names() {
+		let options = Options::from_iter(vec!["log2vcd", "-u", "ns", "--normalize-names", "--alias", "my sig=a", "--alias", "my_sig=b", "--alias", "ctrl/en=c"]);
+		let mut diagnostics = Diagnostics::new(DiagnosticsFormat::Json);
+		let input = "#10 a 1 1\n#20 b 0 1\n#30 c 1 1\n";
+		let mut output = Vec::new();
+		convert(&options, input.as_bytes(), &mut output, &mut diagnostics).unwrap();
+		let output = String::from_utf8(output).unwrap();
+		assert!(output.contains("$var wire 1 ! my_sig $end"));
+		assert!(output.contains("$var wire 1 \" ctrlen $end"));
+		let reported = diagnostics.to_json();
+		let codes: Vec<&str> = reported.as_array().unwrap().iter().map(|d| d["code"].as_str().unwrap()).collect();
+		assert_eq!(vec!["renamed-signal", "name-collision", "renamed-signal"], codes);
+	}
+
+	#[test]
+	fn real_merge() {
+		let input = "#10 r 1.0 f\n#10 a 1 1\n#10 r 2.0 f\n#10 a 0 1\n#20 r 3.0 f\n";
+		let last = run(&["--real-merge", "last"], input);
+		assert!(last.contains("#10\nr2 !\n#10\n1\"\n#10\n0\"\n#20\nr3 !\n"));
+		let mean = run(&["--real-merge", "mean"], input);
+		assert!(mean.contains("#10\nr1.5 !\n#10\n1\"\n#10\n0\"\n#20\nr3 !\n"));
+	}
+
+	#[test]
+	fn conformance() {
+		let mut out = Vec::new();
+		let passed = run_conformance(&mut out).unwrap();
+		let out = String::from_utf8(out).unwrap();
+		assert!(passed, "{}", out);
+		assert!(out.ends_with("5 of 5 conformance cases passed\n"));
+	}
+
+	#[test]
+	fn pull() {
+		let input = "#10 pulled z 1\n#10 floating z 1\n#20 pulled 0 1\n#30 bus 1z0z 4\n";
+		let result = run(&["--pull", "pulled=1", "--pull", "bus=0"], input);
+		assert!(result.contains("#10\n1!\n#10\nz\"\n#20\n0!\n#30\nb1000 #\n"));
+		assert!(parse_pull("pulled=z").is_err());
+		assert_eq!(Ok(0.5), parse_realtime_scale("0.5"));
+		assert!(parse_realtime_scale("0").is_err());
+		assert!(parse_realtime_scale("-1").is_err());
+	}
+
+	#[test]
+	fn merge_bits() {
+		let input = "#10 bus.0 1 1\n#10 bus.1 0 1\n#10 bus.2 1 1\n#10 bus.3 1 1\n#10 other.0 1 1\n#20 bus.1 1 1\n";
+		let result = run(&["--merge-bits", "^bus$"], input);
+		assert!(result.contains("$var integer 4 \" bus $end"));
+		assert!(result.contains("$scope module other $end\n$var wire 1 ! 0 $end"));
+		assert!(result.ends_with("#10\n1!\n#10\nb1101 \"\n#20\nb1111 \"\n"));
+	}
+
+	#[test]
+	fn validate_widths_strict() {
+		let error = try_run(&["--validate-widths-strict"], "#10 bus 101 8\n").unwrap_err();
+		assert_eq!("line 1: signal 'bus' has a 3 bit value, shorter than its width of 8", error.to_string());
+		assert!(try_run(&[], "#10 bus 101 8\n").unwrap().contains("b101 !"));
+		assert!(try_run(&["--validate-widths-strict"], "#10 bus 00000101 8\n#20 bus x 8\n").is_ok());
+	}
+
+	#[test]
+	fn decimate() {
+		let input = "#10 a 1 1\n#12 b 1 1\n#15 a 0 1\n#18 a 1 1\n#20 a 0 1\n#25 b 0 1\n";
+		let result = run(&["--decimate", "10"], input);
+		assert_eq!(vec!["#0", "#12", "#18", "#20", "#25"], timestamps(&result));
+		assert!(result.ends_with("#12\n1!\n#18\n1\"\n#20\n0\"\n#25\n0!\n"));
+	}
+
+	#[test]
+	fn resample() {
+		let input = "#3 a 1 1\n#12 a 0 1\n#14 bus 11 4\n#20 bus 1 4\n#25 a 1 1\n";
+		let result = run(&["--resample", "10"], input);
+		assert_eq!(vec!["#0", "#10", "#20", "#20", "#30", "#30"], timestamps(&result));
+		assert!(result.ends_with("#10\n1!\n#20\n0!\n#20\nb1 \"\n#30\n1!\n#30\nb1 \"\n"));
+		assert!(try_run(&["--resample", "0"], input).is_err());
+	}
+
+	#[test]
+	fn edges() {
+		let input = "#30 a 0 1\n#10 a 0 1\n#20 a 1 1\n#25 a 1 1\n#20 bus 1010 4\n";
+		let result = run(&["--output-format", "edges"], input);
+		assert_eq!("10 a x->0\n20 a 0->1\n20 bus x->1010\n30 a 1->0\n", result);
+
+		let vars = TempPath::with_contents("edges-vars", "a wire 1\n");
+		for format in &["edges", "deltas"] {
+			assert!(try_run(&["--vars", vars.to_str().unwrap(), "--output-format", format], "#10 a 1 1\n").is_err());
+		}
+	}
+
+	#[test]
+	fn also_emit() {
+		let input = "#1400 a 1 1\n#1500 a 0 1\n#2600 a 1 1\n#2700 a 0 1\n";
+		let ns = run(&[], input);
+		let mut us = Vec::new();
+		rescale::rescale(ns.as_bytes(), &mut us, TimescaleUnit::US).unwrap();
+		let us = String::from_utf8(us).unwrap();
+		assert_eq!(vec!["#0", "#1400", "#1500", "#2600", "#2700"], timestamps(&ns));
+		assert!(us.contains("$timescale 1 us $end"));
+		assert_eq!(vec!["#0", "#1", "#2", "#3"], timestamps(&us));
+		assert!(us.ends_with("#1\n1!\n#2\n0!\n#3\n1!\n0!\n"));
+		assert_eq!(PathBuf::from("out.us.vcd"), rescaled_path(Path::new("out.vcd"), TimescaleUnit::US));
+	}
+
+	#[test]
+	fn window_split() {
+		let input = "#5 a 1 1\n#10 b 101 4\n#12 a 0 1\n";
+		let mut windows = Vec::new();
+		window::split(run(&[], input).as_bytes(), 10, |_, vcd| { windows.push(String::from_utf8(vcd.to_vec()).unwrap()); Ok(()) }).unwrap();
+		assert_eq!(2, windows.len());
+		assert!(windows[1].contains("#0\n$dumpvars\n1!\n$end\nb101 \"\n#2\n0!\n"));
+		assert_eq!(PathBuf::from("out.1.vcd"), window_path(Path::new("out.vcd"), 1));
+	}
+
+	#[test]
+	fn preserve_order() {
+		let input = "#10 b 1 1\n#10 a 1 1\n#20 a 0 1\n#20 b 0 1\n";
+		let result = run(&["--preserve-order"], input);
+		assert!(result.ends_with("#10\n1!\n#10\n1\"\n#20\n0\"\n#20\n0!\n"));
+		assert!(try_run(&["--preserve-order", "--strict"], "#20 a 1 1\n#10 a 0 1\n").is_err());
+		assert!(try_run(&["--preserve-order", "--strict"], input).is_ok());
+	}
+
+	#[test]
+	fn split_by_width() {
+		let input = "#10 bus 1010 4\n#20 bus 10101010 8\n#30 bus 1 4\n#30 a 1 1\n";
+		let result = run(&["--split-by-width"], input);
+		assert!(result.contains("$var integer 4 ! bus@4 $end"));
+		assert!(result.contains("$var integer 8 \" bus@8 $end"));
+		assert!(result.contains("$var wire 1 # a $end"));
+		assert!(result.contains("#20\nb10101010 \"\n#30\nb1 !\n"));
+	}
+
+	#[test]
+	fn dump_ids() {
+		let options = Options::from_iter(vec!["log2vcd", "-u", "ns", "--dump-ids"]);
+		let changes: Vec<ValueChange> = ["#10 sig 1 1", "#20 bus 1010 4", "#30 analog 1.5 f"].iter().map(|line| ValueChange::from_str(line).unwrap()).collect();
+		let variables = discover(&options, &changes, &mut IdAllocator::new(HashMap::new())).unwrap();
+		let mut out = Vec::new();
+		write_ids(&variables, &mut out).unwrap();
+		let ids = String::from_utf8(out).unwrap();
+		assert_eq!("sig !\nbus \"\nanalog #\n", ids);
+		// the mapping reads back as an id map
+		let map = read_id_map(ids.as_bytes()).unwrap();
+		assert_eq!(Some(&IdCode::from_str("#").unwrap()), map.get("analog"));
+	}
+
+	#[test]
+	fn hotspots() {
+		let input = "#10 a 1 1\n#10 b 1 1\n#10 c 1 1\n#20 b 0 1\n#30 b 1 1\n#30 a 0 1\n#40 a 0 1\n#40 c 1 1\n";
+		let mut changes: Vec<ValueChange> = input.lines().map(|line| ValueChange::from_str(line).unwrap()).collect();
+		changes.sort_by_key(|change| change.timestamp);
+		let mut out = Vec::new();
+		write_hotspots(&changes, 2, &mut out).unwrap();
+		assert_eq!("signals with the most transitions:\n  b 2\n  a 1\n", String::from_utf8(out).unwrap());
+	}
+
+	#[test]
+	fn thumbnail() {
+		let path = TempPath::new("thumbnail.svg");
+		run(&["--thumbnail", path.to_str().unwrap()], "#10 a 1 1\n#20 b 1 1\n#30 a 0 1\n");
+		let svg = std::fs::read_to_string(&path).unwrap();
+		assert_eq!(2, svg.matches("<path ").count());
+		assert!(svg.contains(">a</text>") && svg.contains(">b</text>"));
+	}
+
+	#[test]
+	fn gap_report() {
+		// the logger dropped out between 30 and 500
+		let input = "#10 a 1 1\n#20 a 0 1\n#20 b 1 1\n#30 a 1 1\n#500 a 0 1\n#510 b 0 1\n#600 a 1 1\n";
+		let changes: Vec<ValueChange> = input.lines().map(|line| ValueChange::from_str(line).unwrap()).collect();
+		let mut out = Vec::new();
+		write_gaps(&changes, 50, &mut out).unwrap();
+		assert_eq!("gaps longer than 50 ticks:\n  30 500 470\n  510 600 90\n", String::from_utf8(out).unwrap());
+		assert_eq!(run(&[], input), run(&["--gap-report", "50"], input));
+
+		let unordered = "#500 a 0 1\n#10 a 1 1\n#100 a 0 1\n";
+		let changes: Vec<ValueChange> = unordered.lines().map(|line| ValueChange::from_str(line).unwrap()).collect();
+		let mut out = Vec::new();
+		write_gaps(&changes, 50, &mut out).unwrap();
+		assert_eq!("gaps longer than 50 ticks:\n  10 100 90\n", String::from_utf8(out).unwrap());
+		assert!(try_run(&["--preserve-order", "--gap-report", "50"], unordered).is_ok());
+	}
+
+	#[test]
+	fn deltas() {
+		let input = "#30 a 0 1\n#10 a 0 1\n#20 a 1 1\n#20 bus 1010 4\n#25 r 1.5 f\n";
+		let result = run(&["--output-format", "deltas"], input);
+		assert_eq!("10 a 0\n10 a 1\n0 bus 1010\n5 r 1.5\n5 a 0\n", result);
+
+		let error = try_run(&["--output-format", "deltas", "--preserve-order"], input).unwrap_err();
+		assert!(error.to_string().contains("timestamp 10 of 'a' is before the previous one 30"));
+	}
+
+	#[test]
+	fn output_fst() {
+		let input = "#10 a 1 1\n#20 bus 1010 4\n#20 a 0 1\n#25 r 1.5 f\n";
+		let fst = |args: &[&str]| {
+			let mut argv = vec!["log2vcd", "-u", "ns", "--output-format", "fst"];
+			argv.extend_from_slice(args);
+			let options = Options::from_iter(argv);
+			let mut output = Vec::new();
+			convert(&options, input.as_bytes(), &mut output, &mut Diagnostics::new(options.diagnostics)).unwrap();
+			fst::read_fst(output)
+		};
+		let changes = fst(&["--self-check"]);
+		assert_eq!(vec!["0 a x", "0 bus xxxx", "0 r 0", "10 a 1", "20 a 0", "20 bus 1010", "25 r 1.5"], changes);
+		assert_eq!(changes, fst(&[]));
+		let vars = TempPath::with_contents("fst_vars", "a wire 1\nbus integer 4\nr real 64\n");
+		assert_eq!(changes, fst(&["--vars", vars.to_str().unwrap()]));
+	}
+
+	#[test]
+	fn external_sort() {
+		// a shuffled log, with a few changes sharing their timestamps
+		let mut input = String::new();
+		let mut seed: u64 = 12345;
+		for _ in 0..2000 {
+			seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+			let timestamp = (seed >> 33) % 500;
+			let signal = (seed >> 20) % 7;
+			input.push_str(&format!("#{} s{} {} 1\n#{} bus{} {:b} 8\n", timestamp, signal, seed % 2, timestamp, signal % 3, (seed >> 40) % 256));
+		}
+		input.push_str("#250 $comment halfway\n");
+		assert_eq!(run(&[], &input), run(&["--external-sort", "--chunk-size", "100", "--self-check"], &input));
+		assert_eq!(run(&["--head", "20"], &input), run(&["--external-sort", "--chunk-size", "100", "--head", "20"], &input));
+		for format in &["edges", "deltas"] {
+			assert_eq!(run(&["--output-format", format], &input), run(&["--external-sort", "--chunk-size", "100", "--output-format", format], &input));
+		}
+		assert!(Options::from_iter_safe(vec!["log2vcd", "--chunk-size", "100"]).is_err());
+	}
+
+	#[test]
+	fn max_memory() {
+		let mut input = String::new();
+		for t in 0..1000 {
+			input.push_str(&format!("#{} bus{} {:b} 8\n", t, t % 10, t % 256));
+		}
+		assert_eq!(run(&[], &input), run(&["--max-memory", "1M"], &input));
+		let error = try_run(&["--max-memory", "16K"], &input).unwrap_err();
+		assert!(error.to_string().starts_with("the value changes take more than the 16384 bytes of --max-memory after "));
+		assert!(Options::from_iter_safe(vec!["log2vcd", "-u", "ns", "--max-memory", "1T"]).is_err());
+	}
+
+	#[test]
+	fn prefix() {
+		let first = TempPath::with_contents("prefix-first.log", "#10 sig 1 1\n#20 sig 0 1\n");
+		let second = TempPath::with_contents("prefix-second.log", "#15 sig 1 1\n");
+		let (first, second) = (first.to_str().unwrap(), second.to_str().unwrap());
+		let labels = ["--label", &format!("{}=soc", first), "--label", &format!("{}=soc", second)];
+		let prefixes = ["--prefix", &format!("{}=a_", first), "--prefix", &format!("{}=b_", second)];
+		let result = run(&[&labels[..], &prefixes[..]].concat(), "");
+		assert!(result.contains("$scope module soc $end\n$var wire 1 ! a_sig $end\n$var wire 1 \" b_sig $end\n$upscope $end\n"));
+		assert!(result.ends_with("#10\n1!\n#15\n1\"\n#20\n0!\n"));
+		// without prefixes the signals of both logs are one
+		assert!(run(&labels, "").contains("$scope module soc $end\n$var wire 1 ! sig $end\n$upscope $end\n"));
+
+		let input = TempPath::with_contents("prefix-input.log", "");
+		let options = Options::from_iter(vec!["log2vcd", "-u", "ns", "--prefix", &format!("{}=top_", input.to_str().unwrap()), "-i", input.to_str().unwrap()]);
+		let mut output = Vec::new();
+		convert(&options, "#10 sig 1 1\n".as_bytes(), &mut output, &mut Diagnostics::new(options.diagnostics)).unwrap();
+		assert!(String::from_utf8(output).unwrap().contains("$var wire 1 ! top_sig $end"));
+	}
+
+	#[test]
+	fn diff() {
+		let other = TempPath::with_contents("diff.log", "#0 a 0 1\n#10 a 1 1\n#20 a 0 1\n#20 bus 1010 4\n");
+		let input = "#0 a 0 1\n#10 a 0 1\n#20 a 0 1\n#20 bus 1010 4\n";
+		let result = run(&["--diff", other.to_str().unwrap()], input);
+		assert!(result.contains("$var wire 1 \" a_diff $end"));
+		assert!(result.ends_with("#0\n0!\n#0\n0\"\n#10\n0!\n#10\n1\"\n#20\n0!\n#20\nb1010 #\n#20\n0\"\n#20\n0$\n"), "{}", result);
+
+		// the diff log has its own line numbers and statistics
+		std::fs::write(&other, "#0 a 0 1\nnot a line\n#20 bus 10 4\n").unwrap();
+		let options = Options::from_iter(vec!["log2vcd", "-u", "ns", "--diff", other.to_str().unwrap()]);
+		let mut diagnostics = Diagnostics::new(DiagnosticsFormat::Json);
+		convert(&options, input.as_bytes(), &mut Vec::new(), &mut diagnostics).unwrap();
+		assert_eq!(4, diagnostics.stats.lines);
+		let reported = diagnostics.to_json();
+		assert_eq!(1, reported.as_array().unwrap().len());
+		assert_eq!(2, reported[0]["line"]);
+		assert!(reported[0]["message"].as_str().unwrap().starts_with(&format!("{}: ", other.display())));
+	}
+
+	#[test]
+	fn trim_signals() {
+		let input = "#10 a 1 1\n#10 idle 0 1\n#20 a 0 1\n#20 idle 0 1\n#30 bus 0101 4\n";
+		let result = run(&["--trim-signals"], input);
+		assert!(result.contains("$var wire 1 ! a $end"));
+		assert!(!result.contains("idle"));
+		assert!(!result.contains("bus"));
+		assert!(run(&[], input).contains("$var wire 1 \" idle $end"));
+	}
+
+	#[test]
+	fn transform() {
+		let input = "#10 n 1 1\n#20 n 0 1\n#20 bus 0011 4\n";
+		let result = run(&["--transform", "n=invert", "--transform", "bus=reverse"], input);
+		assert!(result.contains("#10\n0!\n#20\n1!\n#20\nb1100 \"\n"));
+	}
+
+	#[test]
+	fn base_timestamp() {
+		let input = "#5 a 1 1\nBASE 1000000\n#10 a 0 1\n#20 $comment relative\nBASE 2000000\n#0 a 1 1\n";
+		let result = run(&[], input);
+		assert_eq!(vec!["#0", "#5", "#1000010", "#1000020", "#2000000"], timestamps(&result));
+		assert!(result.contains("#1000020\n$comment\n    relative\n$end"));
+	}
+
+	#[test]
+	fn negative_base() {
+		assert_eq!(vec!["#0", "#5"], timestamps(&run(&[], "BASE -5\n#10 a 1 1\n")));
+		let error = try_run(&[], "#0 a 0 1\nBASE -50\n#10 a 1 1\n").unwrap_err();
+		assert_eq!("line 3: timestamp 10 with base -50 is before time zero, which a VCD can't represent", error.to_string());
+	}
+
+	#[test]
+	fn overflowing_base() {
+		assert_eq!(vec!["#0", "#18446744073709551615"], timestamps(&run(&[], "BASE 18446744073709551615
+#0 a 1 1
+")));
+		let error = try_run(&[], "BASE 18446744073709551615
+#10 a 1 1
+#20 a 0 1
+").unwrap_err();
+		assert_eq!("line 2: timestamp 10 with base 18446744073709551615 is past the last timestamp a VCD can represent", error.to_string());
+	}
+
+	#[test]
+	fn vcd_like() {
+		let input = "#10\na 1 1\nbus 1010 4\n#20\na 0 1\n$comment note\n";
+		let result = run(&["--input-format", "vcd-like"], input);
+		assert_eq!(run(&[], "#10 a 1 1\n#10 bus 1010 4\n#20 a 0 1\n#20 $comment note\n"), result);
+
+		// without a timestamp the line is invalid
+		let result = run(&["--input-format", "vcd-like"], "a 1 1\n#10\nb 1 1\n");
+		assert!(!result.contains(" a $end"));
+		assert_eq!(vec!["#0", "#10"], timestamps(&result));
+	}
+
+	#[test]
+	fn leveled() {
+		let input = "INFO #10 a 1 1\nDEBUG #20 a 0 1\nWARN #30 b 1 1\nDEBUG #40 c 1 1\n";
+		let result = run(&["--input-format", "leveled", "--min-level", "info"], input);
+		assert_eq!(vec!["#0", "#10", "#30"], timestamps(&result));
+		assert!(!result.contains(" c $end"));
+		assert_eq!(vec!["#0", "#10", "#20", "#30", "#40"], timestamps(&run(&["--input-format", "leveled"], input)));
+	}
+
+	#[test]
+	fn json_lines() {
+		let input = "{\"t\": 0, \"en\": true, \"core\": {\"busy\": false}}\n{\"t\": 10, \"en\": true, \"core\": {\"busy\": true}}\n";
+		let mut diagnostics = Diagnostics::new(DiagnosticsFormat::Json);
+		let options = Options::from_iter(vec!["log2vcd", "-u", "ns", "--input-format", "json-lines"]);
+		let mut output = Vec::new();
+		convert(&options, input.as_bytes(), &mut output, &mut diagnostics).unwrap();
+		let result = String::from_utf8(output).unwrap();
+		assert_eq!(3, diagnostics.stats.value_changes);
+		assert!(result.contains("$scope module core $end\n$var wire 1 ! busy $end"));
+		assert!(result.contains("$var wire 1 \" en $end"));
+		assert!(result.ends_with("#10\n1!\n"));
+
+		// integers take the bits they need, a growing counter is promoted to its widest value
+		let input = "{\"t\": 0, \"n\": 1}\n{\"t\": 10, \"n\": 5}\n";
+		let result = run(&["--input-format", "json-lines", "--promote-width"], input);
+		assert!(result.contains("$var integer 3 ! n $end"));
+		assert!(result.ends_with("#0\nb1 !\n#10\nb101 !\n"));
+	}
+
+	#[test]
+	fn json_lines_dedup() {
+		// a vector held across timestamps is only written once, a single different bit is written again
+		let input = "{\"t\": 10, \"bus\": 10}\n{\"t\": 20, \"bus\": 10}\n{\"t\": 30, \"bus\": 10}\n{\"t\": 40, \"bus\": 11}\n{\"t\": 50, \"bus\": 11}\n{\"t\": 60, \"bus\": 10}\n";
+		let result = run(&["--input-format", "json-lines"], input);
+		assert_eq!(vec!["#0", "#10", "#40", "#60"], timestamps(&result));
+		assert!(result.ends_with("#10\nb1010 !\n#40\nb1011 !\n#60\nb1010 !\n"));
+	}
+
+	#[test]
+	fn vcd_passthrough() {
+		let input = "#10 a 1 1\n#10 bus 0000 4\nb1010 \"\n#20\n0!\n#30 a 1 1\n1#\n";
+		let result = run(&["--vcd-passthrough", "--self-check"], input);
+		assert!(result.contains("#10\n1!\n#10\nb0000 \"\n#10\nb1010 \"\n#20\n0!\n#30\n1!\n"));
+		assert!(!result.contains("1#"));
+		assert!(!run(&[], input).contains("b1010"));
+
+		let result = run(&["--vcd-passthrough"], "#10 a 1 1\nBASE 100\n#20\n0!\n");
+		assert!(result.ends_with("#10\n1!\n#120\n0!\n"));
+	}
+
+	#[test]
+	fn collapse_x_after_dumpvars() {
+		let result = run(&["--dumpvars", "--collapse-x"], "#10 a x 1\n#20 a 1 1\n#30 bus zz 2\n");
+		assert!(result.contains("$dumpvars\nx!\nbx \"\n$end\n#20\n1!\n"));
+		assert!(!result.contains("bzz"));
+	}
+
+	#[test]
+	fn collapse_x() {
+		let input = "#10 a x 1\n#20 a x 1\n#30 a 1 1\n#40 a x 1\n#50 a x 1\n#60 a 1 1\n#70 a 1 1\n";
+		let result = run(&["--collapse-x"], input);
+		assert_eq!(vec!["#0", "#10", "#30", "#40", "#60", "#70"], timestamps(&result));
+	}
+
+	#[test]
+	fn real_round_trip() {
+		// reals are written as the shortest decimal that reads back as the same f64
+		let reals: [f64; 4] = [0.1 + 0.2, 1.0 / 3.0, 123_456_789.000_000_01, 0.000_001_234_567_890_123];
+		let input: String = reals.iter().enumerate().map(|(i, r)| format!("#{} r {} f\n", i + 1, r)).collect();
+		let result = run(&["--self-check"], &input);
+		let mut parser = vcd::Parser::new(result.as_bytes());
+		parser.parse_header().unwrap();
+		let written: Vec<f64> = parser.filter_map(|command| match command.unwrap() {
+			vcd::Command::ChangeReal(_, v) => Some(v),
+			_ => None
+		}).collect();
+		assert_eq!(reals.iter().map(|r| r.to_bits()).collect::<Vec<_>>(), written.iter().map(|r| r.to_bits()).collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn real_epsilon() {
+		let input = "#10 r 1.0 f\n#20 r 1.05 f\n#30 r 0.98 f\n#40 r 1.5 f\n#50 r 1.45 f\n";
+		let result = run(&["--real-epsilon", "0.1"], input);
+		assert_eq!(vec!["#0", "#10", "#40"], timestamps(&result));
+		assert!(result.contains("#10\nr1 !\n#40\nr1.5 !\n"));
+		assert_eq!(6, timestamps(&run(&[], input)).len());
+	}
+
+	#[test]
+	fn real_precision() {
+		let result = run(&["--real-precision", "2"], "#10 pi 3.14159 f\n");
+		assert!(result.contains("#10\nr3.14 !\n"));
+	}
+
+	#[test]
+	fn header_block() {
+		let input = "date: 2018-09-01\nversion: logger 1.2\ncomment: bench run\ntimescale: 10 us\n\n#10 a 1 1\nbad line\n";
+		let options = Options::from_iter(vec!["log2vcd"]);
+		let mut output = Vec::new();
+		let mut diagnostics = Diagnostics::new(DiagnosticsFormat::Json);
+		convert(&options, input.as_bytes(), &mut output, &mut diagnostics).unwrap();
+		let result = String::from_utf8(output).unwrap();
+		assert!(result.starts_with("$date\n    2018-09-01\n$end\n$version\n    logger 1.2\n$end\n$comment\n    bench run\n$end\n$timescale 10 us $end\n"));
+		assert!(result.ends_with("#10\n1!\n"));
+		// the lines of the block still count
+		assert_eq!(json!(7), diagnostics.to_json()[0]["line"]);
+		assert_eq!(json!("bad line"), diagnostics.to_json()[0]["text"]);
+
+		assert!(run(&[], input).contains("$timescale 1 ns $end"));
+		assert!(try_run(&["--vars", TempPath::with_contents("header_block_vars", "a wire 1\n").to_str().unwrap()], input).unwrap().contains("$date\n    2018-09-01\n$end"));
+	}
+
+	#[test]
+	fn default_timescale() {
+		let options = Options::from_iter(vec!["log2vcd"]);
+		let mut output = Vec::new();
+		let mut diagnostics = Diagnostics::new(DiagnosticsFormat::Json);
+		convert(&options, "#10 a 1 1\n".as_bytes(), &mut output, &mut diagnostics).unwrap();
+		assert!(String::from_utf8(output).unwrap().starts_with("$timescale 1 ns $end\n"));
+		assert_eq!(json!("default-timescale"), diagnostics.to_json()[0]["code"]);
+
+		let mut diagnostics = Diagnostics::new(DiagnosticsFormat::Json);
+		convert(&options, "timescale: 1 us\n#10 a 1 1\n".as_bytes(), &mut Vec::new(), &mut diagnostics).unwrap();
+		assert_eq!(json!([]), diagnostics.to_json());
+	}
+
+	/// Records the messages logged by every test.
+	struct CapturingLogger;
+
+	static LOGGED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+	impl log::Log for CapturingLogger {
+		fn enabled(&self, _: &log::Metadata) -> bool {
+			true
+		}
+
+		fn log(&self, record: &log::Record) {
+			LOGGED.lock().unwrap().push(format!("{}: {}", record.level(), record.args()));
+		}
+
+		fn flush(&self) {}
+	}
+
+	#[test]
+	fn logging() {
+		static INIT: Once = Once::new();
+		INIT.call_once(|| {
+			log::set_logger(&CapturingLogger).unwrap();
+			log::set_max_level(log::LevelFilter::Trace);
+		});
+		run(&[], "#10 a 1 1\n#20 b 1 1\n#30 c 1 1\n#20 a 0 1\n#40 a 0 1\n#50 a 1 1\n#60 a 0 1\n");
+		// a failed assertion must not poison the log of the other tests
+		let logged = LOGGED.lock().unwrap().clone();
+		// other tests run at the same time, so only the presence of the messages is checked
+		for message in &["INFO: read 7 value changes", "DEBUG: sorted the value changes by timestamp", "INFO: discovered 3 variables", "INFO: emitted 7 value changes"] {
+			assert!(logged.iter().any(|logged| logged == message), "missing '{}'", message);
+		}
+	}
+
+	#[test]
+	fn emit_toc() {
+		let input = "#10 sig 1 1\n#20 bus 1010 4\n#30 analog 1.5 f\n";
+		let result = run(&["--emit-toc"], input);
+		assert!(result.contains("$enddefinitions $end\n$comment\n    toc\n    sig ! 1\n    bus \" 4\n    analog # 32\n$end\n"));
+	}
+
+	#[test]
+	fn input_dir() {
+		let dir = std::env::temp_dir().join(format!("log2vcd_test_{}_input_dir", std::process::id()));
+		let output_dir = dir.join("vcds");
+		std::fs::create_dir_all(&dir).unwrap();
+		std::fs::write(dir.join("a.log"), "#10 a 1 1\n").unwrap();
+		std::fs::write(dir.join("b.log"), "#10 b 1010 4\n").unwrap();
+		std::fs::write(dir.join("c.log"), b"#10 c 1 1\n\xff\n").unwrap();
+		std::fs::write(dir.join("notes.txt"), "not a log").unwrap();
+
+		let options = Options::from_iter(vec!["log2vcd", "-u", "ns", "--input-dir", dir.to_str().unwrap(), "--output-dir", output_dir.to_str().unwrap()]);
+		let mut out = Vec::new();
+		assert!(!convert_dir(&options, &dir, &output_dir, &mut out).unwrap());
+		let out = String::from_utf8(out).unwrap();
+		assert!(out.starts_with("converted a.log\nconverted b.log\nFAIL c.log: "));
+		assert!(out.ends_with("2 of 3 logs converted\n"));
+		assert_eq!(run(&[], "#10 a 1 1\n"), std::fs::read_to_string(output_dir.join("a.vcd")).unwrap());
+		assert!(std::fs::read_to_string(output_dir.join("b.vcd")).unwrap().contains("$var integer 4 ! b $end"));
+		assert!(!output_dir.join("c.vcd").exists());
+		assert!(!output_dir.join("notes.vcd").exists());
+
+		let options = Options::from_iter(vec!["log2vcd", "-u", "ns", "--strict", "--input-dir", dir.to_str().unwrap(), "--output-dir", output_dir.to_str().unwrap()]);
+		let error = convert_dir(&options, &dir, &output_dir, &mut Vec::new()).unwrap_err();
+		assert!(error.to_string().starts_with("c.log: "));
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn annotate_lines() {
+		let input = "#20 bus 1010 4\n\n#10 a 1 1\n#20 a 0 1\n";
+		let result = run(&["--annotate-lines"], input);
+		assert!(result.ends_with("#10\n$comment\n    line 3\n$end\n1!\n#20\n$comment\n    line 1\n$end\nb1010 \"\n\
+			#20\n$comment\n    line 4\n$end\n0!\n"));
+
+		let vars = TempPath::with_contents("annotate_lines_vars", "a wire 1\n");
+		let result = run(&["--annotate-lines", "--vars", vars.to_str().unwrap()], "#10 a 1 1\n#20 a 0 1\n");
+		assert!(result.ends_with("#10\n$comment\n    line 1\n$end\n1!\n#20\n$comment\n    line 2\n$end\n0!\n"));
+	}
+
+	#[test]
+	fn annotate_counts() {
+		let input = "#10 sig 1 1\n#20 bus 1010 4\n#30 sig 0 1\n#40 sig 0 1\n#50 sig 1 1\n#60 bus 1011 4\n#70 idle 1 1\n";
+		let result = run(&["--annotate-counts"], input);
+		assert!(result.contains("$enddefinitions $end\n$comment\n    transition counts\n    sig 2\n    bus 1\n    idle 0\n$end\n"));
+	}
+
+	#[test]
+	fn list_signals() {
+		let input = "#10 sig 1 1\n#20 bus 1010 4\n#30 analog 1.5 f\n#40 sig 0 1\n";
+		let result = run(&["--list-signals"], input);
+		assert_eq!("analog real 32\nbus integer 4\nsig wire 1\n", result);
+	}
+
+	#[test]
+	fn subcommands() {
+		let input = "#10 cpu.clk 1 1\n#10 cpu.pc 1010 4\n#20 mem[1] 11 4\n#30 load 1.5 f\n#40 cpu.clk 0 1\n";
+		let vcd = run(&[], input);
+		assert_eq!(vcd, run(&["convert"], input));
+		assert_eq!(run(&["--list-signals"], input), run(&["list"], input));
+		assert_eq!("", run(&["check"], input));
+
+		let log = run(&["reverse"], &vcd);
+		assert_eq!(input, log);
+		assert_eq!(vcd, run(&[], &log));
+
+		// the options may also follow the subcommand
+		assert_eq!(run(&["--flat"], input), run(&["convert", "--flat"], input));
+		assert_eq!(run(&["--list-signals", "--flat"], input), run(&["list", "--flat"], input));
+		assert_eq!("", run(&["check", "--output-format", "edges"], input));
+		assert_eq!("", run(&["check", "--output-format", "fst"], input));
+		assert_eq!("", run(&["--list-signals", "check"], input));
+		assert!(Options::from_iter_safe(vec!["log2vcd", "-u", "ns", "lint"]).is_err());
+	}
+
+	#[test]
+	fn input_time_format() {
+		let input = "2018-09-01T12:00:00.000010 a 1 1\n2018-09-01T12:00:00.001510 a 0 1\n";
+		let result = run(&["--input-time-format", "%Y-%m-%dT%H:%M:%S%.f"], input);
+		assert_eq!(vec!["#0", "#0", "#1500000"], timestamps(&result));
+	}
+
+	#[test]
+	fn real_special() {
+		let input = "#10 r 1.5 f\n#20 r inf f\n#30 r -inf f\n#40 r nan f\n";
+		let error = try_run(&["--real-special", "error"], input).unwrap_err();
+		assert_eq!("line 2: signal 'r' has the real value inf, see --real-special", error.to_string());
+		let error = try_run(&["--real-special", "error"], "#40 r nan f\n").unwrap_err();
+		assert_eq!("line 1: signal 'r' has the real value NaN, see --real-special", error.to_string());
+
+		let result = run(&["--real-special", "clamp"], input);
+		assert!(result.ends_with(&format!("#20\nr{} !\n#30\nr{} !\n#40\nr0 !\n", f64::MAX, f64::MIN)));
+
+		let result = run(&["--real-special", "pass"], input);
+		assert!(result.ends_with("#20\nrinf !\n#30\nr-inf !\n#40\nrNaN !\n"));
+
+		// they're dropped by default
+		let options = Options::from_iter(vec!["log2vcd", "-u", "ns", "--diagnostics", "json"]);
+		let mut diagnostics = Diagnostics::new(options.diagnostics);
+		let mut output = Vec::new();
+		convert(&options, input.as_bytes(), &mut output, &mut diagnostics).unwrap();
+		assert!(String::from_utf8(output).unwrap().ends_with("#10\nr1.5 !\n"));
+		let lines: Vec<serde_json::Value> = diagnostics.to_json().as_array().unwrap().iter().map(|d| d["line"].clone()).collect();
+		assert_eq!(vec![json!(2), json!(3), json!(4)], lines);
+		assert_eq!(json!("dropping the real value inf of 'r', see --real-special"), diagnostics.to_json()[0]["message"]);
+
+		let vars = TempPath::with_contents("real_special_vars", "r real 64\n");
+		assert!(try_run(&["--vars", vars.to_str().unwrap(), "--real-special", "error"], input).is_err());
+		assert!(run(&["--vars", vars.to_str().unwrap()], input).ends_with("#10\nr1.5 !\n"));
+		assert!(Options::from_iter_safe(vec!["log2vcd", "-u", "ns", "--real-special", "round"]).is_err());
+	}
+
+	#[test]
+	fn synthetic_time() {
+		let input = "a 1 1\nbus 1010 4\n\na 0 1\nbus 1 4\n";
+		let result = run(&["--synthetic-time", "5"], input);
+		assert_eq!(vec!["#0", "#0", "#5", "#10", "#15"], timestamps(&result));
+		assert!(result.ends_with("#10\n0!\n#15\nb1 \"\n"));
+
+		let vars = TempPath::with_contents("synthetic_time_vars", "a wire 1\nbus integer 4\n");
+		let result = run(&["--synthetic-time", "5", "--vars", vars.to_str().unwrap()], input);
+		assert_eq!(vec!["#0", "#0", "#5", "#10", "#15"], timestamps(&result));
+	}
+
+	#[test]
+	fn input_time_unit() {
+		let input = "#12.5 a 1 1\n#13 a 0 1\n";
+		let result = run(&["--input-time-unit", "s"], input);
+		assert_eq!(vec!["#0", "#12500000000", "#13000000000"], timestamps(&result));
+
+		let result = run(&["--input-time-unit", "ps", "--step_size", "2"], "#1500 a 1 1\n#5000 a 0 1\n");
+		assert_eq!(vec!["#0", "#1", "#3"], timestamps(&result));
+	}
+
+	#[test]
+	fn arrays() {
+		let input = "#100 mem[0] 1010 4\n#100 mem[1] 0001 4\n#110 other 1 1\n#120 mem[0] 1111 4\n";
+		let result = run(&["--scope-type", "outputs.mem=task"], input);
+		let expected = "$scope module outputs $end\n\
+			$scope task mem $end\n\
+			$var integer 4 ! mem[0] $end\n\
+			$var integer 4 \" mem[1] $end\n\
+			$upscope $end\n\
+			$var wire 1 # other $end\n\
+			$upscope $end\n";
+		assert!(result.contains(expected));
+		assert!(result.contains("#120\nb1111 !\n"));
+	}
+
+	#[test]
+	fn progress() {
+		let input = "#10 a 1 1\n#20 bus 1010 4\n#30 a 0 1\n";
+		let options = Options::from_iter(vec!["log2vcd", "-u", "ns", "--progress"]);
+		let mut status = Vec::new();
+		let mut output = Vec::new();
+		let reader = BufReader::new(Progress::new(input.as_bytes(), Some(input.len() as u64), &mut status));
+		convert(&options, reader, &mut output, &mut Diagnostics::new(options.diagnostics)).unwrap();
+		assert_eq!(run(&[], input), String::from_utf8(output).unwrap());
+		assert!(String::from_utf8(status).unwrap().contains("100%"));
+	}
+
+	#[test]
+	fn timed_comments() {
+		let input = "#10 a 1 1\n#150 a 0 1\n#100 $comment reset handler entered\n#200 $comment done\n";
+		let result = run(&[], input);
+		assert!(result.contains("#10\n1!\n#100\n$comment\n    reset handler entered\n$end\n#150\n0!\n#200\n$comment\n    done\n$end\n"));
+		let result = run(&[], "#10 a 1 1\n#20 $comment early $end 1!\n");
+		assert!(result.contains("$comment\n    early \\$end 1!\n$end\n"));
+	}
 }
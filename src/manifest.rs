@@ -0,0 +1,124 @@
+use std::fmt::Display;
+use std::io::{ self, BufRead, Read, Write };
+
+use serde::{ Serialize, Serializer };
+use sha2::{ Digest, Sha256 };
+
+/// Hashes the bytes read through it.
+pub struct HashingReader<R: BufRead> {
+	inner: R,
+	hasher: Sha256
+}
+
+impl<R: BufRead> HashingReader<R> {
+	pub fn new(inner: R) -> HashingReader<R> {
+		HashingReader { inner, hasher: Sha256::new() }
+	}
+
+	/// The hex SHA-256 of everything read so far.
+	pub fn hash(&self) -> String {
+		hex(self.hasher.clone())
+	}
+}
+
+impl<R: BufRead> Read for HashingReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let n = self.inner.read(buf)?;
+		self.hasher.update(&buf[..n]);
+		Ok(n)
+	}
+}
+
+impl<R: BufRead> BufRead for HashingReader<R> {
+	fn fill_buf(&mut self) -> io::Result<&[u8]> {
+		self.inner.fill_buf()
+	}
+
+	fn consume(&mut self, amt: usize) {
+		// the buffer is still filled, consuming is what moves past it
+		if let Ok(buf) = self.inner.fill_buf() {
+			self.hasher.update(&buf[..amt.min(buf.len())]);
+		}
+		self.inner.consume(amt);
+	}
+}
+
+/// Hashes the bytes written through it.
+pub struct HashingWriter<W: Write> {
+	inner: W,
+	hasher: Sha256
+}
+
+impl<W: Write> HashingWriter<W> {
+	pub fn new(inner: W) -> HashingWriter<W> {
+		HashingWriter { inner, hasher: Sha256::new() }
+	}
+
+	/// The hex SHA-256 of everything written so far.
+	pub fn hash(&self) -> String {
+		hex(self.hasher.clone())
+	}
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let n = self.inner.write(buf)?;
+		self.hasher.update(&buf[..n]);
+		Ok(n)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
+
+fn hex(hasher: Sha256) -> String {
+	hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The manifest of a conversion, tying the output to the input and the options it was made with.
+pub fn to_json(input_hash: &str, output_hash: &str, options: serde_json::Value, stats: serde_json::Value) -> serde_json::Value {
+	json!({
+		"version": env!("CARGO_PKG_VERSION"),
+		"options": options,
+		"input_sha256": input_hash,
+		"output_sha256": output_hash,
+		"stats": stats
+	})
+}
+
+/// Serializes an optional option value that isn't `Serialize` by its displayed form, such as a regex.
+pub fn display_option<T: Display, S: Serializer>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error> {
+	value.as_ref().map(ToString::to_string).serialize(serializer)
+}
+
+/// Serializes a repeated option value by its displayed form.
+pub fn display_all<T: Display, S: Serializer>(values: &[T], serializer: S) -> Result<S::Ok, S::Error> {
+	serializer.collect_seq(values.iter().map(ToString::to_string))
+}
+
+/// Serializes repeated `key=value` options, the value by its displayed form.
+pub fn display_pairs<K: Serialize, T: Display, S: Serializer>(pairs: &[(K, T)], serializer: S) -> Result<S::Ok, S::Error> {
+	serializer.collect_seq(pairs.iter().map(|(key, value)| (key, value.to_string())))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	const EMPTY_SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+	const ABC_SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+	#[test]
+	fn hashes() {
+		let mut reader = HashingReader::new("abc".as_bytes());
+		assert_eq!(EMPTY_SHA256, reader.hash());
+		let mut line = String::new();
+		reader.read_line(&mut line).unwrap();
+		assert_eq!(ABC_SHA256, reader.hash());
+
+		let mut writer = HashingWriter::new(Vec::new());
+		writer.write_all(b"abc").unwrap();
+		assert_eq!(ABC_SHA256, writer.hash());
+	}
+}
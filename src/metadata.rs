@@ -0,0 +1,93 @@
+use std::str::FromStr;
+
+use vcd::TimescaleUnit;
+
+use log2vcd::value_change;
+
+/// The header fields of a metadata block of `key: value` lines at the top of a log.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata {
+	pub date: Option<String>,
+	pub version: Option<String>,
+	pub comment: Option<String>,
+	pub timescale: Option<(u32, TimescaleUnit)>
+}
+
+impl Metadata {
+	/// Sets a header field from a `key: value` line.
+	pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+		if contains_end(value) {
+			return Err(format!("header field '{}' can't contain '$end'", key));
+		}
+		let value = String::from(value);
+		match key.to_lowercase().as_str() {
+			"date" => self.date = Some(value),
+			"version" => self.version = Some(value),
+			"comment" => self.comment = Some(value),
+			"timescale" => self.timescale = Some(parse_timescale(&value)?),
+			_ => return Err(format!("unknown header field '{}'", key))
+		}
+		Ok(())
+	}
+}
+
+/// Whether `text` has an `$end` token, which would end the VCD section it's written in.
+pub fn contains_end(text: &str) -> bool {
+	text.split_whitespace().any(|token| token == "$end")
+}
+
+/// Escapes the `$end` tokens of `text` so it can be written inside a `$comment` section.
+pub fn escape_end(text: &str) -> String {
+	text.split_inclusive(char::is_whitespace)
+		.map(|token| if token.trim_end() == "$end" { format!("\\{}", token) } else { String::from(token) })
+		.collect()
+}
+
+/// Splits a line of a metadata block into its key and value.
+pub fn parse_field(line: &str) -> Option<(&str, &str)> {
+	let colon = line.find(':')?;
+	let key = &line[..colon];
+	if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphabetic()) {
+		return None;
+	}
+	Some((key, line[colon + 1..].trim()))
+}
+
+/// Parses a timescale such as `10 us` or `1ns`.
+fn parse_timescale(s: &str) -> Result<(u32, TimescaleUnit), String> {
+	let invalid = || format!("invalid timescale '{}'", s);
+	let split = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+	let step = u32::from_str(&s[..split]).map_err(|_| invalid())?;
+	let unit = value_change::parse_time_unit(s[split..].trim()).ok_or_else(invalid)?;
+	Ok((step, unit))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn fields() {
+		let mut metadata = Metadata::default();
+		for line in &["date: 2018-09-01", "Version: logger 1.2", "timescale: 10 µs"] {
+			let (key, value) = parse_field(line).unwrap();
+			metadata.set(key, value).unwrap();
+		}
+		assert_eq!(Some(String::from("2018-09-01")), metadata.date);
+		assert_eq!(Some(String::from("logger 1.2")), metadata.version);
+		assert_eq!(Some((10, TimescaleUnit::US)), metadata.timescale);
+		assert!(metadata.set("timescale", "fast").is_err());
+		assert!(metadata.set("author", "me").is_err());
+		assert!(metadata.set("comment", "first $end second").is_err());
+		assert!(metadata.set("comment", "costs $end$").is_ok());
+		assert_eq!(None, parse_field("#10 a 1 1"));
+		assert_eq!(None, parse_field("t=10 a=1"));
+	}
+
+	#[test]
+	fn escaped_end() {
+		assert_eq!("reset \\$end of init", escape_end("reset $end of init"));
+		assert_eq!("done \\$end", escape_end("done $end"));
+		assert_eq!("$ending", escape_end("$ending"));
+	}
+}
@@ -0,0 +1,96 @@
+use std::io::{ self, Read, Write };
+
+/// Wraps the input, reporting how much of it has been read.
+///
+/// With a known input size the percentage read is reported, otherwise the number of lines read.
+pub struct Progress<R: Read, W: Write> {
+	inner: R,
+	status: W,
+	size: Option<u64>,
+	bytes: u64,
+	lines: u64,
+	reported: u64,
+	done: bool
+}
+
+/// Number of lines between reports when the input size is unknown.
+const LINES_PER_REPORT: u64 = 100_000;
+
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+impl<R: Read, W: Write> Progress<R, W> {
+	pub fn new(inner: R, size: Option<u64>, status: W) -> Progress<R, W> {
+		Progress { inner, status, size, bytes: 0, lines: 0, reported: 0, done: false }
+	}
+
+	fn report(&mut self) -> io::Result<()> {
+		match self.size {
+			Some(size) => {
+				let percent = (self.bytes * 100).checked_div(size).unwrap_or(100);
+				if percent != self.reported {
+					self.reported = percent;
+					write!(self.status, "\rprogress: {}%", percent)?;
+				}
+			},
+			None => {
+				let reports = self.lines / LINES_PER_REPORT;
+				if reports != self.reported {
+					self.reported = reports;
+					let spinner = SPINNER[(reports % SPINNER.len() as u64) as usize];
+					write!(self.status, "\r{} read {} lines", spinner, self.lines)?;
+				}
+			}
+		}
+		self.status.flush()
+	}
+
+	fn finish(&mut self) -> io::Result<()> {
+		match self.size {
+			Some(_) => writeln!(self.status, "\rprogress: 100%"),
+			None => writeln!(self.status, "\rread {} lines", self.lines)
+		}
+	}
+}
+
+impl<R: Read, W: Write> Read for Progress<R, W> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let n = self.inner.read(buf)?;
+		// errors writing the status are ignored, they shouldn't stop the conversion
+		if n == 0 && !buf.is_empty() {
+			if !self.done {
+				self.done = true;
+				let _ = self.finish();
+			}
+			return Ok(0);
+		}
+		self.bytes += n as u64;
+		self.lines += buf[..n].iter().filter(|b| **b == b'\n').count() as u64;
+		let _ = self.report();
+		Ok(n)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn known_size() {
+		let input = "0123456789".as_bytes();
+		let mut status = Vec::new();
+		let mut output = String::new();
+		Progress::new(input, Some(10), &mut status).read_to_string(&mut output).unwrap();
+		assert_eq!("0123456789", output);
+		assert!(String::from_utf8(status).unwrap().ends_with("\rprogress: 100%\n"));
+	}
+
+	#[test]
+	fn unknown_size() {
+		let input = "a\nb\nc\n".as_bytes();
+		let mut status = Vec::new();
+		let mut output = String::new();
+		Progress::new(input, None, &mut status).read_to_string(&mut output).unwrap();
+		assert_eq!("a\nb\nc\n", output);
+		assert_eq!("\rread 3 lines\n", String::from_utf8(status).unwrap());
+	}
+}
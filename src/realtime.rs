@@ -0,0 +1,58 @@
+use std::thread;
+use std::time::{ Duration, Instant };
+
+use vcd::TimescaleUnit;
+
+/// Paces the timestamps written with `--realtime` to the wall clock.
+pub struct Pacer {
+	/// Ticks of the VCD per wall-clock second
+	ticks_per_second: f64,
+	/// When the first timestamp was written, and the timestamp
+	start: Option<(Instant, u64)>
+}
+
+impl Pacer {
+	/// A pacer writing timestamps `scale` times as far apart as their VCD time, 1 is real time.
+	pub fn new(scale: f64, (step, unit): (u32, TimescaleUnit)) -> Pacer {
+		Pacer { ticks_per_second: unit.divisor() as f64 / (scale * f64::from(step)), start: None }
+	}
+
+	/// How long to wait from `now` until `timestamp` is due, the first timestamp is due right away.
+	fn delay(&mut self, timestamp: u64, now: Instant) -> Duration {
+		let (start, first) = *self.start.get_or_insert((now, timestamp));
+		let elapsed = timestamp.saturating_sub(first) as f64 / self.ticks_per_second;
+		(start + Duration::from_secs_f64(elapsed)).saturating_duration_since(now)
+	}
+
+	/// Sleeps until `timestamp` is due.
+	pub fn wait(&mut self, timestamp: u64) {
+		thread::sleep(self.delay(timestamp, Instant::now()));
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn pacing() {
+		// 1 us ticks at half speed
+		let mut pacer = Pacer::new(2.0, (1, TimescaleUnit::US));
+		let start = Instant::now();
+		assert_eq!(Duration::from_secs(0), pacer.delay(1000, start));
+		assert_eq!(Duration::from_millis(10), pacer.delay(6000, start));
+		// the time already spent writing counts
+		assert_eq!(Duration::from_millis(6), pacer.delay(6000, start + Duration::from_millis(4)));
+		assert_eq!(Duration::from_secs(0), pacer.delay(6000, start + Duration::from_millis(20)));
+		assert_eq!(Duration::from_secs(0), pacer.delay(500, start));
+	}
+
+	#[test]
+	fn wait() {
+		let mut pacer = Pacer::new(1.0, (1, TimescaleUnit::MS));
+		let start = Instant::now();
+		pacer.wait(0);
+		pacer.wait(30);
+		assert!(start.elapsed() >= Duration::from_millis(30));
+	}
+}
@@ -0,0 +1,31 @@
+use std::io::{ self, BufRead, Write };
+
+use vcd::{ Command, Parser, TimescaleUnit, Writer };
+
+/// Rewrites a VCD with a timescale of 1 `unit`, rounding its timestamps to the nearest tick.
+///
+/// Timestamps that round to the same tick are merged.
+pub fn rescale<R: BufRead>(vcd: R, output: &mut dyn Write, unit: TimescaleUnit) -> io::Result<()> {
+	let mut parser = Parser::new(vcd);
+	let mut header = parser.parse_header()?;
+	let (step, from) = header.timescale.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "the VCD has no timescale"))?;
+	header.timescale = Some((1, unit));
+
+	let (numerator, denominator) = (u128::from(step) * u128::from(unit.divisor()), u128::from(from.divisor()));
+	let mut writer = Writer::new(output);
+	writer.header(&header)?;
+	let mut last = None;
+	for command in parser {
+		match command? {
+			Command::Timestamp(t) => {
+				let t = ((u128::from(t) * numerator + denominator / 2) / denominator) as u64;
+				if last != Some(t) {
+					writer.timestamp(t)?;
+					last = Some(t);
+				}
+			},
+			command => writer.command(&command)?
+		}
+	}
+	Ok(())
+}
@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+use std::io::{ self, Read, Write };
+
+use vcd::{ Command, IdCode, Parser, ScopeItem, VarType };
+
+use crate::value_change::{ ScalarValue, Value, ValueChange };
+
+/// The signals logged for the changes of an id, with their widths (none for reals).
+type Signals = HashMap<IdCode, Vec<(String, Option<usize>)>>;
+
+/// Names the variables of a scope after their path, the `name[index]` elements of an array
+/// aren't named after the scope of the array.
+fn add_signals(items: &[ScopeItem], path: &str, signals: &mut Signals) {
+	for item in items {
+		match item {
+			ScopeItem::Var(var) => {
+				let array = path.rsplit('.').next().filter(|scope| var.reference.starts_with(&format!("{}[", scope)));
+				let path = match array {
+					Some(array) => { let path = &path[..path.len() - array.len()]; path.strip_suffix('.').unwrap_or(path) },
+					None => path
+				};
+				let name = if path.is_empty() { var.reference.clone() } else { format!("{}.{}", path, var.reference) };
+				let width = if var.var_type == VarType::Real { None } else { Some(var.size as usize) };
+				signals.entry(var.code).or_default().push((name, width));
+			},
+			ScopeItem::Scope(scope) => {
+				let path = if path.is_empty() { scope.identifier.clone() } else { format!("{}.{}", path, scope.identifier) };
+				add_signals(&scope.children, &path, signals);
+			}
+		}
+	}
+}
+
+/// Converts a VCD back to a log, one `#timestamp name value width` line per value change.
+///
+/// The signals are named after their scopes below the top scopes, joined by `.` the way signal
+/// names are split into them.
+pub fn reverse<R: Read>(vcd: R, output: &mut dyn Write) -> io::Result<()> {
+	let mut parser = Parser::new(vcd);
+	let header = parser.parse_header()?;
+	let mut signals = Signals::new();
+	for item in &header.items {
+		match item {
+			ScopeItem::Scope(scope) => add_signals(&scope.children, "", &mut signals),
+			var => add_signals(std::slice::from_ref(var), "", &mut signals)
+		}
+	}
+
+	let mut time = 0;
+	for command in parser {
+		let (id, value) = match command? {
+			Command::Timestamp(t) => { time = t; continue; },
+			Command::ChangeScalar(id, v) => (id, Value::Scalar(ScalarValue::from(v))),
+			Command::ChangeVector(id, v) => {
+				let value: Vec<ScalarValue> = v.into_iter().map(ScalarValue::from).collect();
+				(id, Value::BinaryVector{width: value.len(), value})
+			},
+			Command::ChangeReal(id, v) => (id, Value::Real(v)),
+			_ => continue
+		};
+		for (name, width) in signals.get(&id).map_or(&[][..], |signals| &signals[..]) {
+			let value = match (value.clone(), *width) {
+				(Value::BinaryVector{value, ..}, Some(1)) if value.len() == 1 => Value::Scalar(value[0].clone()),
+				(Value::BinaryVector{value, ..}, Some(width)) => Value::BinaryVector{width, value},
+				(Value::Scalar(v), Some(width)) if width > 1 => Value::BinaryVector{width, value: vec![v]},
+				(value, _) => value
+			};
+			writeln!(output, "{}", ValueChange { timestamp: time, signal_name: name.clone(), value, line: None, scope: None, strength: None })?;
+		}
+	}
+	output.flush()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn round_trip() {
+		let vcd = "$timescale 1 ns $end\n$scope module outputs $end\n$var wire 1 ! clk $end\n\
+			$scope module mem $end\n$var integer 4 \" mem[0] $end\n$upscope $end\n\
+			$scope module cpu $end\n$var real 64 # load $end\n$var wire 4 $ pc $end\n$upscope $end\n$upscope $end\n\
+			$enddefinitions $end\n#0\n$dumpvars\nx!\n$end\n#10\n1!\nb10 \"\nr0.5 #\n1$\n";
+		let mut output = Vec::new();
+		reverse(vcd.as_bytes(), &mut output).unwrap();
+		assert_eq!("#0 clk x 1\n#10 clk 1 1\n#10 mem[0] 10 4\n#10 cpu.load 0.5 f\n#10 cpu.pc 1 4\n", String::from_utf8(output).unwrap());
+	}
+}
@@ -0,0 +1,92 @@
+use std::io::{ self, Read };
+
+use vcd::{ Command, IdCode, Parser, SimulationCommand };
+
+use crate::value_change::{ self, Value, ScalarValue };
+
+/// A value change as it was written to the VCD.
+pub type Written = (u64, IdCode, Value);
+
+fn failed(message: String) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, format!("self check failed: {}", message))
+}
+
+/// Whether a decoded value is the same as the written one, vectors may have been shortened by
+/// the writer.
+fn same(decoded: &Value, written: &Value) -> bool {
+	match (decoded, written) {
+		(Value::BinaryVector{value: decoded, ..}, Value::BinaryVector{width, value}) => {
+			value_change::left_extend(decoded, *width) == value_change::left_extend(value, *width)
+		},
+		(decoded, written) => decoded == written
+	}
+}
+
+/// Reads a VCD back and checks that its value changes, ignoring the `$dumpvars` block, are
+/// exactly the ones that were written.
+pub fn verify<R: Read>(vcd: R, expected: &[Written]) -> io::Result<()> {
+	let mut parser = Parser::new(vcd);
+	parser.parse_header()?;
+
+	let mut time = 0;
+	let mut dumpvars = false;
+	let mut expected = expected.iter();
+	for command in parser {
+		let (id, value) = match command? {
+			Command::Timestamp(t) => { time = t; continue; },
+			Command::Begin(SimulationCommand::Dumpvars) => { dumpvars = true; continue; },
+			Command::End(SimulationCommand::Dumpvars) => { dumpvars = false; continue; },
+			Command::ChangeScalar(id, v) => (id, Value::Scalar(ScalarValue::from(v))),
+			Command::ChangeVector(id, v) => {
+				let value: Vec<ScalarValue> = v.into_iter().map(ScalarValue::from).collect();
+				(id, Value::BinaryVector{width: value.len(), value})
+			},
+			Command::ChangeReal(id, v) => (id, Value::Real(v)),
+			_ => continue
+		};
+		if dumpvars {
+			continue;
+		}
+		match expected.next() {
+			Some((t, i, v)) if *t == time && *i == id && same(&value, v) => {},
+			Some((t, i, v)) => {
+				return Err(failed(format!("expected {:?} for {} at #{}, decoded {:?} for {} at #{}", v, i, t, value, id, time)));
+			},
+			None => return Err(failed(format!("unexpected change of {} at #{}", id, time)))
+		}
+	}
+	match expected.next() {
+		Some((t, i, _)) => Err(failed(format!("missing change of {} at #{}", i, t))),
+		None => Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	const VCD: &str = "$timescale 1 ns $end\n$scope module outputs $end\n$var wire 1 ! a $end\n\
+		$var integer 4 \" b $end\n$upscope $end\n$enddefinitions $end\n#0\n$dumpvars\nx!\nbx \"\n$end\n\
+		#10\n1!\n#20\nb101 \"\n";
+
+	fn expected() -> Vec<Written> {
+		let a = IdCode::FIRST;
+		vec![
+			(10, a, Value::parse("1", "1").unwrap()),
+			(20, a.next(), Value::parse("0101", "4").unwrap())
+		]
+	}
+
+	#[test]
+	fn clean() {
+		verify(VCD.as_bytes(), &expected()).unwrap();
+	}
+
+	#[test]
+	fn corrupted() {
+		let corrupted = VCD.replace("b101", "b111");
+		assert!(verify(corrupted.as_bytes(), &expected()).is_err());
+		let truncated = VCD.replace("#20\nb101 \"\n", "");
+		assert!(verify(truncated.as_bytes(), &expected()).is_err());
+	}
+}
@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Counts of what happened to the lines of a conversion.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConvertStats {
+	/// Non-blank lines that were expected to be value changes
+	pub lines: usize,
+	/// Lines parsed as value changes
+	pub value_changes: usize,
+	/// Lines that were dropped, by diagnostic code
+	pub dropped: BTreeMap<&'static str, usize>,
+	/// Number of declared variables
+	pub signals: usize,
+	/// Lines identical to the line before them, only counted with `--report-dup-lines`
+	pub duplicate_lines: Option<usize>
+}
+
+impl ConvertStats {
+	pub fn drop_line(&mut self, code: &'static str) {
+		*self.dropped.entry(code).or_insert(0) += 1;
+	}
+
+	pub fn to_json(&self) -> serde_json::Value {
+		let mut json = json!({
+			"lines": self.lines,
+			"value_changes": self.value_changes,
+			"dropped": self.dropped.values().sum::<usize>(),
+			"errors": self.dropped,
+			"signals": self.signals
+		});
+		if let Some(duplicate_lines) = self.duplicate_lines {
+			json["duplicate_lines"] = json!(duplicate_lines);
+		}
+		json
+	}
+}
+
+impl fmt::Display for ConvertStats {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let dropped: usize = self.dropped.values().sum();
+		write!(f, "{} lines, {} value changes, {} signals, {} lines dropped", self.lines, self.value_changes, self.signals, dropped)?;
+		for (code, count) in &self.dropped {
+			write!(f, "\n  {}: {}", code, count)?;
+		}
+		if let Some(duplicate_lines) = self.duplicate_lines {
+			write!(f, "\n{} duplicate lines", duplicate_lines)?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn json() {
+		let mut stats = ConvertStats { lines: 4, value_changes: 1, signals: 1, ..ConvertStats::default() };
+		stats.drop_line("invalid-format");
+		stats.drop_line("invalid-format");
+		stats.drop_line("invalid-value");
+		let expected = json!({
+			"lines": 4,
+			"value_changes": 1,
+			"dropped": 3,
+			"errors": { "invalid-format": 2, "invalid-value": 1 },
+			"signals": 1
+		});
+		assert_eq!(expected, stats.to_json());
+		stats.duplicate_lines = Some(2);
+		assert_eq!(json!(2), stats.to_json()["duplicate_lines"]);
+	}
+
+	#[test]
+	fn display() {
+		let mut stats = ConvertStats { lines: 4, value_changes: 2, signals: 1, ..ConvertStats::default() };
+		assert_eq!("4 lines, 2 value changes, 1 signals, 0 lines dropped", stats.to_string());
+		stats.drop_line("invalid-value");
+		stats.drop_line("invalid-format");
+		stats.drop_line("invalid-value");
+		assert_eq!("4 lines, 2 value changes, 1 signals, 3 lines dropped\n  invalid-format: 1\n  invalid-value: 2", stats.to_string());
+	}
+}
@@ -0,0 +1,48 @@
+use std::io::{ self, Write };
+
+/// Duplicates everything written to it into two outputs.
+pub struct Tee<A: Write, B: Write> {
+	first: A,
+	second: B
+}
+
+impl<A: Write, B: Write> Tee<A, B> {
+	pub fn new(first: A, second: B) -> Tee<A, B> {
+		Tee { first, second }
+	}
+}
+
+impl<A: Write, B: Write> Write for Tee<A, B> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		// both outputs have to take the whole buffer, otherwise they'd drift apart
+		self.first.write_all(buf)?;
+		self.second.write_all(buf)?;
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.first.flush()?;
+		self.second.flush()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use std::fs;
+	use crate::temp::TempPath;
+
+	#[test]
+	fn file_and_stdout() {
+		let path = TempPath::new("tee.vcd");
+		let mut stdout = Vec::new();
+		crate::atomic::write_atomically(&path, |file| {
+			let mut tee = Tee::new(file, &mut stdout);
+			tee.write_all(b"$timescale 1 ns $end\n")?;
+			tee.write_all(b"#0\n")?;
+			tee.flush()
+		}).unwrap();
+		assert_eq!("$timescale 1 ns $end\n#0\n", String::from_utf8(stdout.clone()).unwrap());
+		assert_eq!(stdout, fs::read(&path).unwrap());
+	}
+}
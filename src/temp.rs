@@ -0,0 +1,53 @@
+use std::fs;
+use std::ops::Deref;
+use std::path::{ Path, PathBuf };
+
+/// A path of the tests in the temporary directory, unique to the process. The file is removed
+/// when the path is dropped, even when the test fails.
+pub struct TempPath(PathBuf);
+
+impl TempPath {
+	pub fn new(name: &str) -> TempPath {
+		TempPath(std::env::temp_dir().join(format!("log2vcd-{}-{}", std::process::id(), name)))
+	}
+
+	/// A temporary file with these contents.
+	pub fn with_contents(name: &str, contents: &str) -> TempPath {
+		let path = TempPath::new(name);
+		fs::write(&path, contents).unwrap();
+		path
+	}
+}
+
+impl Deref for TempPath {
+	type Target = Path;
+	fn deref(&self) -> &Path {
+		&self.0
+	}
+}
+
+impl AsRef<Path> for TempPath {
+	fn as_ref(&self) -> &Path {
+		&self.0
+	}
+}
+
+impl Drop for TempPath {
+	fn drop(&mut self) {
+		let _ = fs::remove_file(&self.0);
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn removed_when_dropped() {
+		let path = TempPath::with_contents("temp-removed", "contents");
+		let kept = path.to_path_buf();
+		assert_eq!("contents", fs::read_to_string(&path).unwrap());
+		drop(path);
+		assert!(!kept.exists());
+	}
+}
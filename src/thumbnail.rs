@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::io::{ self, Write };
+
+use crate::value_change::{ ScalarValue, Value, ValueChange };
+
+/// The most signals drawn, the first ones to change.
+const SIGNALS: usize = 8;
+/// The width of the waveforms, each signal has at most one point per pixel.
+const WIDTH: u64 = 400;
+/// The width of the signal names, left of the waveforms.
+const LABELS: u64 = 80;
+const ROW: u64 = 24;
+
+/// The level of a signal at each pixel it changes at.
+type Points = Vec<(u64, Option<f64>)>;
+
+/// The height of a value between 0 (the bottom) and 1 (the top), none if it isn't known. Vectors
+/// and reals are scaled to the range of their signal afterwards.
+fn level(value: &Value) -> Option<f64> {
+	match value {
+		Value::Scalar(ScalarValue::V0) => Some(0.0),
+		Value::Scalar(ScalarValue::V1) => Some(1.0),
+		Value::Scalar(_) => None,
+		Value::BinaryVector{value, ..} => value.iter().try_fold(0.0, |level, bit| match bit {
+			ScalarValue::V0 => Some(level * 2.0),
+			ScalarValue::V1 => Some(level * 2.0 + 1.0),
+			_ => None
+		}),
+		Value::Real(v) if v.is_finite() => Some(*v),
+		Value::Real(_) => None
+	}
+}
+
+fn escape(s: &str) -> String {
+	s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Writes an SVG of the first signals to change over the whole time range, a path per signal.
+///
+/// `value_changes` must already be sorted by timestamp. The changes are downsampled to the last one
+/// of each pixel, so the size is bounded whatever the length of the trace. Unknown values are drawn
+/// halfway up.
+pub fn write<W: Write>(mut out: W, value_changes: &[ValueChange]) -> io::Result<()> {
+	let end = value_changes.last().map_or(0, |change| change.timestamp).max(1);
+	// the signals in the order of their first change
+	let mut signals: Vec<(&str, Points)> = Vec::new();
+	let mut index: HashMap<&str, usize> = HashMap::new();
+	for change in value_changes {
+		let i = match index.get(change.signal_name.as_str()) {
+			Some(&i) => i,
+			None if signals.len() < SIGNALS => {
+				index.insert(&change.signal_name, signals.len());
+				signals.push((&change.signal_name, Vec::new()));
+				signals.len() - 1
+			},
+			None => continue
+		};
+		let x = (u128::from(change.timestamp) * u128::from(WIDTH) / u128::from(end)) as u64;
+		let points = &mut signals[i].1;
+		match points.last_mut() {
+			Some(last) if last.0 == x => last.1 = level(&change.value),
+			_ => points.push((x, level(&change.value)))
+		}
+	}
+
+	let height = ROW * signals.len() as u64;
+	writeln!(out, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">", LABELS + WIDTH, height)?;
+	for (row, (name, points)) in signals.iter().enumerate() {
+		let top = ROW * row as u64;
+		let (min, max) = points.iter().filter_map(|(_, level)| *level)
+			.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), level| (min.min(level), max.max(level)));
+		let y = |level: Option<f64>| {
+			let level = match level {
+				Some(level) if max > min => (level - min) / (max - min),
+				Some(level) if max == min => level.clamp(0.0, 1.0),
+				_ => 0.5
+			};
+			top as f64 + 4.0 + (1.0 - level) * (ROW - 8) as f64
+		};
+		let mut d = String::new();
+		for (i, &(x, level)) in points.iter().enumerate() {
+			let command = if i == 0 { format!("M{} {:.1}", LABELS + x, y(level)) } else { format!(" H{} V{:.1}", LABELS + x, y(level)) };
+			d.push_str(&command);
+		}
+		d.push_str(&format!(" H{}", LABELS + WIDTH));
+		writeln!(out, "<text x=\"2\" y=\"{}\" font-size=\"10\">{}</text>", top + ROW / 2 + 4, escape(name))?;
+		writeln!(out, "<path d=\"{}\" fill=\"none\" stroke=\"black\"/>", d)?;
+	}
+	writeln!(out, "</svg>")?;
+	out.flush()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use std::str::FromStr;
+
+	#[test]
+	fn paths() {
+		let input = "#0 clk 0 1\n#5 clk 1 1\n#10 clk 0 1\n#10 bus 1010 4\n#20 bus 1 4\n#20 r 1.5 f\n";
+		let changes: Vec<ValueChange> = input.lines().map(|line| ValueChange::from_str(line).unwrap()).collect();
+		let mut out = Vec::new();
+		write(&mut out, &changes).unwrap();
+		let svg = String::from_utf8(out).unwrap();
+		assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"480\" height=\"72\""));
+		assert_eq!(3, svg.matches("<path ").count());
+		assert!(svg.contains("<path d=\"M80 20.0 H180 V4.0 H280 V20.0 H480\""));
+		// a single real is drawn at the top
+		assert!(svg.contains("<path d=\"M480 52.0 H480\""));
+	}
+
+	#[test]
+	fn bounded() {
+		let changes: Vec<ValueChange> = (0..100_000u64)
+			.map(|t| ValueChange::from_str(&format!("#{} s{} {} 1", t, t % 20, t % 2)).unwrap())
+			.collect();
+		let mut out = Vec::new();
+		write(&mut out, &changes).unwrap();
+		let svg = String::from_utf8(out).unwrap();
+		assert_eq!(SIGNALS, svg.matches("<path ").count());
+		assert!(svg.len() < 100_000);
+	}
+}
@@ -0,0 +1,88 @@
+use std::cell::Cell;
+use std::io::{ self, Write };
+use std::rc::Rc;
+
+/// The bytes and lines written so far, shared between a `CountingWriter` and its `TimeIndex`.
+type Counts = Rc<Cell<(u64, u64)>>;
+
+/// Counts the bytes and lines written through it.
+pub struct CountingWriter<W: Write> {
+	inner: W,
+	counts: Counts
+}
+
+impl<W: Write> CountingWriter<W> {
+	pub fn new(inner: W) -> CountingWriter<W> {
+		CountingWriter { inner, counts: Rc::new(Cell::new((0, 0))) }
+	}
+
+	/// An index of the timestamps written through this writer.
+	pub fn index(&self, crlf: bool) -> TimeIndex {
+		TimeIndex { counts: self.counts.clone(), crlf, entries: Vec::new() }
+	}
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let n = self.inner.write(buf)?;
+		let (bytes, lines) = self.counts.get();
+		let newlines = buf[..n].iter().filter(|&&b| b == b'\n').count() as u64;
+		self.counts.set((bytes + n as u64, lines + newlines));
+		Ok(n)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
+
+/// The byte offset in the output of each distinct timestamp, for `--time-index`.
+pub struct TimeIndex {
+	counts: Counts,
+	/// The output is written with CRLF line endings after the count, one more byte per line
+	crlf: bool,
+	entries: Vec<(u64, u64)>
+}
+
+impl TimeIndex {
+	/// Records the offset of a timestamp about to be written, unless it was just written.
+	pub fn record(&mut self, timestamp: u64) {
+		if self.entries.last().is_some_and(|&(last, _)| last == timestamp) {
+			return;
+		}
+		let (bytes, lines) = self.counts.get();
+		let offset = if self.crlf { bytes + lines } else { bytes };
+		self.entries.push((timestamp, offset));
+	}
+
+	/// Writes the index as `timestamp offset` lines.
+	pub fn write<W: Write>(&self, mut output: W) -> io::Result<()> {
+		for (timestamp, offset) in &self.entries {
+			writeln!(output, "{} {}", timestamp, offset)?;
+		}
+		output.flush()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn offsets() {
+		let mut output = CountingWriter::new(Vec::new());
+		let mut index = output.index(false);
+		output.write_all(b"$end\n").unwrap();
+		index.record(10);
+		output.write_all(b"#10\n").unwrap();
+		index.record(10);
+		index.record(20);
+		let mut written = Vec::new();
+		index.write(&mut written).unwrap();
+		assert_eq!("10 5\n20 9\n", String::from_utf8(written).unwrap());
+
+		let mut crlf = output.index(true);
+		crlf.record(30);
+		assert_eq!(vec![(30, 11)], crlf.entries);
+	}
+}
@@ -1,23 +1,322 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
 use std::str::FromStr;
 use std::vec::Vec;
 
-use vcd;
+use serde::{ Serialize, Deserialize };
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Value {
 	Scalar(ScalarValue),
 	BinaryVector{width: usize, value: Vec<ScalarValue>},
 	Real(f64)
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// The kind of value a value type marker stands for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValueType {
+	/// A width, `1` being a scalar
+	Width(usize),
+	/// `<width>s`, a signed decimal integer
+	Signed(usize),
+	/// `f`, a real
+	Real
+}
+
+/// The recognized value types of a log line, other than widths: the suffix (for markers following
+/// a width) or the marker itself.
+pub const MARKERS: [&str; 2] = ["s", "f"];
+
+/// The widest vector a value type may declare, wider ones are taken for garbage rather than
+/// allocated.
+pub const MAX_WIDTH: usize = 1 << 24;
+
+impl ValueType {
+	/// Looks up the value type of a marker.
+	///
+	/// Markers made of letters that aren't in `MARKERS` are `UnknownMarker`s, anything else that
+	/// isn't a value type is an `InvalidValueType`.
+	pub fn from_marker(marker: &str) -> Result<ValueType, ParseValueChangeError> {
+		let digits = marker.len() - marker.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+		let (width, suffix) = marker.split_at(digits);
+		let width = match width {
+			"" => None,
+			width => Some(usize::from_str(width).ok().filter(|&width| width <= MAX_WIDTH).ok_or(ParseValueChangeError::InvalidValueType)?)
+		};
+		match (width, suffix) {
+			(Some(width), "") => Ok(ValueType::Width(width)),
+			(Some(width), "s") => Ok(ValueType::Signed(width)),
+			(None, "f") => Ok(ValueType::Real),
+			(_, suffix) if suffix.chars().all(|c| c.is_ascii_alphabetic()) && !MARKERS.contains(&suffix) => Err(ParseValueChangeError::UnknownMarker),
+			_ => Err(ParseValueChangeError::InvalidValueType)
+		}
+	}
+}
+
+impl Value {
+	/// Parses a value from its textual value and value type (a width, or `f` for reals).
+	///
+	/// Values of width 1 are always scalars, however they're written. The value may be wrapped in
+	/// a pair of brackets or parentheses (`[1010]`, `(0xF2)`), a `0x` prefix is always hex, `0x1X`
+	/// having an unknown low nibble. A `width:radix` value type such as `8:hex` reads
+	/// the value in that radix, one of `bin`, `oct`, `dec` or `hex`.
+	pub fn parse(value_str: &str, value_type_str: &str) -> Result<Value, ParseValueChangeError> {
+		let value_str = strip_brackets(value_str);
+		if let Some((width, radix)) = value_type_str.split_once(':') {
+			let tag = match radix {
+				"bin" => 'b',
+				"oct" => 'o',
+				"dec" => 'd',
+				"hex" => 'h',
+				_ => return Err(ParseValueChangeError::InvalidValueType)
+			};
+			return match ValueType::from_marker(width)? {
+				value_type @ ValueType::Width(_) => Value::parse_radix(&format!("{}{}", tag, value_str), value_type),
+				_ => Err(ParseValueChangeError::InvalidValueType)
+			};
+		}
+		let value = match value_str.strip_prefix("0x").or_else(|| value_str.strip_prefix("0X")) {
+			Some(digits) => Value::parse_radix(&format!("h{}", digits), ValueType::from_marker(value_type_str)?),
+			None => Value::parse_vector(value_str, value_type_str)
+		};
+		match value? {
+			Value::BinaryVector{width: 1, value} => Ok(Value::Scalar(left_extend(&value, 1)[0].clone())),
+			value => Ok(value)
+		}
+	}
+
+	/// Parses a value like `parse`, values of width 1 may be single bit vectors.
+	fn parse_vector(value_str: &str, value_type_str: &str) -> Result<Value, ParseValueChangeError> {
+		let value_type = ValueType::from_marker(value_type_str)?;
+		if let Some(c) = value_str.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')) {
+			return Value::parse_char(c, value_type);
+		}
+		if let Some(literal) = value_str.strip_prefix('\'') {
+			return Value::parse_radix(literal, value_type);
+		}
+		match value_type {
+			ValueType::Real => {
+				let real = f64::from_str(value_str).map_err(|_| ParseValueChangeError::InvalidValue)?;
+				Ok(Value::Real(real))
+			},
+			ValueType::Signed(width) => Value::parse_signed(value_str, width),
+			// a vector token such as `b1`, as in VCD
+			ValueType::Width(width) if value_str.len() > 1 && value_str.starts_with(['b', 'B']) => {
+				Value::parse_vector(&value_str[1..], &width.to_string())
+			},
+			ValueType::Width(1) if value_str == "-" => Ok(Value::Scalar(ScalarValue::X)),
+			ValueType::Width(1) => {
+				if value_str.len() > 1 && value_str.chars().all(|c| ScalarValue::try_from(c).is_ok()) {
+					return Err(ParseValueChangeError::MultiBitScalar);
+				}
+				let value = ScalarValue::from_str(value_str).map_err(|_| ParseValueChangeError::InvalidValue)?;
+				Ok(Value::Scalar(value))
+			},
+			ValueType::Width(width) => {
+				let mut vec = Vec::with_capacity(value_str.len());
+				for c in value_str.chars() {
+					// `-` is a don't care bit, which VCD can only represent as unknown
+					let bit = if c == '-' { Ok(ScalarValue::X) } else { ScalarValue::try_from(c) };
+					vec.push(bit.map_err(|_| ParseValueChangeError::InvalidValue)?);
+				}
+				if vec.len() > width {
+					return Err(ParseValueChangeError::ValueTooLargeForVecWidth);
+				}
+				Ok(Value::BinaryVector{width, value: vec})
+			}
+		}
+	}
+
+	/// Parses the character of a quoted character value as a vector of its code point, with all of
+	/// its bits.
+	fn parse_char(c: &str, value_type: ValueType) -> Result<Value, ParseValueChangeError> {
+		let mut chars = c.chars();
+		let (code, width) = match (chars.next(), chars.next(), value_type) {
+			(Some(c), None, ValueType::Width(width)) => (u32::from(c), width),
+			_ => return Err(ParseValueChangeError::InvalidValue)
+		};
+		if width < 32 && code >> width != 0 {
+			return Err(ParseValueChangeError::ValueTooLargeForVecWidth);
+		}
+		match Value::from_integer(i64::from(code), 64) {
+			_ if width == 1 => Ok(Value::Scalar(if code == 1 { ScalarValue::V1 } else { ScalarValue::V0 })),
+			Value::BinaryVector{value, ..} => Ok(Value::BinaryVector{width, value: left_extend(&value, width)}),
+			_ => unreachable!()
+		}
+	}
+
+	/// Parses a Verilog-style radix literal without its quote (`hFF`, `b1010`, `o17` or `d255`)
+	/// as a vector of the width. Digits of x or z are unknown in all of their bits.
+	fn parse_radix(literal: &str, value_type: ValueType) -> Result<Value, ParseValueChangeError> {
+		let width = match value_type {
+			ValueType::Width(width) | ValueType::Signed(width) => width,
+			ValueType::Real => return Err(ParseValueChangeError::InvalidValue)
+		};
+		let mut chars = literal.chars();
+		let tag = chars.next().map(|c| c.to_ascii_lowercase());
+		let digits: String = chars.filter(|&c| c != '_').collect();
+		let digit_bits = match tag {
+			_ if digits.is_empty() => return Err(ParseValueChangeError::InvalidValue),
+			Some('b') => 1,
+			Some('o') => 3,
+			Some('h') => 4,
+			Some('d') => 0,
+			_ => return Err(ParseValueChangeError::InvalidValue)
+		};
+		let mut bits = Vec::new();
+		if digit_bits == 0 {
+			if digits.contains(|c| "xXzZ".contains(c)) {
+				return Err(ParseValueChangeError::UnknownDecimalDigit);
+			}
+			let value = u128::from_str(&digits).map_err(|_| ParseValueChangeError::InvalidValue)?;
+			let len = (128 - value.leading_zeros() as usize).max(1);
+			bits.extend((0..len).rev().map(|i| if (value >> i) & 1 == 1 { ScalarValue::V1 } else { ScalarValue::V0 }));
+		}
+		for c in digits.chars().filter(|_| digit_bits > 0) {
+			let digit = match c {
+				'x' | 'X' => Err(ScalarValue::X),
+				'z' | 'Z' => Err(ScalarValue::Z),
+				c => Ok(c.to_digit(1 << digit_bits).ok_or(ParseValueChangeError::InvalidValue)?)
+			};
+			bits.extend((0..digit_bits).rev().map(|i| match digit {
+				Ok(digit) if (digit >> i) & 1 == 1 => ScalarValue::V1,
+				Ok(_) => ScalarValue::V0,
+				Err(ref unknown) => unknown.clone()
+			}));
+		}
+		// leading zeros past the width are dropped, other bits don't fit
+		let excess = bits.len().saturating_sub(width);
+		if bits[..excess].iter().any(|bit| *bit != ScalarValue::V0) {
+			return Err(ParseValueChangeError::ValueTooLargeForVecWidth);
+		}
+		let bits = &bits[excess..];
+		if width == 1 {
+			return Ok(Value::Scalar(bits[0].clone()));
+		}
+		Ok(Value::BinaryVector{width, value: left_extend(bits, width)})
+	}
+
+	/// Parses a decimal integer as a two's complement vector of a signed (`8s`) value type.
+	fn parse_signed(value_str: &str, width: usize) -> Result<Value, ParseValueChangeError> {
+		if width == 0 {
+			return Err(ParseValueChangeError::InvalidValueType);
+		}
+		let value = i128::from_str(value_str).map_err(|_| ParseValueChangeError::InvalidValue)?;
+		if width < 128 {
+			let limit = 1i128 << (width - 1);
+			if value < -limit || value >= limit {
+				return Err(ParseValueChangeError::ValueTooLargeForVecWidth);
+			}
+		}
+		Ok(Value::twos_complement(value, width))
+	}
+
+	/// Encodes an integer as a two's complement binary vector of the given width.
+	///
+	/// Non-negative values are written without leading zeros, values that don't fit in `width` bits
+	/// saturate at the representable range.
+	pub fn from_integer(value: i64, width: usize) -> Value {
+		let value = if width == 0 || width >= 64 {
+			value
+		}
+		else {
+			let max = (1i64 << (width - 1)) - 1;
+			value.max(-max - 1).min(max)
+		};
+		Value::twos_complement(i128::from(value), width)
+	}
+
+	/// Encodes an integer that fits in `width` bits as a two's complement binary vector. Negative
+	/// values are sign extended to the whole width, VCD would extend them with zeros.
+	fn twos_complement(value: i128, width: usize) -> Value {
+		let bits = if value < 0 { width } else { (128 - value.leading_zeros() as usize).max(1) };
+		let value = (0..bits).rev().map(|i| {
+			if (value >> i.min(127)) & 1 == 1 { ScalarValue::V1 } else { ScalarValue::V0 }
+		}).collect();
+		Value::BinaryVector{width, value}
+	}
+
+	/// Whether two values are the same once vectors are extended to their width, so `b10` and
+	/// `b0010` of a 4 bit vector are the same but vectors of different widths never are.
+	pub fn same(&self, other: &Value) -> bool {
+		match (self, other) {
+			(Value::BinaryVector{width, value}, Value::BinaryVector{width: other_width, value: other_value}) => {
+				width == other_width && left_extend(value, *width) == left_extend(other_value, *width)
+			},
+			(value, other) => value == other
+		}
+	}
+
+	/// A total order of the values, scalars before vectors before reals, so changes that only
+	/// differ by their value always sort the same way.
+	pub fn canonical_cmp(&self, other: &Value) -> Ordering {
+		let kind = |value: &Value| match value {
+			Value::Scalar(_) => 0,
+			Value::BinaryVector{..} => 1,
+			Value::Real(_) => 2
+		};
+		match (self, other) {
+			(Value::Scalar(a), Value::Scalar(b)) => a.to_char().cmp(&b.to_char()),
+			(Value::BinaryVector{width, value}, Value::BinaryVector{width: other_width, value: other_value}) => {
+				width.cmp(other_width).then_with(|| value.iter().map(ScalarValue::to_char).cmp(other_value.iter().map(ScalarValue::to_char)))
+			},
+			(Value::Real(a), Value::Real(b)) => a.total_cmp(b),
+			(a, b) => kind(a).cmp(&kind(b))
+		}
+	}
+
+	/// Whether every bit of the value is unknown (`x` or `z`), a real is never unknown.
+	pub fn is_unknown(&self) -> bool {
+		let unknown = |v: &ScalarValue| *v == ScalarValue::X || *v == ScalarValue::Z;
+		match self {
+			Value::Scalar(value) => unknown(value),
+			Value::BinaryVector{value, ..} => !value.is_empty() && value.iter().all(unknown),
+			Value::Real(_) => false
+		}
+	}
+
+	/// Encodes a real as a fixed point binary vector with `frac_bits` fractional bits.
+	pub fn fixed_point(real: f64, frac_bits: u32, width: usize) -> Value {
+		let scaled = (real * 2f64.powi(frac_bits as i32)).round();
+		Value::from_integer(scaled as i64, width)
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ScalarValue {
 	V0, V1, X, Z
 }
 
-impl Into<vcd::Value> for ScalarValue {
-	fn into(self) -> vcd::Value {
-		match self {
+/// Strips a single pair of brackets or parentheses around a value.
+fn strip_brackets(value_str: &str) -> &str {
+	let stripped = value_str.strip_prefix('[').and_then(|s| s.strip_suffix(']'))
+		.or_else(|| value_str.strip_prefix('(').and_then(|s| s.strip_suffix(')')));
+	match stripped {
+		Some(inner) if !inner.is_empty() => inner,
+		_ => value_str
+	}
+}
+
+/// Extends a vector value to `width` bits the way VCD does: with 0 when the most significant bit is
+/// 0 or 1, otherwise with copies of the x or z most significant bit.
+pub fn left_extend(bits: &[ScalarValue], width: usize) -> Vec<ScalarValue> {
+	if bits.len() >= width {
+		return bits.to_vec();
+	}
+	let fill = match bits.first() {
+		Some(ScalarValue::X) => ScalarValue::X,
+		Some(ScalarValue::Z) => ScalarValue::Z,
+		_ => ScalarValue::V0
+	};
+	let mut extended = vec![fill; width - bits.len()];
+	extended.extend_from_slice(bits);
+	extended
+}
+
+impl From<ScalarValue> for vcd::Value {
+	fn from(value: ScalarValue) -> vcd::Value {
+		match value {
 			ScalarValue::V0 => vcd::Value::V0,
 			ScalarValue::V1 => vcd::Value::V1,
 			ScalarValue::X => vcd::Value::X,
@@ -26,14 +325,72 @@ impl Into<vcd::Value> for ScalarValue {
 	}
 }
 
+impl From<vcd::Value> for ScalarValue {
+	fn from(value: vcd::Value) -> ScalarValue {
+		match value {
+			vcd::Value::V0 => ScalarValue::V0,
+			vcd::Value::V1 => ScalarValue::V1,
+			vcd::Value::X => ScalarValue::X,
+			vcd::Value::Z => ScalarValue::Z
+		}
+	}
+}
+
+impl ScalarValue {
+	/// The canonical (lowercase) character of the value.
+	pub fn to_char(&self) -> char {
+		match self {
+			ScalarValue::V0 => '0',
+			ScalarValue::V1 => '1',
+			ScalarValue::X => 'x',
+			ScalarValue::Z => 'z'
+		}
+	}
+}
+
+impl TryFrom<char> for ScalarValue {
+	type Error = ();
+	fn try_from(c: char) -> Result<ScalarValue, ()> {
+		match c {
+			'0' => Ok(ScalarValue::V0),
+			'1' => Ok(ScalarValue::V1),
+			'x' | 'X' => Ok(ScalarValue::X),
+			'z' | 'Z' => Ok(ScalarValue::Z),
+			_ => Err(())
+		}
+	}
+}
+
+/// The Verilog drive strengths, from supply to high impedance.
+pub const STRENGTHS: [&str; 8] = ["Su", "St", "Pu", "La", "We", "Me", "Sm", "Hi"];
+
+/// Splits a value with a drive strength such as `St1` or `Pu0` into its strength and value.
+pub fn split_strength(s: &str) -> Option<(&'static str, ScalarValue)> {
+	let strength = *STRENGTHS.iter().find(|strength| s.get(..2) == Some(**strength))?;
+	let mut chars = s[2..].chars();
+	match (chars.next(), chars.next()) {
+		(Some(c), None) => Some((strength, ScalarValue::try_from(c).ok()?)),
+		_ => None
+	}
+}
+
+/// Parses a single value character, a `true`/`false` boolean (in any case), or a value with a drive
+/// strength, which VCD can't represent and is dropped.
 impl FromStr for ScalarValue {
 	type Err = ();
 	fn from_str(s: &str) -> Result<ScalarValue, ()> {
-		match s {
-			"0" => Ok(ScalarValue::V0),
-			"1" => Ok(ScalarValue::V1),
-			"x" | "X" => Ok(ScalarValue::X),
-			"z" | "Z" => Ok(ScalarValue::Z),
+		if s.eq_ignore_ascii_case("true") {
+			return Ok(ScalarValue::V1);
+		}
+		if s.eq_ignore_ascii_case("false") {
+			return Ok(ScalarValue::V0);
+		}
+		if let Some((_, value)) = split_strength(s) {
+			return Ok(value);
+		}
+		let mut chars = s.chars();
+		match (chars.next(), chars.next()) {
+			(Some(c), None) => ScalarValue::try_from(c),
 			_ => Err(())
 		}
 	}
@@ -41,98 +398,699 @@ impl FromStr for ScalarValue {
 
 /// Log syntax:
 ///
-/// ```
+/// ```text
 /// #timestamp signal_name value < size | f >
 /// ```
 ///
-/// timestamp: integer in the range [0,2^64)
+/// timestamp: integer in the range [0,2^64), or a decimal number if the log has its own time unit.
+/// With `ParseOptions::inline_units` it may be followed by a unit (`#100ns`).
 ///
-/// signal_name: The name of the signal. Must start with an alphabet character (a-zA-Z).
+/// signal_name: The name of the signal. Must start with an alphabet character (a-zA-Z), unless
+/// `ParseOptions::allow_numeric_names` is set. Elements of arrays (memories) are named `name[index]`.
 ///
-/// value: the value, followed by either the size for a scalar/binary vector, or f for a floating point value.
+/// value: the value (scalars may also be `true` or `false`, `-` bits of vectors are don't cares read as `x`),
+/// followed by either the size for a scalar/binary vector, or f for a floating point value.
+/// A size with an `s` suffix (`8s`) is a signed vector, its value is a decimal integer written in
+/// two's complement. A quoted character (`'A'`) is a vector of its code point.
+/// With `FieldOrder::WidthValue` the size (or f) comes before the value instead.
 ///
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ValueChange {
 	pub timestamp: u64,
 	pub signal_name: String,
-	pub value: Value
+	pub value: Value,
+	/// The line of the log the change was read from, if it was read from a single line
+	pub line: Option<usize>,
+	/// The top scope of the signal when it isn't the default one, the signal name starts with it
+	/// (`scope.name`)
+	pub scope: Option<String>,
+	/// The drive strength the value was logged with, such as `St`, which VCD values don't have
+	#[serde(skip)]
+	pub strength: Option<&'static str>
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ParseValueChangeError {
 	InvalidFormat,
 	ParseTimestampErr,
 	InvalidValueType,
 	InvalidValue,
-	ValueTooLargeForVecWidth
+	/// The value of a width 1 signal was more than a single 0, 1, x or z
+	MultiBitScalar,
+	ValueTooLargeForVecWidth,
+	/// The signal name doesn't start with a letter
+	InvalidSignalName,
+	/// The value type is a marker that isn't one of `MARKERS`
+	UnknownMarker,
+	/// A decimal value has an x or z digit, which has no bits of its own
+	UnknownDecimalDigit
 }
 
-impl FromStr for ValueChange {
-	type Err = ParseValueChangeError;
+impl ParseValueChangeError {
+	/// A short, stable identifier of the error for tooling.
+	pub fn code(&self) -> &'static str {
+		match self {
+			ParseValueChangeError::InvalidFormat => "invalid-format",
+			ParseValueChangeError::ParseTimestampErr => "invalid-timestamp",
+			ParseValueChangeError::InvalidValueType => "invalid-value-type",
+			ParseValueChangeError::InvalidValue => "invalid-value",
+			ParseValueChangeError::MultiBitScalar => "multi-bit-scalar",
+			ParseValueChangeError::ValueTooLargeForVecWidth => "value-too-large",
+			ParseValueChangeError::InvalidSignalName => "invalid-signal-name",
+			ParseValueChangeError::UnknownMarker => "unknown-marker",
+			ParseValueChangeError::UnknownDecimalDigit => "unknown-decimal-digit"
+		}
+	}
+}
 
-	fn from_str(s: &str) -> Result<ValueChange,ParseValueChangeError> {
+impl fmt::Display for ParseValueChangeError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let message = match self {
+			ParseValueChangeError::InvalidFormat => "line is not of the form '#timestamp signal_name value < size | f >'",
+			ParseValueChangeError::ParseTimestampErr => "timestamp is not an integer in the range [0,2^64)",
+			ParseValueChangeError::InvalidValueType => "value type must be a width or 'f'",
+			ParseValueChangeError::InvalidValue => "value doesn't match its value type",
+			ParseValueChangeError::MultiBitScalar => "scalar value must be a single 0, 1, x or z",
+			ParseValueChangeError::ValueTooLargeForVecWidth => "value has more bits than the vector width",
+			ParseValueChangeError::InvalidSignalName => "signal name must start with a letter, can only have a '/' or ':' in a scope separator and can't have an empty scope",
+			ParseValueChangeError::UnknownMarker => "value type is an unknown marker",
+			ParseValueChangeError::UnknownDecimalDigit => "decimal value can't have unknown digits"
+		};
+		write!(f, "{}", message)
+	}
+}
+
+/// A `ParseValueChangeError` along with the 1-based column of the field that caused it, in
+/// characters of the line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+	pub error: ParseValueChangeError,
+	pub column: usize
+}
+
+impl ParseError {
+	/// Converts the column from a 1-based byte offset of `line` to a 1-based character offset.
+	fn in_chars(self, line: &str) -> ParseError {
+		let offset = self.column.saturating_sub(1);
+		let column = line.char_indices().take_while(|(i, _)| *i < offset).count() + 1 + offset.saturating_sub(line.len());
+		ParseError { column, ..self }
+	}
+}
+
+/// Order of the value and value type fields in a log line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FieldOrder {
+	/// `#timestamp signal_name value < size | f >`
+	#[default]
+	ValueWidth,
+	/// `#timestamp signal_name < size | f > value`
+	WidthValue
+}
+
+impl FromStr for FieldOrder {
+	type Err = String;
+	fn from_str(s: &str) -> Result<FieldOrder, String> {
+		match s {
+			"value-width" => Ok(FieldOrder::ValueWidth),
+			"width-value" => Ok(FieldOrder::WidthValue),
+			_ => Err(format!("invalid field order '{}', expected 'value-width' or 'width-value'", s))
+		}
+	}
+}
+
+/// How timestamps between two ticks are snapped to one of them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SnapMode {
+	#[default]
+	Nearest,
+	/// The tick before the timestamp
+	Floor,
+	/// The tick after the timestamp
+	Ceil
+}
+
+impl SnapMode {
+	/// Snaps a number of ticks to a whole one. Ticks within rounding error of a whole tick are that
+	/// tick, so that `0.3` units of `0.1` aren't snapped to `4` with `Ceil`.
+	fn snap(self, ticks: f64) -> f64 {
+		if (ticks - ticks.round()).abs() < 1e-9 {
+			return ticks.round();
+		}
+		match self {
+			SnapMode::Nearest => ticks.round(),
+			SnapMode::Floor => ticks.floor(),
+			SnapMode::Ceil => ticks.ceil()
+		}
+	}
+}
+
+impl FromStr for SnapMode {
+	type Err = String;
+	fn from_str(s: &str) -> Result<SnapMode, String> {
+		match s {
+			"nearest" => Ok(SnapMode::Nearest),
+			"floor" => Ok(SnapMode::Floor),
+			"ceil" => Ok(SnapMode::Ceil),
+			_ => Err(format!("invalid snap mode '{}', expected 'nearest', 'floor' or 'ceil'", s))
+		}
+	}
+}
+
+/// The fields of a positional log line picked by `--columns`, as indices of its whitespace
+/// separated fields.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Columns {
+	pub timestamp: usize,
+	pub name: usize,
+	pub value: usize,
+	pub value_type: usize
+}
+
+/// Parses the 1-based columns of the timestamp, name, value and value type, such as `1,3,4,5`.
+impl FromStr for Columns {
+	type Err = String;
+	fn from_str(s: &str) -> Result<Columns, String> {
+		let invalid = || format!("invalid columns '{}', expected the columns of the timestamp, name, value and type such as '1,3,4,5'", s);
+		let columns: Vec<usize> = s.split(',')
+			.map(|column| usize::from_str(column.trim()).ok().filter(|&c| c > 0).map(|c| c - 1))
+			.collect::<Option<_>>()
+			.ok_or_else(invalid)?;
+		match columns[..] {
+			[timestamp, name, value, value_type] if (1..4).all(|i| !columns[..i].contains(&columns[i])) => {
+				Ok(Columns { timestamp, name, value, value_type })
+			},
+			_ => Err(invalid())
+		}
+	}
+}
+
+/// Syntax of the log lines.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InputFormat {
+	/// `#timestamp signal_name value < size | f >`, see `ValueChange`
+	#[default]
+	Log,
+	/// `t=timestamp sig=signal_name val=value w=< size | f >` in any order
+	Kv,
+	/// A JSON object per timestamp, `{"t": timestamp, "signal_name": value, ...}`, see `ValueChange::parse_json`
+	JsonLines,
+	/// `LEVEL #timestamp signal_name value < size | f >`, a log line after a severity `Level`
+	Leveled,
+	/// `#timestamp` lines, each followed by the `signal_name value < size | f >` lines of the timestamp.
+	/// The lines are parsed once they're prefixed with their timestamp, like log lines
+	VcdLike
+}
+
+impl FromStr for InputFormat {
+	type Err = String;
+	fn from_str(s: &str) -> Result<InputFormat, String> {
+		match s {
+			"log" => Ok(InputFormat::Log),
+			"kv" => Ok(InputFormat::Kv),
+			"json-lines" => Ok(InputFormat::JsonLines),
+			"leveled" => Ok(InputFormat::Leveled),
+			"vcd-like" => Ok(InputFormat::VcdLike),
+			_ => Err(format!("invalid input format '{}', expected 'log', 'kv', 'json-lines', 'leveled' or 'vcd-like'", s))
+		}
+	}
+}
+
+/// Severity of a line of a `InputFormat::Leveled` log, from the least to the most severe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Level {
+	Trace,
+	Debug,
+	Info,
+	Warn,
+	Error
+}
+
+impl FromStr for Level {
+	type Err = String;
+	fn from_str(s: &str) -> Result<Level, String> {
+		match s.to_ascii_uppercase().as_str() {
+			"TRACE" => Ok(Level::Trace),
+			"DEBUG" => Ok(Level::Debug),
+			"INFO" => Ok(Level::Info),
+			"WARN" | "WARNING" => Ok(Level::Warn),
+			"ERROR" => Ok(Level::Error),
+			_ => Err(format!("invalid level '{}', expected one of 'trace', 'debug', 'info', 'warn' or 'error'", s))
+		}
+	}
+}
+
+/// Splits the level of a line of a `InputFormat::Leveled` log from the rest of the line.
+pub fn split_level(s: &str) -> Option<(Level, &str)> {
+	let s = s.trim_start();
+	let end = s.find(char::is_whitespace)?;
+	let level = Level::from_str(&s[..end]).ok()?;
+	Some((level, &s[end..]))
+}
+
+/// Options controlling how log lines are parsed.
+#[derive(Clone, Debug, Default)]
+pub struct ParseOptions {
+	pub input_format: InputFormat,
+	pub field_order: FieldOrder,
+	/// Number of output ticks per unit of the timestamps in the log. When set, timestamps may have
+	/// a fractional part and are snapped to a tick.
+	pub timestamp_scale: Option<f64>,
+	/// How timestamps between two ticks are snapped, with a `timestamp_scale` or `inline_units`
+	pub snap_mode: SnapMode,
+	/// Allow signal names starting with a digit, such as numbered channels
+	pub allow_numeric_names: bool,
+	/// Timescale of the output. When set, timestamps may have a unit suffix (`#100ns`) and are
+	/// converted to output ticks.
+	pub inline_units: Option<(u32, vcd::TimescaleUnit)>,
+	/// Fields of the lines picked by position instead of the usual syntax
+	pub columns: Option<Columns>,
+	/// Width of the values of lines without a width field (`#100 sig 1`). Without it, such lines
+	/// are invalid.
+	pub assume_width: Option<usize>,
+	/// Separator of the scopes in signal names other than `.`, such as `/` or `::`. Names may only
+	/// have a `/` or `:` in it.
+	pub scope_separator: Option<String>,
+	/// Names are split into nested scopes at the scope separator, none of the scopes may then be
+	/// empty (`.a`, `a.` or `a..b`)
+	pub nest: bool,
+	/// Names that aren't split into scopes when nesting, such as ip addresses
+	pub no_nest_patterns: Vec<regex::Regex>
+}
+
+impl ParseOptions {
+	/// Whether a signal name starts with a letter, unless numeric names are allowed, has no `/`
+	/// or `:` other than the ones of the scope separator and no empty scope when nesting.
+	fn valid_name(&self, name: &str) -> bool {
+		if !self.allow_numeric_names && !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+			return false;
+		}
+		let nested = self.nest && !self.no_nest_patterns.iter().any(|pattern| pattern.is_match(name));
+		if nested && name.split(self.scope_separator.as_deref().unwrap_or(".")).any(str::is_empty) {
+			return false;
+		}
+		let separator = self.scope_separator.as_deref().unwrap_or("");
+		let rest = if separator.is_empty() { name.to_string() } else { name.replace(separator, ".") };
+		!rest.contains(&['/', ':'][..])
+	}
+}
+
+/// Parses the unit suffix of a timestamp, microseconds are either `us` or `µs`.
+pub fn parse_time_unit(s: &str) -> Option<vcd::TimescaleUnit> {
+	match s {
+		"µs" | "μs" => Some(vcd::TimescaleUnit::US),
+		_ => vcd::TimescaleUnit::from_str(s).ok()
+	}
+}
+
+/// Converts a timestamp with a unit suffix to ticks of the `(step_size, unit)` output timescale.
+fn parse_unit_timestamp(s: &str, (step_size, output_unit): (u32, vcd::TimescaleUnit), snap_mode: SnapMode) -> Option<u64> {
+	let digits = s.find(|c: char| c.is_alphabetic())?;
+	let unit = parse_time_unit(&s[digits..])?;
+	let scale = output_unit.divisor() as f64 / unit.divisor() as f64 / f64::from(step_size);
+	parse_timestamp(&s[..digits], Some(scale), None, snap_mode)
+}
+
+/// Parses a timestamp, scaling it to output ticks if the log has its own time unit.
+fn parse_timestamp(s: &str, scale: Option<f64>, inline_units: Option<(u32, vcd::TimescaleUnit)>, snap_mode: SnapMode) -> Option<u64> {
+	if s.ends_with(|c: char| c.is_ascii_alphabetic()) {
+		return parse_unit_timestamp(s, inline_units?, snap_mode);
+	}
+	match scale {
+		Some(scale) => {
+			let ticks = snap_mode.snap(f64::from_str(s).ok()? * scale);
+			if ticks < 0.0 || ticks >= u64::MAX as f64 {
+				return None;
+			}
+			Some(ticks as u64)
+		},
+		None => u64::from_str(s).ok()
+	}
+}
+
+/// Parses a timed comment directive, `#timestamp $comment text`.
+pub fn parse_comment(s: &str, options: &ParseOptions) -> Option<(u64, String)> {
+	use regex::Regex;
+
+	lazy_static! {
+		static ref RE: Regex = Regex::new(r#"^#(\d+(?:\.\d+)?(?:[munpfµμ]?s)?)\s+\$comment\s+(.*)$"#).unwrap();
+	}
+
+	let caps = RE.captures(s.trim())?;
+	let timestamp = parse_timestamp(caps.get(1).unwrap().as_str(), options.timestamp_scale, options.inline_units, options.snap_mode)?;
+	Some((timestamp, String::from(caps.get(2).unwrap().as_str())))
+}
+
+/// Parses a base timestamp directive, `BASE timestamp`, the timestamps of the lines after it are
+/// relative to it. A negative base (`BASE -100`) is subtracted from them.
+pub fn parse_base(s: &str, options: &ParseOptions) -> Option<i128> {
+	let mut fields = s.split_whitespace();
+	let (negative, timestamp) = match (fields.next(), fields.next(), fields.next()) {
+		(Some("BASE"), Some(timestamp), None) => match timestamp.strip_prefix('-') {
+			Some(timestamp) => (true, timestamp),
+			None => (false, timestamp)
+		},
+		_ => return None
+	};
+	let timestamp = i128::from(parse_timestamp(timestamp, options.timestamp_scale, options.inline_units, options.snap_mode)?);
+	Some(if negative { -timestamp } else { timestamp })
+}
+
+/// The 1-based byte columns of a key of a JSON `line` and of its value, the key is searched from
+/// byte `start` on. Both are 1 if the key can't be found.
+fn json_columns(line: &str, start: usize, key: &str) -> (usize, usize) {
+	let quoted = serde_json::to_string(key).unwrap();
+	let key_start = match line.get(start..).and_then(|rest| rest.find(&quoted)) {
+		Some(i) => start + i,
+		None => return (1, 1)
+	};
+	let after = key_start + quoted.len();
+	let value_start = line[after..].trim_start().strip_prefix(':').map_or(after, |value| line.len() - value.trim_start().len());
+	(key_start + 1, value_start + 1)
+}
+
+/// Encodes an integer of a JSON line as a vector of the fewest bits that hold it, in two's
+/// complement if it's negative. A single bit is a scalar.
+fn json_integer(value: i64) -> Value {
+	let width = if value < 0 { 65 - (!value).leading_zeros() as usize } else { (64 - value.leading_zeros() as usize).max(1) };
+	match Value::twos_complement(i128::from(value), width) {
+		Value::BinaryVector{width: 1, value} => Value::Scalar(value[0].clone()),
+		vector => vector
+	}
+}
+
+/// Adds the value changes of the fields of a JSON object of `parse_json`, prefixing their names.
+/// The object starts at byte `start` of `line`.
+fn json_fields(timestamp: u64, prefix: &str, object: serde_json::Map<String, serde_json::Value>, (line, start): (&str, usize), options: &ParseOptions, changes: &mut Vec<ValueChange>) -> Result<(), ParseError> {
+	for (name, value) in object {
+		let (name_column, value_column) = json_columns(line, start, &name);
+		let error = |error| ParseError { error, column: value_column };
+		if !options.valid_name(&name) {
+			return Err(ParseError { error: ParseValueChangeError::InvalidSignalName, column: name_column });
+		}
+		let name = format!("{}{}", prefix, name);
+		let value = match value {
+			serde_json::Value::Object(object) => {
+				json_fields(timestamp, &format!("{}.", name), object, (line, value_column - 1), options, changes)?;
+				continue;
+			},
+			serde_json::Value::Bool(b) => Value::Scalar(if b { ScalarValue::V1 } else { ScalarValue::V0 }),
+			serde_json::Value::Number(n) => match n.as_i64() {
+				Some(i) => json_integer(i),
+				None => Value::Real(n.as_f64().ok_or(error(ParseValueChangeError::InvalidValue))?)
+			},
+			serde_json::Value::String(bits) => Value::parse(&bits, &bits.len().to_string()).map_err(error)?,
+			_ => return Err(error(ParseValueChangeError::InvalidValue))
+		};
+		changes.push(ValueChange { timestamp, signal_name: name, value, line: None, scope: None, strength: None });
+	}
+	Ok(())
+}
+
+/// Parses a raw VCD timestamp line, `#timestamp`.
+pub fn parse_vcd_timestamp(s: &str) -> Option<u64> {
+	s.trim().strip_prefix('#').and_then(|t| u64::from_str(t).ok())
+}
+
+/// Parses a raw VCD value change line: `0!`, `b1010 "` or `r1.5 #`.
+pub fn parse_vcd_change(s: &str) -> Option<(vcd::IdCode, Value)> {
+	let s = s.trim();
+	let mut chars = s.chars();
+	match chars.next()? {
+		'b' | 'B' | 'r' | 'R' => {
+			let mut fields = s[1..].split_whitespace();
+			let (value, id) = (fields.next()?, fields.next()?);
+			if fields.next().is_some() {
+				return None;
+			}
+			let value = if s.starts_with(['r', 'R']) {
+				Value::Real(f64::from_str(value).ok()?)
+			}
+			else {
+				let bits = value.chars().map(ScalarValue::try_from).collect::<Result<Vec<_>, _>>().ok()?;
+				Value::BinaryVector{width: bits.len(), value: bits}
+			};
+			Some((vcd::IdCode::from_str(id).ok()?, value))
+		},
+		c => {
+			let value = ScalarValue::try_from(c).ok()?;
+			Some((vcd::IdCode::from_str(chars.as_str()).ok()?, Value::Scalar(value)))
+		}
+	}
+}
+
+impl ValueChange {
+	pub fn parse(s: &str, options: &ParseOptions) -> Result<ValueChange, ParseValueChangeError> {
+		ValueChange::parse_located(s, options).map_err(|e| e.error)
+	}
+
+	/// Like `parse`, but also reports the column at which parsing failed.
+	pub fn parse_located(s: &str, options: &ParseOptions) -> Result<ValueChange, ParseError> {
+		ValueChange::parse_bytes(s, options).map_err(|e| e.in_chars(s))
+	}
+
+	/// Like `parse_located`, with the column of the error as a byte offset.
+	fn parse_bytes(s: &str, options: &ParseOptions) -> Result<ValueChange, ParseError> {
 		use regex::Regex;
 
+		// the patterns the regexes of the field orders are built from
+		const TIMESTAMP_NAME: &str = r#"^#(\d+(?:\.\d+)?(?:[munpfµμ]?s)?)\s([a-zA-Z0-9./:]+(?:\[\d+\])?)"#;
+		// the values that can't be mistaken for a width, when a line may not have one
+		const VALUE_NO_WIDTH: &str = r#"-?\d+(?:\.\d+)?|[bB]?[01xXzZ-]+|(?i:true|false)|[+-]?(?i:inf|infinity|nan)|'[^']+'|'[bBoOdDhH][0-9a-fA-FxXzZ_]+|0[xX][0-9a-fA-FxXzZ_]+|\[[^\s\]]+\]|\([^\s)]+\)"#;
+		const VALUE_TYPE: &str = r#"\d+:[a-z0-9]+|\d+[a-zA-Z]?|[a-zA-Z]+"#;
+
 		lazy_static! {
-			static ref RE: Regex = Regex::new(r#"#(\d+)\s([a-zA-Z0-9.]+)\s([01xXzZ]+|\d+\.\d+)\s(\d+|f)"#).unwrap();
+			static ref VALUE: String = format!(r#"{}|[0-9a-fA-FxXzZ_]+|(?:{})[01xXzZ]"#, VALUE_NO_WIDTH, STRENGTHS.join("|"));
+			static ref RE: Regex = Regex::new(&format!(r#"{}\s({})\s({})$"#, TIMESTAMP_NAME, *VALUE, VALUE_TYPE)).unwrap();
+			static ref RE_WIDTH_FIRST: Regex = Regex::new(&format!(r#"{}\s({})\s({})$"#, TIMESTAMP_NAME, VALUE_TYPE, *VALUE)).unwrap();
+			static ref RE_NO_WIDTH: Regex = Regex::new(&format!(r#"{}\s({})$"#, TIMESTAMP_NAME, VALUE_NO_WIDTH)).unwrap();
+		}
+
+		match options.input_format {
+			InputFormat::Kv => return ValueChange::parse_kv(s, options),
+			InputFormat::JsonLines => {
+				// a JSON line is a single value change only if it has a single field
+				let mut changes = ValueChange::parse_json_bytes(s, options)?;
+				if changes.len() != 1 {
+					return Err(ParseError { error: ParseValueChangeError::InvalidFormat, column: 1 });
+				}
+				return Ok(changes.remove(0));
+			},
+			InputFormat::Leveled => {
+				let rest = split_level(s).ok_or(ParseError { error: ParseValueChangeError::InvalidFormat, column: 1 })?.1;
+				let options = ParseOptions { input_format: InputFormat::Log, ..options.clone() };
+				// the columns are of the whole line
+				return ValueChange::parse_bytes(rest, &options).map_err(|e| ParseError { column: e.column + s.len() - rest.len(), ..e });
+			},
+			InputFormat::Log | InputFormat::VcdLike if options.columns.is_some() => return ValueChange::parse_columns(s, options),
+			InputFormat::Log | InputFormat::VcdLike => {}
 		}
 
+		let indent = s.len() - s.trim_start().len();
 		let s = s.trim();
-		let caps = RE.captures(s).ok_or(ParseValueChangeError::InvalidFormat)?;
+		let (caps, value_group, value_type_group) = match options.field_order {
+			FieldOrder::ValueWidth => (RE.captures(s), 3, 4),
+			FieldOrder::WidthValue => (RE_WIDTH_FIRST.captures(s), 4, 3)
+		};
+		let format_error = ParseError { error: ParseValueChangeError::InvalidFormat, column: indent + 1 };
+		let (caps, assumed_width) = match (caps, options.assume_width) {
+			(Some(caps), _) => (caps, None),
+			// a line without a width is only valid when there's one to assume
+			(None, Some(width)) => (RE_NO_WIDTH.captures(s).ok_or(format_error)?, Some(width.to_string())),
+			(None, None) => return Err(format_error)
+		};
+		let field = |group: usize| {
+			let m = caps.get(group).unwrap();
+			(m.as_str(), indent + m.start() + 1)
+		};
+		match assumed_width {
+			Some(ref width) => ValueChange::from_fields(field(1), field(2), field(3), (width, indent + s.len() + 1), options),
+			None => ValueChange::from_fields(field(1), field(2), field(value_group), field(value_type_group), options)
+		}
+	}
 
-		let timestamp_str = caps.get(1).unwrap().as_str();
-		let name_str = caps.get(2).unwrap().as_str();
-		let value_str = caps.get(3).unwrap().as_str();
-		let value_type_str = caps.get(4).unwrap().as_str();
+	/// Parses a line like `parse_located` whose timestamp may be a `#start-#end` range, a run of
+	/// the value from the start to the end. Returns the change at the start, and the end of the
+	/// range if there is one.
+	pub fn parse_range(s: &str, options: &ParseOptions) -> Result<(ValueChange, Option<u64>), ParseError> {
+		ValueChange::parse_range_bytes(s, options).map_err(|e| e.in_chars(s))
+	}
 
-		// try to parse timestamp and value from captured groups
-		let timestamp = u64::from_str(timestamp_str).map_err(|_| ParseValueChangeError::ParseTimestampErr)?;
-		let value = if value_type_str == "f" {
-			let real = f64::from_str(value_str).map_err(|_| ParseValueChangeError::InvalidValue)?;
-			Value::Real(real)
+	fn parse_range_bytes(s: &str, options: &ParseOptions) -> Result<(ValueChange, Option<u64>), ParseError> {
+		let first = s.trim_start();
+		let indent = s.len() - first.len();
+		let token = first.split(char::is_whitespace).next().unwrap_or("");
+		let (start, end) = match token.split_once("-#") {
+			Some(range) if token.starts_with('#') => range,
+			_ => return ValueChange::parse_bytes(s, options).map(|change| (change, None))
+		};
+		let end_error = ParseError { error: ParseValueChangeError::ParseTimestampErr, column: indent + start.len() + 3 };
+		let end = parse_timestamp(end, options.timestamp_scale, options.inline_units, options.snap_mode).ok_or(end_error.clone())?;
+
+		// the end is cut from the line, the columns after it move back by its length
+		let cut = token.len() - start.len();
+		let line = format!("{}{}{}", &s[..indent], start, &first[token.len()..]);
+		let change = ValueChange::parse_bytes(&line, options).map_err(|e| match e.column > indent + start.len() {
+			true => ParseError { column: e.column + cut, ..e },
+			false => e
+		})?;
+		if end < change.timestamp {
+			return Err(end_error);
 		}
-		else {
-			// try to parse value_type_str as an integer
-			match usize::from_str(value_type_str) {
-				Ok(1) => {
-					let value = ScalarValue::from_str(value_str).map_err(|_| ParseValueChangeError::InvalidValue)?;
-					Value::Scalar(value)
-				},
-				Ok(width) => {
-					let mut vec = Vec::with_capacity(s.len());
-					for c in value_str.chars() {
-						match c {
-							'0' => vec.push(ScalarValue::V0),
-							'1' => vec.push(ScalarValue::V1),
-							'x' | 'X' => vec.push(ScalarValue::X),
-							'z' | 'Z' => vec.push(ScalarValue::Z),
-							_ => return Err(ParseValueChangeError::InvalidValue)
-						};
-					}
-					if vec.len() > width {
-						return Err(ParseValueChangeError::ValueTooLargeForVecWidth);
-					}
-					Value::BinaryVector{width, value: vec}
-				},
-				Err(_e) => {
-					return Err(ParseValueChangeError::InvalidValueType)
-				}
+		Ok((change, Some(end)))
+	}
+
+	/// Parses a line of whitespace separated fields, picking the ones of `options.columns`. The
+	/// timestamp may start with a `#`.
+	fn parse_columns(s: &str, options: &ParseOptions) -> Result<ValueChange, ParseError> {
+		let columns = options.columns.expect("parsing columns without any");
+		let fields: Vec<(&str, usize)> = s.split_whitespace()
+			.map(|field| (field, field.as_ptr() as usize - s.as_ptr() as usize + 1))
+			.collect();
+		let field = |i: usize| fields.get(i).cloned().ok_or(ParseError { error: ParseValueChangeError::InvalidFormat, column: s.len() + 1 });
+		let (timestamp, column) = field(columns.timestamp)?;
+		let timestamp = match timestamp.strip_prefix('#') {
+			Some(timestamp) => (timestamp, column + 1),
+			None => (timestamp, column)
+		};
+		ValueChange::from_fields(timestamp, field(columns.name)?, field(columns.value)?, field(columns.value_type)?, options)
+	}
+
+	/// Parses a `t=timestamp sig=signal_name val=value w=< size | f >` line, the fields may be in any order.
+	fn parse_kv(s: &str, options: &ParseOptions) -> Result<ValueChange, ParseError> {
+		let mut fields: [Option<(&str, usize)>; 4] = [None; 4];
+		for token in s.split_whitespace() {
+			let column = token.as_ptr() as usize - s.as_ptr() as usize + 1;
+			let (key, value) = match token.find('=') {
+				Some(i) => (&token[..i], (&token[i + 1..], column + i + 1)),
+				None => return Err(ParseError { error: ParseValueChangeError::InvalidFormat, column })
+			};
+			// other keys are extra information in the log
+			if let Some(i) = ["t", "sig", "val", "w"].iter().position(|k| *k == key) {
+				fields[i] = Some(value);
 			}
+		}
+		match fields {
+			[Some(timestamp), Some(name), Some(value), Some(value_type)] => {
+				ValueChange::from_fields(timestamp, name, value, value_type, options)
+			},
+			// a missing field is at the end of the line, like a missing column
+			_ => Err(ParseError { error: ParseValueChangeError::InvalidFormat, column: s.len() + 1 })
+		}
+	}
+
+	/// Parses a JSON object into a value change of every field other than the timestamp `t`.
+	///
+	/// Booleans are scalars, integers vectors of the fewest bits that hold them, other numbers reals
+	/// and strings are read as the bits of a vector of their length. The fields of nested objects
+	/// are named `object.field`.
+	pub fn parse_json(s: &str, options: &ParseOptions) -> Result<Vec<ValueChange>, ParseError> {
+		ValueChange::parse_json_bytes(s, options).map_err(|e| e.in_chars(s))
+	}
+
+	/// Like `parse_json`, with the column of the error as a byte offset.
+	fn parse_json_bytes(s: &str, options: &ParseOptions) -> Result<Vec<ValueChange>, ParseError> {
+		let format_error = ParseError { error: ParseValueChangeError::InvalidFormat, column: 1 };
+		let mut object = match serde_json::from_str(s) {
+			Ok(serde_json::Value::Object(object)) => object,
+			_ => return Err(format_error)
+		};
+		let timestamp_error = ParseError { error: ParseValueChangeError::ParseTimestampErr, column: json_columns(s, 0, "t").1 };
+		let timestamp = match object.remove("t") {
+			Some(serde_json::Value::Number(t)) => parse_timestamp(&t.to_string(), options.timestamp_scale, None, options.snap_mode).ok_or(timestamp_error)?,
+			Some(serde_json::Value::String(t)) => parse_timestamp(&t, options.timestamp_scale, options.inline_units, options.snap_mode).ok_or(timestamp_error)?,
+			_ => return Err(format_error)
 		};
+		let mut changes = Vec::new();
+		json_fields(timestamp, "", object, (s, 0), options, &mut changes)?;
+		Ok(changes)
+	}
+
+	/// Builds a value change from its fields, along with the 1-based columns they start at.
+	fn from_fields(timestamp: (&str, usize), name: (&str, usize), value: (&str, usize), value_type: (&str, usize), options: &ParseOptions) -> Result<ValueChange, ParseError> {
+		let (name_str, name_column) = name;
+		if !options.valid_name(name_str) {
+			return Err(ParseError { error: ParseValueChangeError::InvalidSignalName, column: name_column });
+		}
+
+		// try to parse timestamp and value from captured groups
+		let timestamp_error = ParseError { error: ParseValueChangeError::ParseTimestampErr, column: timestamp.1 };
+		let timestamp = parse_timestamp(timestamp.0, options.timestamp_scale, options.inline_units, options.snap_mode).ok_or(timestamp_error)?;
+		let value_str = value.0;
+		let value = Value::parse(value.0, value_type.0).map_err(|error| {
+			let column = match error {
+				ParseValueChangeError::InvalidValueType | ParseValueChangeError::UnknownMarker => value_type.1,
+				_ => value.1
+			};
+			ParseError { error, column }
+		})?;
 
 		Ok(ValueChange {
 			timestamp,
 			signal_name: String::from(name_str),
-			value
+			value,
+			line: None,
+			scope: None,
+			strength: split_strength(value_str).map(|(strength, _)| strength)
 		})
 	}
 }
 
+/// Writes the value the way it is logged, without its value type.
+impl fmt::Display for Value {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Value::Scalar(v) => write!(f, "{}", v.to_char()),
+			Value::BinaryVector{value, ..} => value.iter().try_for_each(|v| write!(f, "{}", v.to_char())),
+			Value::Real(v) => write!(f, "{}", v)
+		}
+	}
+}
+
+/// Writes the value change as a log line, `#timestamp signal_name value < size | f >`.
+impl fmt::Display for ValueChange {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "#{} {} {} ", self.timestamp, self.signal_name, self.value)?;
+		match self.value {
+			Value::Scalar(_) => write!(f, "1"),
+			Value::BinaryVector{width, ..} => write!(f, "{}", width),
+			Value::Real(_) => write!(f, "f")
+		}
+	}
+}
+
+impl FromStr for ValueChange {
+	type Err = ParseValueChangeError;
+
+	fn from_str(s: &str) -> Result<ValueChange,ParseValueChangeError> {
+		ValueChange::parse(s, &ParseOptions::default())
+	}
+}
+
 
 #[cfg(test)]
 mod test {
 	use super::*;
 
+	#[test]
+	fn scalar_char_round_trip() {
+		use super::ScalarValue::*;
+		for value in &[V0, V1, X, Z] {
+			assert_eq!(Ok(value.clone()), ScalarValue::try_from(value.to_char()));
+		}
+		for c in &['0', '1', 'x', 'z'] {
+			assert_eq!(*c, ScalarValue::try_from(*c).unwrap().to_char());
+		}
+		assert_eq!(Ok(X), ScalarValue::try_from('X'));
+		assert_eq!(Ok(Z), ScalarValue::try_from('Z'));
+		assert_eq!(Err(()), ScalarValue::try_from('2'));
+		assert_eq!(Err(()), ScalarValue::from_str("01"));
+		assert_eq!(Err(()), ScalarValue::from_str(""));
+	}
+
 	#[test]
 	fn scalar() {
 		let s = "#100 imasignal 1 1";
@@ -140,11 +1098,24 @@ mod test {
 		let expected = ValueChange {
 			timestamp: 100,
 			signal_name: String::from("imasignal"),
-			value: Value::Scalar(ScalarValue::V1)
+			value: Value::Scalar(ScalarValue::V1),
+			line: None,
+			scope: None,
+			strength: None
 		};
 		assert_eq!(expected, result);
 	}
 
+	#[test]
+	fn single_bit_spellings() {
+		for (value, expected) in &[("1", ScalarValue::V1), ("b1", ScalarValue::V1), ("B0", ScalarValue::V0), ("'b1", ScalarValue::V1), ("'h0", ScalarValue::V0), ("true", ScalarValue::V1), ("-", ScalarValue::X), ("x", ScalarValue::X)] {
+			assert_eq!(Value::Scalar(expected.clone()), ValueChange::from_str(&format!("#10 a {} 1", value)).unwrap().value, "{}", value);
+		}
+		assert_eq!(Value::Scalar(ScalarValue::V1), ValueChange::from_str("#10 a -1 1s").unwrap().value);
+		assert_eq!(Err(ParseValueChangeError::MultiBitScalar), ValueChange::from_str("#10 a b10 1"));
+		assert_eq!(Value::parse("1010", "4").unwrap(), ValueChange::from_str("#10 a b1010 4").unwrap().value);
+	}
+
 	#[test]
 	fn scalar2() {
 		let s = "#1283075 AFC003.Outputs.D1 1 1";
@@ -152,11 +1123,39 @@ mod test {
 		let expected = ValueChange {
 			timestamp: 1283075,
 			signal_name: String::from("AFC003.Outputs.D1"),
-			value: Value::Scalar(ScalarValue::V1)
+			value: Value::Scalar(ScalarValue::V1),
+			line: None,
+			scope: None,
+			strength: None
 		};
 		assert_eq!(expected, result);
 	}
 
+	#[test]
+	fn separators() {
+		let slash = ParseOptions { scope_separator: Some(String::from("/")), ..ParseOptions::default() };
+		assert_eq!("cpu/alu/zero", ValueChange::parse("#10 cpu/alu/zero 1 1", &slash).unwrap().signal_name);
+		assert_eq!(Err(ParseValueChangeError::InvalidSignalName), ValueChange::parse("#10 core::busy 1 1", &slash));
+		let colons = ParseOptions { scope_separator: Some(String::from("::")), ..ParseOptions::default() };
+		assert_eq!("core::busy", ValueChange::parse("#10 core::busy 1 1", &colons).unwrap().signal_name);
+		assert_eq!(Err(ParseValueChangeError::InvalidSignalName), ValueChange::parse("#10 core:busy 1 1", &colons));
+		assert_eq!(Err(ParseValueChangeError::InvalidSignalName), ValueChange::from_str("#10 cpu/alu/zero 1 1"));
+
+		let nest = ParseOptions { nest: true, allow_numeric_names: true, ..slash.clone() };
+		for name in &["/a", "a/", "a//b"] {
+			assert_eq!(Err(ParseValueChangeError::InvalidSignalName), ValueChange::parse(&format!("#10 {} 1 1", name), &nest), "{}", name);
+			assert!(ValueChange::parse(&format!("#10 {} 1 1", name), &ParseOptions { nest: false, ..nest.clone() }).is_ok());
+		}
+		let no_nest = ParseOptions { no_nest_patterns: vec![regex::Regex::new("^a/$").unwrap()], ..nest };
+		assert!(ValueChange::parse("#10 a/ 1 1", &no_nest).is_ok());
+	}
+
+	#[test]
+	fn array_element() {
+		let result = ValueChange::from_str("#100 mem[5] 1010 4").unwrap();
+		assert_eq!("mem[5]", result.signal_name);
+	}
+
 	#[test]
 	#[should_panic]
 	fn invalid_scalar() {
@@ -164,6 +1163,14 @@ mod test {
 		let _ = ValueChange::from_str(s).unwrap();
 	}
 
+	#[test]
+	fn multi_bit_scalar() {
+		let s = "#100 imasignal 01 1";
+		assert_eq!(Err(ParseValueChangeError::MultiBitScalar), ValueChange::from_str(s));
+		let s = "#100 imasignal 1.5 1";
+		assert_eq!(Err(ParseValueChangeError::InvalidValue), ValueChange::from_str(s));
+	}
+
 	#[test]
 	fn vec8() {
 		use super::ScalarValue::*;
@@ -186,10 +1193,432 @@ mod test {
 		assert_eq!(Value::Real(123.4f64), result.value);
 	}
 
+	#[test]
+	fn same() {
+		let value = |bits: &str, width: &str| Value::parse(bits, width).unwrap();
+		assert!(value("10", "4").same(&value("0010", "4")));
+		assert!(value("x1", "4").same(&value("xxx1", "4")));
+		assert!(!value("10", "4").same(&value("0011", "4")));
+		assert!(!value("10", "4").same(&value("10", "8")));
+		assert!(value("1", "1").same(&value("1", "1")));
+		assert!(!value("1.5", "f").same(&value("2.5", "f")));
+	}
+
+	#[test]
+	fn canonical_cmp() {
+		let value = |bits: &str, width: &str| Value::parse(bits, width).unwrap();
+		assert_eq!(Ordering::Less, value("0", "1").canonical_cmp(&value("1", "1")));
+		assert_eq!(Ordering::Less, value("1", "1").canonical_cmp(&value("x", "1")));
+		assert_eq!(Ordering::Greater, value("0011", "4").canonical_cmp(&value("0010", "4")));
+		assert_eq!(Ordering::Less, value("1111", "4").canonical_cmp(&value("0000", "8")));
+		assert_eq!(Ordering::Less, value("1", "1").canonical_cmp(&value("0000", "4")));
+		assert_eq!(Ordering::Less, value("-1.5", "f").canonical_cmp(&value("1.5", "f")));
+		assert_eq!(Ordering::Equal, value("NaN", "f").canonical_cmp(&value("NaN", "f")));
+	}
+
+	#[test]
+	fn left_extend() {
+		use super::ScalarValue::*;
+		assert_eq!(vec![V0, V0, V1, V0], super::left_extend(&[V1, V0], 4));
+		assert_eq!(vec![X, X, X, V1], super::left_extend(&[X, V1], 4));
+		assert_eq!(vec![Z, Z, V0], super::left_extend(&[Z, V0], 3));
+		assert_eq!(vec![V0, V0], super::left_extend(&[], 2));
+		assert_eq!(vec![V1, V1], super::left_extend(&[V1, V1], 1));
+	}
+
+	#[test]
+	fn fixed_point() {
+		use super::ScalarValue::*;
+		assert_eq!(Value::BinaryVector{width: 32, value: vec![V1, V1, V0, V0, V0]}, Value::fixed_point(1.5, 4, 32));
+		assert_eq!(Value::BinaryVector{width: 8, value: vec![V1, V1, V1, V0, V1, V0, V0, V0]}, Value::fixed_point(-1.5, 4, 8));
+		// saturates instead of wrapping around
+		assert_eq!(Value::BinaryVector{width: 4, value: vec![V1, V1, V1]}, Value::fixed_point(100.0, 4, 4));
+	}
+
 	#[test]
 	#[should_panic]
 	fn value_value_type_mismatch() {
 		let s = "#222 signame 123.4 8";
 		let _ = ValueChange::from_str(s).unwrap();
 	}
+
+	#[test]
+	fn width_value_order() {
+		let options = ParseOptions { field_order: FieldOrder::WidthValue, ..ParseOptions::default() };
+		let swapped = ValueChange::parse("#100 signame 8 11110010", &options).unwrap();
+		assert_eq!(ValueChange::from_str("#100 signame 11110010 8").unwrap(), swapped);
+		let swapped = ValueChange::parse("#222 signame f 123.4", &options).unwrap();
+		assert_eq!(ValueChange::from_str("#222 signame 123.4 f").unwrap(), swapped);
+		let swapped = ValueChange::parse("#1 a 1 0", &options).unwrap();
+		assert_eq!(Value::Scalar(ScalarValue::V0), swapped.value);
+		assert!(ValueChange::parse("#100 signame 11110010 8", &options).is_err());
+	}
+
+	#[test]
+	fn error_column() {
+		let options = ParseOptions::default();
+		let error = ValueChange::parse_located("  #100 sig 01 1", &options).unwrap_err();
+		assert_eq!(ParseError { error: ParseValueChangeError::MultiBitScalar, column: 12 }, error);
+		let error = ValueChange::parse_located("#99999999999999999999 sig 1 1", &options).unwrap_err();
+		assert_eq!(ParseError { error: ParseValueChangeError::ParseTimestampErr, column: 2 }, error);
+		let error = ValueChange::parse_located(" garbage", &options).unwrap_err();
+		assert_eq!(ParseError { error: ParseValueChangeError::InvalidFormat, column: 2 }, error);
+	}
+
+	#[test]
+	fn scaled_timestamp() {
+		let options = ParseOptions { timestamp_scale: Some(1e9), ..ParseOptions::default() };
+		assert_eq!(12_500_000_000, ValueChange::parse("#12.5 a 1 1", &options).unwrap().timestamp);
+		assert_eq!(3_000_000_000, ValueChange::parse("#3 a 1 1", &options).unwrap().timestamp);
+		// rounded to the nearest tick
+		assert_eq!(2, ValueChange::parse("#0.0000000015 a 1 1", &options).unwrap().timestamp);
+		assert_eq!(1, ValueChange::parse("#0.0000000012 a 1 1", &options).unwrap().timestamp);
+
+		let result = ValueChange::from_str("#12.5 a 1 1");
+		assert_eq!(Err(ParseValueChangeError::ParseTimestampErr), result);
+	}
+
+	#[test]
+	fn snap_modes() {
+		for (mode, expected) in [(SnapMode::Nearest, [12, 13, 3]), (SnapMode::Floor, [12, 12, 3]), (SnapMode::Ceil, [13, 13, 3])].iter() {
+			let options = ParseOptions { timestamp_scale: Some(10.0), snap_mode: *mode, ..ParseOptions::default() };
+			let ticks: Vec<u64> = ["#1.24 a 1 1", "#1.26 a 1 1", "#0.3 a 1 1"].iter()
+				.map(|line| ValueChange::parse(line, &options).unwrap().timestamp)
+				.collect();
+			assert_eq!(&expected[..], &ticks[..], "{:?}", mode);
+		}
+	}
+
+	#[test]
+	fn comment() {
+		let options = ParseOptions::default();
+		let comment = parse_comment("#100 $comment reset handler entered", &options);
+		assert_eq!(Some((100, String::from("reset handler entered"))), comment);
+		assert_eq!(None, parse_comment("#100 sig 1 1", &options));
+		assert_eq!(None, parse_comment("#100 $comment", &options));
+	}
+
+	#[test]
+	fn integer_real() {
+		assert_eq!(Value::Real(5.0), ValueChange::from_str("#1 a 5 f").unwrap().value);
+		assert_eq!(Value::Real(0.0), ValueChange::from_str("#1 a 0 f").unwrap().value);
+		assert_eq!(Value::Real(-7.0), ValueChange::from_str("#1 a -7 f").unwrap().value);
+		let options = ParseOptions { field_order: FieldOrder::WidthValue, ..ParseOptions::default() };
+		assert_eq!(Value::Real(-7.0), ValueChange::parse("#1 a f -7", &options).unwrap().value);
+	}
+
+	#[test]
+	fn unknown() {
+		assert!(Value::parse("x", "1").unwrap().is_unknown());
+		assert!(Value::parse("xxxx", "4").unwrap().is_unknown());
+		assert!(Value::parse("xzzx", "4").unwrap().is_unknown());
+		assert!(Value::parse("z", "1").unwrap().is_unknown());
+		assert!(!Value::parse("x1xx", "4").unwrap().is_unknown());
+		assert!(!Value::parse("0", "1").unwrap().is_unknown());
+		assert!(!Value::parse("1.5", "f").unwrap().is_unknown());
+	}
+
+	#[test]
+	fn assumed_width() {
+		assert_eq!(Err(ParseValueChangeError::InvalidFormat), ValueChange::from_str("#100 sig 1"));
+		let options = ParseOptions { assume_width: Some(1), ..ParseOptions::default() };
+		assert_eq!(Value::Scalar(ScalarValue::V1), ValueChange::parse("#100 sig 1", &options).unwrap().value);
+		assert_eq!(Value::Scalar(ScalarValue::X), ValueChange::parse("  #100 sig x", &options).unwrap().value);
+		// lines with a width keep it
+		assert_eq!(Value::BinaryVector{width: 4, value: vec![ScalarValue::V1, ScalarValue::V0]}, ValueChange::parse("#100 sig 10 4", &options).unwrap().value);
+		assert_eq!(Err(ParseValueChangeError::MultiBitScalar), ValueChange::parse("#100 sig 10", &options));
+		let options = ParseOptions { assume_width: Some(8), ..ParseOptions::default() };
+		assert_eq!(Value::BinaryVector{width: 8, value: vec![ScalarValue::V1, ScalarValue::V0]}, ValueChange::parse("#100 sig 10", &options).unwrap().value);
+	}
+
+	#[test]
+	fn columns() {
+		let options = ParseOptions { columns: Some(Columns::from_str("1,3,4,5").unwrap()), ..ParseOptions::default() };
+		let change = ValueChange::parse("100 host1 bus 1010 4 extra", &options).unwrap();
+		assert_eq!(100, change.timestamp);
+		assert_eq!("bus", change.signal_name);
+		assert_eq!(Value::parse("1010", "4").unwrap(), change.value);
+		assert_eq!(30, ValueChange::parse("#30 host1 a 1 1 extra", &options).unwrap().timestamp);
+		let error = ValueChange::parse_located("100 host1 bus 10102 4 extra", &options).unwrap_err();
+		assert_eq!(ParseError { error: ParseValueChangeError::InvalidValue, column: 15 }, error);
+		assert_eq!(Err(ParseValueChangeError::InvalidFormat), ValueChange::parse("100 host1 bus", &options));
+
+		let options = ParseOptions { columns: Some(Columns::from_str("6, 2, 4, 3").unwrap()), ..ParseOptions::default() };
+		assert_eq!(20, ValueChange::parse("x sig 1 0 y 20", &options).unwrap().timestamp);
+		assert!(Columns::from_str("1,3,4").is_err());
+		assert!(Columns::from_str("1,3,3,5").is_err());
+		assert!(Columns::from_str("0,1,2,3").is_err());
+	}
+
+	#[test]
+	fn numeric_name() {
+		assert_eq!(Err(ParseValueChangeError::InvalidSignalName), ValueChange::from_str("#10 3 1 1"));
+		let options = ParseOptions { allow_numeric_names: true, ..ParseOptions::default() };
+		let change = ValueChange::parse("#10 3 1 1", &options).unwrap();
+		assert_eq!("3", change.signal_name);
+	}
+
+	#[test]
+	fn boolean_scalar() {
+		assert_eq!(Value::Scalar(ScalarValue::V1), ValueChange::from_str("#10 a true 1").unwrap().value);
+		assert_eq!(Value::Scalar(ScalarValue::V0), ValueChange::from_str("#10 a false 1").unwrap().value);
+		assert_eq!(Value::Scalar(ScalarValue::V1), ValueChange::from_str("#10 a TRUE 1").unwrap().value);
+		assert_eq!(Err(ParseValueChangeError::InvalidValue), ValueChange::from_str("#10 a true 8"));
+	}
+
+	#[test]
+	fn inline_units() {
+		assert_eq!(Err(ParseValueChangeError::ParseTimestampErr), ValueChange::from_str("#100ns a 1 1"));
+		let options = ParseOptions { inline_units: Some((10, vcd::TimescaleUnit::NS)), ..ParseOptions::default() };
+		assert_eq!(10, ValueChange::parse("#100ns a 1 1", &options).unwrap().timestamp);
+		assert_eq!(100, ValueChange::parse("#1us a 1 1", &options).unwrap().timestamp);
+		assert_eq!(100, ValueChange::parse("#1µs a 1 1", &options).unwrap().timestamp);
+		assert_eq!(200, ValueChange::parse("#2μs a 1 1", &options).unwrap().timestamp);
+		assert_eq!(1, ValueChange::parse("#10000ps a 1 1", &options).unwrap().timestamp);
+		assert_eq!(7, ValueChange::parse("#7 a 1 1", &options).unwrap().timestamp);
+	}
+
+	#[test]
+	fn dont_care() {
+		let result = ValueChange::from_str("#10 a 1-0x 4").unwrap();
+		assert_eq!(Value::parse("1x0x", "4").unwrap(), result.value);
+		assert_eq!(Value::Real(-7.0), ValueChange::from_str("#10 a -7 f").unwrap().value);
+	}
+
+	#[test]
+	fn kv() {
+		let options = ParseOptions { input_format: InputFormat::Kv, ..ParseOptions::default() };
+		let expected = ValueChange::from_str("#100 foo 1010 4").unwrap();
+		assert_eq!(expected, ValueChange::parse("t=100 sig=foo val=1010 w=4", &options).unwrap());
+		assert_eq!(expected, ValueChange::parse("w=4 val=1010 sig=foo t=100", &options).unwrap());
+		assert_eq!(expected, ValueChange::parse("  sig=foo level=info t=100 w=4 val=1010", &options).unwrap());
+
+		let error = ValueChange::parse_located("t=100 sig=foo val=1010 w=2", &options).unwrap_err();
+		assert_eq!(ParseError { error: ParseValueChangeError::ValueTooLargeForVecWidth, column: 19 }, error);
+		assert_eq!(Err(ParseValueChangeError::InvalidFormat), ValueChange::parse("t=100 sig=foo val=1", &options));
+		assert_eq!(Err(ParseValueChangeError::InvalidFormat), ValueChange::parse("t=100 foo 1 1", &options));
+		assert_eq!(ParseError { error: ParseValueChangeError::InvalidFormat, column: 7 }, ValueChange::parse_located("t=100 foo 1 1", &options).unwrap_err());
+		assert_eq!(ParseError { error: ParseValueChangeError::InvalidFormat, column: 20 }, ValueChange::parse_located("t=100 sig=foo val=1", &options).unwrap_err());
+	}
+
+	#[test]
+	fn display_round_trip() {
+		for line in &["#100 sig 1 1", "#100 sig z 1", "#5 bus 10x0 8", "#7 r 1.25 f", "#7 r -3 f"] {
+			let change = ValueChange::from_str(line).unwrap();
+			assert_eq!(*line, change.to_string());
+			assert_eq!(change, ValueChange::from_str(&change.to_string()).unwrap());
+		}
+		assert_eq!("10x0", ValueChange::from_str("#5 bus 10x0 8").unwrap().value.to_string());
+	}
+
+	#[test]
+	fn signed() {
+		assert_eq!(Value::parse("11111011", "8").unwrap(), ValueChange::from_str("#10 a -5 8s").unwrap().value);
+		assert_eq!(Value::parse("1111111", "8").unwrap(), ValueChange::from_str("#10 a 127 8s").unwrap().value);
+		assert_eq!(Value::parse("10000000", "8").unwrap(), ValueChange::from_str("#10 a -128 8s").unwrap().value);
+		assert_eq!(Err(ParseValueChangeError::ValueTooLargeForVecWidth), ValueChange::from_str("#10 a 128 8s"));
+		assert_eq!(Err(ParseValueChangeError::ValueTooLargeForVecWidth), ValueChange::from_str("#10 a -129 8s"));
+		assert_eq!(Err(ParseValueChangeError::InvalidValue), ValueChange::from_str("#10 a 1x 8s"));
+		// wider than 64 bits, the sign is extended to the width
+		assert_eq!(Value::parse(&format!("{}011", "1".repeat(97)), "100").unwrap(), ValueChange::from_str("#10 a -5 100s").unwrap().value);
+		assert_eq!(Value::parse("101", "100").unwrap(), ValueChange::from_str("#10 a 5 100s").unwrap().value);
+		assert_eq!(Value::parse(&"1".repeat(200), "200").unwrap(), ValueChange::from_str("#10 a -1 200s").unwrap().value);
+	}
+
+	#[test]
+	fn vcd_change() {
+		assert_eq!(Some((vcd::IdCode::FIRST, Value::Scalar(ScalarValue::V1))), parse_vcd_change("1!"));
+		assert_eq!(Some((vcd::IdCode::FIRST.next(), Value::parse("10x", "3").unwrap())), parse_vcd_change("b10x \""));
+		assert_eq!(Some((vcd::IdCode::FIRST, Value::Real(1.5))), parse_vcd_change("r1.5 !"));
+		assert_eq!(None, parse_vcd_change("#10 a 1 1"));
+		assert_eq!(None, parse_vcd_change("x y z"));
+		assert_eq!(None, parse_vcd_change("b12 !"));
+		assert_eq!(Some(25), parse_vcd_timestamp("#25"));
+		assert_eq!(None, parse_vcd_timestamp("#25 a 1 1"));
+	}
+
+	#[test]
+	fn json() {
+		let options = ParseOptions { input_format: InputFormat::JsonLines, ..ParseOptions::default() };
+		let changes = ValueChange::parse_json(r#"{"t": 100, "a": true, "bus": "10x", "n": -2, "r": 1.5, "core": {"en": false}}"#, &options).unwrap();
+		let expected: Vec<ValueChange> = vec![
+			"#100 a 1 1", "#100 bus 10x 3", "#100 core.en 0 1", "#100 n -2 2s", "#100 r 1.5 f"
+		].into_iter().map(|s| ValueChange::from_str(s).unwrap()).collect();
+		assert_eq!(expected, changes);
+		let values = ValueChange::parse_json(r#"{"t": 1, "a": 1, "b": 5, "c": -1, "d": -5, "e": 0}"#, &options).unwrap();
+		let values: Vec<String> = values.iter().map(|change| change.to_string()).collect();
+		assert_eq!(vec!["#1 a 1 1", "#1 b 101 3", "#1 c 1 1", "#1 d 1011 4", "#1 e 0 1"], values);
+		assert_eq!(ValueChange::from_str("#5 a 0 1").unwrap(), ValueChange::parse_located(r#"{"a": false, "t": 5}"#, &options).unwrap());
+		assert_eq!(ParseValueChangeError::InvalidFormat, ValueChange::parse_json(r#"{"a": true}"#, &options).unwrap_err().error);
+		assert_eq!(ParseValueChangeError::InvalidFormat, ValueChange::parse_json("#5 a 0 1", &options).unwrap_err().error);
+		assert_eq!(ParseValueChangeError::InvalidValue, ValueChange::parse_json(r#"{"t": 1, "a": null}"#, &options).unwrap_err().error);
+		let error = |line| ValueChange::parse_json(line, &options).unwrap_err();
+		assert_eq!(ParseError { error: ParseValueChangeError::InvalidValue, column: 15 }, error(r#"{"t": 1, "a": "12"}"#));
+		assert_eq!(ParseError { error: ParseValueChangeError::InvalidValue, column: 33 }, error(r#"{"t": 1, "core": {"a": 1, "en": "2"}}"#));
+		assert_eq!(ParseError { error: ParseValueChangeError::InvalidSignalName, column: 10 }, error(r#"{"t": 1, "0a": 1}"#));
+		assert_eq!(ParseError { error: ParseValueChangeError::ParseTimestampErr, column: 15 }, error(r#"{"é": 1, "t": "1.5.2"}"#));
+	}
+
+	#[test]
+	fn markers() {
+		assert_eq!(Ok(ValueType::Width(8)), ValueType::from_marker("8"));
+		assert_eq!(Ok(ValueType::Signed(8)), ValueType::from_marker("8s"));
+		assert_eq!(Ok(ValueType::Real), ValueType::from_marker("f"));
+		assert_eq!(Err(ParseValueChangeError::UnknownMarker), ValueType::from_marker("e"));
+		assert_eq!(Err(ParseValueChangeError::UnknownMarker), ValueType::from_marker("8h"));
+		assert_eq!(Err(ParseValueChangeError::InvalidValueType), ValueType::from_marker("8f"));
+		assert_eq!(Err(ParseValueChangeError::InvalidValueType), ValueType::from_marker("s"));
+		let error = ValueChange::parse_located("#10 a 1 e", &ParseOptions::default()).unwrap_err();
+		assert_eq!(ParseError { error: ParseValueChangeError::UnknownMarker, column: 9 }, error);
+	}
+
+	#[test]
+	fn quoted_char() {
+		assert_eq!(Value::parse("01000001", "8").unwrap(), ValueChange::from_str("#10 a 'A' 8").unwrap().value);
+		assert_eq!(Value::parse("00100000", "8").unwrap(), ValueChange::from_str("#10 a ' ' 8").unwrap().value);
+		assert_eq!(Err(ParseValueChangeError::ValueTooLargeForVecWidth), ValueChange::from_str("#10 a '€' 8"));
+		assert_eq!(Value::parse("0010000010101100", "16").unwrap(), ValueChange::from_str("#10 a '€' 16").unwrap().value);
+		assert_eq!(Err(ParseValueChangeError::InvalidValue), ValueChange::from_str("#10 a 'AB' 8"));
+		assert_eq!(Err(ParseValueChangeError::InvalidValue), ValueChange::from_str("#10 a 'A' f"));
+	}
+
+	#[test]
+	fn special_reals() {
+		assert_eq!(Value::Real(f64::INFINITY), ValueChange::from_str("#10 r inf f").unwrap().value);
+		assert_eq!(Value::Real(f64::NEG_INFINITY), ValueChange::from_str("#10 r -Infinity f").unwrap().value);
+		assert!(match ValueChange::from_str("#10 r NaN f").unwrap().value { Value::Real(r) => r.is_nan(), _ => false });
+		assert_eq!(Err(ParseValueChangeError::InvalidValue), ValueChange::from_str("#10 a inf 1"));
+	}
+
+	#[test]
+	fn huge_width() {
+		// found by fuzzing, the value was extended to the width before the width was checked
+		assert_eq!(Err(ParseValueChangeError::InvalidValueType), ValueChange::from_str("#0 W 0X00000000X0000000000000000066660 08000000000066660"));
+		assert_eq!(Err(ParseValueChangeError::InvalidValueType), ValueChange::from_str(&format!("#0 a 'h1 {}", MAX_WIDTH + 1)));
+		assert_eq!(MAX_WIDTH, ValueChange::from_str(&format!("#0 a 'h1 {}", MAX_WIDTH)).unwrap().value.to_string().len());
+	}
+
+	#[test]
+	fn brackets() {
+		let bare = ValueChange::from_str("#10 bus 11110010 8").unwrap();
+		assert_eq!(bare, ValueChange::from_str("#10 bus [11110010] 8").unwrap());
+		assert_eq!(bare, ValueChange::from_str("#10 bus (0xF2) 8").unwrap());
+		assert_eq!(bare, ValueChange::from_str("#10 bus 0xF2 8").unwrap());
+		assert_eq!(bare, ValueChange::from_str("#10 bus ('hF2) 8").unwrap());
+		assert_eq!(Value::Real(1.5), ValueChange::from_str("#10 r (1.5) f").unwrap().value);
+		// 0x is hex even when the digits are bits
+		assert_eq!(Value::parse("00010000", "8").unwrap(), ValueChange::from_str("#10 bus (0x10) 8").unwrap().value);
+		assert_eq!(Err(ParseValueChangeError::ValueTooLargeForVecWidth), ValueChange::from_str("#10 bus 0x10 4"));
+		// only one pair is stripped, and it has to match
+		assert_eq!(Err(ParseValueChangeError::InvalidFormat), ValueChange::from_str("#10 bus [1010) 8"));
+		assert!(ValueChange::from_str("#10 bus [[1010]] 8").is_err());
+		assert_eq!(Err(ParseValueChangeError::ValueTooLargeForVecWidth), ValueChange::from_str("#10 bus (0x1F2) 8"));
+	}
+
+	#[test]
+	fn radix_literal() {
+		assert_eq!(Value::parse("00011111", "8").unwrap(), ValueChange::from_str("#10 a 'h1F 8").unwrap().value);
+		assert_eq!(Value::parse("00001010", "8").unwrap(), ValueChange::from_str("#10 a 'b1010 8").unwrap().value);
+		assert_eq!(Value::parse("001111", "6").unwrap(), ValueChange::from_str("#10 a 'o17 6").unwrap().value);
+		assert_eq!(Value::parse("11111111", "8").unwrap(), ValueChange::from_str("#10 a 'd255 8").unwrap().value);
+		assert_eq!(Value::parse("0000xxxx", "8").unwrap(), ValueChange::from_str("#10 a 'h0x 8").unwrap().value);
+		assert_eq!(Value::parse("1", "1").unwrap(), ValueChange::from_str("#10 a 'h1 1").unwrap().value);
+		assert_eq!(Value::parse("1111000011110000", "16").unwrap(), ValueChange::from_str("#10 a 'HF0_F0 16").unwrap().value);
+		// leading zeros fit any width, other bits don't
+		assert_eq!(Value::parse("1111", "4").unwrap(), ValueChange::from_str("#10 a 'h0F 4").unwrap().value);
+		assert_eq!(Err(ParseValueChangeError::ValueTooLargeForVecWidth), ValueChange::from_str("#10 a 'h1FF 8"));
+		assert_eq!(Err(ParseValueChangeError::ValueTooLargeForVecWidth), ValueChange::from_str("#10 a 'd256 8"));
+		assert_eq!(Err(ParseValueChangeError::InvalidValue), ValueChange::from_str("#10 a 'b102 8"));
+		assert_eq!(Err(ParseValueChangeError::InvalidValue), ValueChange::from_str("#10 a 'hFF f"));
+	}
+
+	#[test]
+	fn timestamp_range() {
+		let options = ParseOptions::default();
+		let (change, end) = ValueChange::parse_range("#100-#200 sig 1 1", &options).unwrap();
+		assert_eq!(ValueChange::from_str("#100 sig 1 1").unwrap(), change);
+		assert_eq!(Some(200), end);
+		assert_eq!((ValueChange::from_str("#5 bus 1010 4").unwrap(), None), ValueChange::parse_range("#5 bus 1010 4", &options).unwrap());
+		assert_eq!(ParseError { error: ParseValueChangeError::ParseTimestampErr, column: 7 }, ValueChange::parse_range("#100-#50 sig 1 1", &options).unwrap_err());
+		// columns are those of the line with its range
+		assert_eq!(ParseError { error: ParseValueChangeError::MultiBitScalar, column: 15 }, ValueChange::parse_range("  #10-#20 sig 10 1", &options).unwrap_err());
+	}
+
+	#[test]
+	fn unknown_digits() {
+		assert_eq!(Value::parse("0001xxxx", "8").unwrap(), ValueChange::from_str("#10 a 0x1X 8").unwrap().value);
+		assert_eq!(Value::parse("zzzz0000", "8").unwrap(), Value::parse("0xz0", "8").unwrap());
+		assert_eq!(Value::parse("0001xxxx", "8").unwrap(), Value::parse("1x", "8:hex").unwrap());
+		assert_eq!(Err(ParseValueChangeError::UnknownDecimalDigit), ValueChange::from_str("#10 a 'dx 8"));
+		assert_eq!(Err(ParseValueChangeError::UnknownDecimalDigit), Value::parse("xx", "8:dec"));
+		assert_eq!("decimal value can't have unknown digits", ParseValueChangeError::UnknownDecimalDigit.to_string());
+	}
+
+	#[test]
+	fn strength() {
+		assert_eq!(Some(("St", ScalarValue::V1)), split_strength("St1"));
+		assert_eq!(Some(("Pu", ScalarValue::V0)), split_strength("Pu0"));
+		assert_eq!(Some(("Hi", ScalarValue::Z)), split_strength("HiZ"));
+		assert_eq!(None, split_strength("St2"));
+		assert_eq!(None, split_strength("Xx1"));
+		assert_eq!(None, split_strength("St10"));
+		assert_eq!(Ok(ScalarValue::V1), ScalarValue::from_str("St1"));
+		assert_eq!(Value::Scalar(ScalarValue::V0), ValueChange::from_str("#10 a Pu0 1").unwrap().value);
+		assert_eq!(Some("Pu"), ValueChange::from_str("#10 a Pu0 1").unwrap().strength);
+		assert_eq!(None, ValueChange::from_str("#10 a 0 1").unwrap().strength);
+		assert_eq!(Err(ParseValueChangeError::InvalidValue), ValueChange::from_str("#10 bus St1 4"));
+	}
+
+	#[test]
+	fn columns_in_chars() {
+		let error = ValueChange::parse_located("#10 température 12 1", &ParseOptions::default()).unwrap_err();
+		assert_eq!(ParseError { error: ParseValueChangeError::InvalidFormat, column: 1 }, error);
+		// the é is two bytes
+		let options = ParseOptions { input_format: InputFormat::Kv, ..ParseOptions::default() };
+		let error = ValueChange::parse_located("note=é t=10 sig=a val=2 w=1", &options).unwrap_err();
+		assert_eq!(ParseError { error: ParseValueChangeError::InvalidValue, column: 23 }, error);
+	}
+
+	#[test]
+	fn trailing_garbage() {
+		assert_eq!(Err(ParseValueChangeError::InvalidFormat), ValueChange::from_str("#10 a 1 1 extra"));
+		assert_eq!(Err(ParseValueChangeError::InvalidFormat), ValueChange::from_str("x#10 a 1 1"));
+		assert_eq!(Err(ParseValueChangeError::InvalidFormat), ValueChange::from_str("#10 a 1 8s:dec"));
+		let width_first = ParseOptions { field_order: FieldOrder::WidthValue, ..ParseOptions::default() };
+		assert_eq!(Err(ParseValueChangeError::InvalidFormat), ValueChange::parse("#10 a 1 1 extra", &width_first));
+	}
+
+	#[test]
+	fn width_and_radix() {
+		assert_eq!(Value::parse("11110010", "8").unwrap(), ValueChange::from_str("#10 a F2 8:hex").unwrap().value);
+		assert_eq!(Value::parse("11111010", "8").unwrap(), ValueChange::from_str("#10 a 250 8:dec").unwrap().value);
+		assert_eq!(Value::parse("00001010", "8").unwrap(), ValueChange::from_str("#10 a 1010 8:bin").unwrap().value);
+		// 10 isn't read as a vector of bits
+		assert_eq!(Value::parse("00010000", "8").unwrap(), ValueChange::from_str("#10 a 10 8:hex").unwrap().value);
+		assert_eq!(Err(ParseValueChangeError::ValueTooLargeForVecWidth), ValueChange::from_str("#10 a 1F2 8:hex"));
+		assert_eq!(Err(ParseValueChangeError::ValueTooLargeForVecWidth), ValueChange::from_str("#10 a 256 8:dec"));
+		assert_eq!(Err(ParseValueChangeError::InvalidValue), ValueChange::from_str("#10 a F2 8:dec"));
+		assert_eq!(Err(ParseValueChangeError::InvalidValueType), ValueChange::from_str("#10 a F2 8:base64"));
+		let width_first = ParseOptions { field_order: FieldOrder::WidthValue, ..ParseOptions::default() };
+		assert_eq!(Value::parse("11110010", "8").unwrap(), ValueChange::parse("#10 a 8:hex F2", &width_first).unwrap().value);
+	}
+
+	#[test]
+	fn base() {
+		assert_eq!(Some(1000000), parse_base("BASE 1000000", &ParseOptions::default()));
+		assert_eq!(None, parse_base("BASE", &ParseOptions::default()));
+		assert_eq!(None, parse_base("#10 BASE 1 1", &ParseOptions::default()));
+		assert_eq!(Some(-50), parse_base("BASE -50", &ParseOptions::default()));
+	}
+
+	#[test]
+	fn leveled() {
+		let options = ParseOptions { input_format: InputFormat::Leveled, ..ParseOptions::default() };
+		assert_eq!(Some((Level::Debug, " #10 a 1 1")), split_level("DEBUG #10 a 1 1"));
+		assert_eq!(None, split_level("VERBOSE #10 a 1 1"));
+		assert!(Level::Debug < Level::Info);
+		assert_eq!(ValueChange::from_str("#10 a 1 1").unwrap(), ValueChange::parse_located("info #10 a 1 1", &options).unwrap());
+		let error = ValueChange::parse_located("INFO #10 a 2 1", &options).unwrap_err();
+		assert_eq!(ParseError { error: ParseValueChangeError::InvalidValue, column: 12 }, error);
+	}
 }
@@ -0,0 +1,105 @@
+use chrono::NaiveDateTime;
+use vcd::TimescaleUnit;
+
+/// Converts the wall-clock timestamps of `--input-time-format` to ticks after the first one.
+pub struct WallClock {
+	format: String,
+	/// Ticks per nanosecond, as a fraction
+	ticks: (u128, u128),
+	first: Option<Time>
+}
+
+#[derive(Clone, Copy)]
+enum Time {
+	Date(NaiveDateTime),
+	EpochMillis(i64)
+}
+
+impl Time {
+	/// The nanoseconds from `earlier` to this time, negative if `earlier` is after it.
+	fn since(self, earlier: Time) -> Option<i64> {
+		match (self, earlier) {
+			(Time::Date(time), Time::Date(earlier)) => (time - earlier).num_nanoseconds(),
+			(Time::EpochMillis(time), Time::EpochMillis(earlier)) => time.checked_sub(earlier)?.checked_mul(1_000_000),
+			_ => None
+		}
+	}
+}
+
+/// Why a line's timestamp couldn't be converted.
+#[derive(Debug, PartialEq)]
+pub enum WallClockError {
+	/// The line doesn't start with a timestamp of the format
+	Unparsed,
+	/// The timestamp is before the one of the first line
+	BeforeFirst
+}
+
+impl WallClock {
+	/// The format is a `chrono` format such as `%Y-%m-%dT%H:%M:%S%.f`, or `epoch-millis` for
+	/// milliseconds since the Unix epoch.
+	pub fn new(format: &str, (step, unit): (u32, TimescaleUnit)) -> WallClock {
+		WallClock {
+			format: String::from(format),
+			ticks: (u128::from(unit.divisor()), 1_000_000_000 * u128::from(step)),
+			first: None
+		}
+	}
+
+	fn parse(&self, timestamp: &str) -> Option<Time> {
+		match self.format.as_str() {
+			"epoch-millis" => timestamp.parse().ok().map(Time::EpochMillis),
+			format => NaiveDateTime::parse_from_str(timestamp, format).ok().map(Time::Date)
+		}
+	}
+
+	/// Rewrites a line starting with a wall-clock timestamp, optionally after a `#`, to one
+	/// starting with `#ticks`. The rest of the line is kept as it is.
+	pub fn rewrite(&mut self, line: &str) -> Result<String, WallClockError> {
+		let line = line.trim_start();
+		let line = line.strip_prefix('#').unwrap_or(line);
+		// the timestamp has as many words as its format
+		let words = self.format.split_whitespace().count().max(1);
+		let timestamp: Vec<&str> = line.split_whitespace().take(words).collect();
+		let rest = timestamp.last().map_or(line, |last| &line[last.as_ptr() as usize - line.as_ptr() as usize + last.len()..]);
+		let time = self.parse(&timestamp.join(" ")).ok_or(WallClockError::Unparsed)?;
+
+		let first = *self.first.get_or_insert(time);
+		let nanos = time.since(first).ok_or(WallClockError::Unparsed)?;
+		if nanos < 0 {
+			return Err(WallClockError::BeforeFirst);
+		}
+		let (numerator, denominator) = self.ticks;
+		let ticks = nanos as u128 * numerator / denominator;
+		Ok(format!("#{}{}", ticks, rest))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn iso_8601() {
+		let mut clock = WallClock::new("%Y-%m-%dT%H:%M:%S%.f", (1, TimescaleUnit::US));
+		assert_eq!(Ok(String::from("#0 a 1 1")), clock.rewrite("2018-09-01T12:00:00.000010 a 1 1"));
+		assert_eq!(Ok(String::from("#1500 a 0 1")), clock.rewrite("2018-09-01T12:00:00.001510 a 0 1"));
+		assert_eq!(Err(WallClockError::BeforeFirst), clock.rewrite("2018-09-01T11:59:59 a 0 1"));
+		assert_eq!(Err(WallClockError::Unparsed), clock.rewrite("#10 a 0 1"));
+	}
+
+	#[test]
+	fn epoch_millis() {
+		let mut clock = WallClock::new("epoch-millis", (10, TimescaleUnit::MS));
+		assert_eq!(Ok(String::from("#0 a 1 1")), clock.rewrite("1535803200000 a 1 1"));
+		assert_eq!(Ok(String::from("#25 a 0 1")), clock.rewrite("1535803200250 a 0 1"));
+	}
+
+	#[test]
+	fn format_with_spaces() {
+		let mut clock = WallClock::new("%Y-%m-%d %H:%M:%S", (1, TimescaleUnit::S));
+		clock.rewrite("2018-09-01 12:00:00 a 1 1").unwrap();
+		assert_eq!(Ok(String::from("#90 a 0 1")), clock.rewrite("2018-09-01 12:01:30 a 0 1"));
+		assert_eq!(Ok(String::from("#90  a\t0 1")), clock.rewrite("2018-09-01 12:01:30  a\t0 1"));
+	}
+}
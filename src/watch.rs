@@ -0,0 +1,64 @@
+use std::io;
+use std::path::Path;
+use std::thread;
+use std::time::{ Duration, Instant, SystemTime };
+
+/// The time the file at `path` was last modified.
+pub fn modified(path: &Path) -> io::Result<SystemTime> {
+	path.metadata()?.modified()
+}
+
+/// Polls the file at `path` every `interval` until it's modified after `since`, returning the new
+/// modification time. Fails with `TimedOut` once `timeout` has passed without a change.
+pub fn wait_for_change(path: &Path, since: SystemTime, interval: Duration, timeout: Option<Duration>) -> io::Result<SystemTime> {
+	let deadline = timeout.map(|timeout| Instant::now() + timeout);
+	loop {
+		if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+			return Err(io::Error::new(io::ErrorKind::TimedOut, format!("{} wasn't modified", path.display())));
+		}
+		thread::sleep(interval);
+		// the file may briefly be missing while an editor replaces it
+		match modified(path) {
+			Ok(time) if time != since => return Ok(time),
+			Err(ref e) if e.kind() != io::ErrorKind::NotFound => return Err(io::Error::new(e.kind(), e.to_string())),
+			_ => {}
+		}
+	}
+}
+
+/// Writes the file again until its modification time moves past `since`, which stays the same
+/// within the resolution of the file system.
+#[cfg(test)]
+pub fn rewrite(path: &Path, contents: &str, since: SystemTime) {
+	loop {
+		std::fs::write(path, contents).unwrap();
+		if modified(path).unwrap() != since {
+			return;
+		}
+		thread::sleep(Duration::from_millis(10));
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::temp::TempPath;
+
+	#[test]
+	fn change() {
+		let path = TempPath::with_contents("watched.log", "#10 a 1 1\n");
+		let since = modified(&path).unwrap();
+		let timeout = Some(Duration::from_secs(10));
+		assert_eq!(io::ErrorKind::TimedOut, wait_for_change(&path, since, Duration::from_millis(10), Some(Duration::from_millis(50))).unwrap_err().kind());
+		let writer = {
+			let path = path.to_path_buf();
+			thread::spawn(move || {
+				thread::sleep(Duration::from_millis(50));
+				rewrite(&path, "#10 a 1 1\n#20 a 0 1\n", since);
+			})
+		};
+		let changed = wait_for_change(&path, since, Duration::from_millis(10), timeout).unwrap();
+		writer.join().unwrap();
+		assert!(changed != since);
+	}
+}
@@ -0,0 +1,82 @@
+use std::collections::BTreeMap;
+use std::io::{ self, BufRead };
+
+use vcd::{ Command, Header, IdCode, Parser, SimulationCommand, Writer };
+
+/// Splits a VCD into one VCD per `window` ticks, written with `write(index, vcd)`.
+///
+/// The timestamps of each window start at 0, where a `$dumpvars` block carries over the values
+/// the signals had at the end of the previous window. Returns the number of windows.
+pub fn split<R, F>(vcd: R, window: u64, mut write: F) -> io::Result<u64>
+	where R: BufRead, F: FnMut(u64, &[u8]) -> io::Result<()>
+{
+	let mut parser = Parser::new(vcd);
+	let header = parser.parse_header()?;
+	let mut values: BTreeMap<IdCode, Command> = BTreeMap::new();
+	let mut index = 0;
+	let mut buffer = start(&header, &values)?;
+	let mut last = Some(0);
+	for command in parser {
+		let command = command?;
+		match command {
+			Command::Timestamp(t) => {
+				while t / window > index {
+					write(index, &buffer)?;
+					index += 1;
+					buffer = start(&header, &values)?;
+					last = Some(0);
+				}
+				let t = t - index * window;
+				if last != Some(t) {
+					Writer::new(&mut buffer).timestamp(t)?;
+					last = Some(t);
+				}
+				continue;
+			},
+			Command::ChangeScalar(id, _) | Command::ChangeVector(id, _) | Command::ChangeReal(id, _) | Command::ChangeString(id, _) => {
+				values.insert(id, command.clone());
+			},
+			_ => ()
+		}
+		Writer::new(&mut buffer).command(&command)?;
+	}
+	write(index, &buffer)?;
+	Ok(index + 1)
+}
+
+/// Starts the VCD of a window with the header and the carried over values at time 0.
+fn start(header: &Header, values: &BTreeMap<IdCode, Command>) -> io::Result<Vec<u8>> {
+	let mut buffer = Vec::new();
+	{
+		let mut writer = Writer::new(&mut buffer);
+		writer.header(header)?;
+		writer.timestamp(0)?;
+		if !values.is_empty() {
+			writer.begin(SimulationCommand::Dumpvars)?;
+			for command in values.values() {
+				writer.command(command)?;
+			}
+			writer.end()?;
+		}
+	}
+	Ok(buffer)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn carried_values() {
+		let vcd = "$timescale 1 ns $end\n$scope module outputs $end\n$var wire 1 ! a $end\n$var wire 4 \" b $end\n$upscope $end\n$enddefinitions $end\n#0\n#5\n1!\n#10\nb101 \"\n#12\n0!\n";
+		let mut windows = Vec::new();
+		let count = split(vcd.as_bytes(), 10, |index, vcd| {
+			windows.push((index, String::from_utf8(vcd.to_vec()).unwrap()));
+			Ok(())
+		}).unwrap();
+		assert_eq!(2, count);
+		assert!(windows[0].1.ends_with("$enddefinitions $end\n#0\n#5\n1!\n"));
+		assert_eq!(1, windows[1].0);
+		assert!(windows[1].1.ends_with("$enddefinitions $end\n#0\n$dumpvars\n1!\n$end\nb101 \"\n#2\n0!\n"));
+	}
+}